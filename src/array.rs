@@ -0,0 +1,123 @@
+//! Firebird arrays representation
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::Error;
+
+/// Size of the array descriptor before the dimensions
+const ADS_SIZE: usize = 16;
+
+/// Array descriptor
+///
+/// Arrays are stored as blobs, starting with this descriptor and
+/// followed by all elements, each one with the same length. The
+/// elements are ordered by the last dimension first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayDescriptor {
+    pub version: u8,
+    pub dimensions: u8,
+    /// Size of each element
+    pub element_length: u16,
+    /// Size of the descriptor, where the elements begin
+    pub length: u16,
+    /// Amount of elements
+    pub count: i32,
+    pub total_length: i32,
+    /// Lower and upper bounds of each dimension
+    pub bounds: Vec<(i32, i32)>,
+}
+
+impl ArrayDescriptor {
+    /// Parse the descriptor from the start of the array blob
+    pub fn from_bytes(bytes: &[u8]) -> Result<ArrayDescriptor, Error> {
+        if bytes.len() < ADS_SIZE {
+            return Err(Error::from("Array without descriptor"));
+        }
+
+        let dimensions = bytes[1];
+        let length = LittleEndian::read_u16(&bytes[6..8]);
+
+        if dimensions == 0 || bytes.len() < length as usize || (length as usize) < ADS_SIZE {
+            return Err(Error::Other(format!(
+                "Invalid array descriptor of {} dimensions and {} bytes",
+                dimensions, length
+            )));
+        }
+
+        // Each dimension has an element descriptor, whose size depends
+        // on the pointer size of the server, followed by the length,
+        // lower and upper bounds
+        let rpt_size = (length as usize - ADS_SIZE) / dimensions as usize;
+        if rpt_size < 12 {
+            return Err(Error::from("Invalid array descriptor dimensions"));
+        }
+
+        let mut bounds = vec![];
+        for dim in 0..dimensions as usize {
+            let end = ADS_SIZE + (dim + 1) * rpt_size;
+            let lower = LittleEndian::read_i32(&bytes[end - 8..end - 4]);
+            let upper = LittleEndian::read_i32(&bytes[end - 4..end]);
+            bounds.push((lower, upper));
+        }
+
+        Ok(ArrayDescriptor {
+            version: bytes[0],
+            dimensions,
+            element_length: LittleEndian::read_u16(&bytes[4..6]),
+            length,
+            count: LittleEndian::read_i32(&bytes[8..12]),
+            total_length: LittleEndian::read_i32(&bytes[12..16]),
+            bounds,
+        })
+    }
+
+    /// Bytes of each element
+    pub fn elements<'a>(&self, bytes: &'a [u8]) -> Result<Vec<&'a [u8]>, Error> {
+        let start = self.length as usize;
+        let size = self.element_length as usize;
+        let end = start + size * self.count.max(0) as usize;
+
+        if size == 0 || bytes.len() < end {
+            return Err(Error::Other(format!(
+                "Array with {} bytes, expected {}",
+                bytes.len(),
+                end
+            )));
+        }
+
+        Ok(bytes[start..end].chunks(size).collect())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    #[test]
+    pub fn descriptor_from_bytes() -> Result<(), Error> {
+        let mut bytes = vec![1, 1, 1, 0, 4, 0, 40, 0, 3, 0, 0, 0, 12, 0, 0, 0];
+        // element descriptor of a 32 bits server
+        bytes.extend([9, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        // length and bounds
+        bytes.extend([4, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0]);
+        // elements
+        bytes.extend([1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0]);
+
+        let desc = ArrayDescriptor::from_bytes(&bytes)?;
+        assert_eq!(1, desc.dimensions);
+        assert_eq!(4, desc.element_length);
+        assert_eq!(40, desc.length);
+        assert_eq!(3, desc.count);
+        assert_eq!(vec![(1, 3)], desc.bounds);
+
+        let elements = desc.elements(&bytes)?;
+        assert_eq!(3, elements.len());
+        assert_eq!(&[2, 0, 0, 0], elements[1]);
+
+        assert!(desc.elements(&bytes[..50]).is_err());
+        assert!(ArrayDescriptor::from_bytes(&bytes[..20]).is_err());
+
+        Ok(())
+    }
+}
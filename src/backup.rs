@@ -0,0 +1,1123 @@
+//! Firebird gbak logical backup (.fbk) representation
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::charset::Charset;
+use crate::column::*;
+use crate::generator::Generator;
+use crate::row::*;
+use crate::table::Table;
+use crate::Error;
+
+// Records of the backup, each one followed by its attributes
+const REC_BURP: u8 = 0;
+const REC_DATABASE: u8 = 1;
+const REC_GLOBAL_FIELD: u8 = 2;
+const REC_RELATION: u8 = 3;
+const REC_FIELD: u8 = 4;
+const REC_INDEX: u8 = 5;
+const REC_DATA: u8 = 6;
+const REC_BLOB: u8 = 7;
+const REC_RELATION_DATA: u8 = 8;
+const REC_RELATION_END: u8 = 9;
+const REC_END: u8 = 10;
+const REC_SECURITY_CLASS: u8 = 12;
+const REC_TRIGGER: u8 = 13;
+const REC_FUNCTION_END: u8 = 17;
+const REC_GEN_ID: u8 = 18;
+const REC_ARRAY: u8 = 23;
+const REC_GENERATOR: u8 = 26;
+const REC_PROCEDURE: u8 = 27;
+const REC_PROCEDURE_PRM: u8 = 28;
+const REC_PROCEDURE_END: u8 = 29;
+const REC_EXCEPTION: u8 = 30;
+
+/// End of the attributes of a record
+const ATT_END: u8 = 0;
+
+// Backup header attributes
+const ATT_BACKUP_FORMAT: u8 = 2;
+const ATT_BACKUP_COMPRESS: u8 = 4;
+const ATT_BACKUP_TRANSPORTABLE: u8 = 5;
+const ATT_BACKUP_VOLUME: u8 = 8;
+
+// Database attributes
+const ATT_DATABASE_FILE_NAME: u8 = 1;
+const ATT_DATABASE_PAGE_SIZE: u8 = 5;
+const ATT_DATABASE_DESCRIPTION2: u8 = 10;
+const ATT_DATABASE_SQL_DIALECT: u8 = 14;
+
+// Relation attributes
+const ATT_RELATION_NAME: u8 = 1;
+const ATT_RELATION_VIEW_BLR: u8 = 2;
+const ATT_RELATION_SYSTEM_FLAG: u8 = 7;
+const ATT_RELATION_DESCRIPTION2: u8 = 13;
+const ATT_RELATION_EXT_DESCRIPTION2: u8 = 15;
+
+// Field attributes, of both the global and the relation fields
+const ATT_FIELD_NAME: u8 = 1;
+const ATT_FIELD_SOURCE: u8 = 2;
+const ATT_FIELD_TYPE: u8 = 7;
+const ATT_FIELD_SUB_TYPE: u8 = 8;
+const ATT_FIELD_LENGTH: u8 = 9;
+const ATT_FIELD_SCALE: u8 = 10;
+const ATT_FIELD_POSITION: u8 = 12;
+const ATT_FIELD_DEFAULT_VALUE: u8 = 14;
+const ATT_FIELD_MISSING_VALUE: u8 = 16;
+const ATT_FIELD_COMPUTED_BLR: u8 = 17;
+const ATT_FIELD_VALIDATION_BLR: u8 = 19;
+const ATT_FIELD_NUMBER: u8 = 21;
+const ATT_FIELD_COMPUTED_FLAG: u8 = 22;
+const ATT_FIELD_DIMENSIONS: u8 = 26;
+const ATT_FIELD_DESCRIPTION2: u8 = 27;
+const ATT_FIELD_COMPUTED_SOURCE2: u8 = 29;
+const ATT_FIELD_NULL_FLAG: u8 = 30;
+const ATT_FIELD_DEFAULT_SOURCE: u8 = 31;
+const ATT_FIELD_MISSING_SOURCE: u8 = 32;
+const ATT_FIELD_CHARACTER_LENGTH: u8 = 33;
+const ATT_FIELD_CHARACTER_SET: u8 = 34;
+
+// Index attributes
+const ATT_INDEX_NAME: u8 = 1;
+const ATT_INDEX_INACTIVE: u8 = 3;
+const ATT_INDEX_UNIQUE_FLAG: u8 = 4;
+const ATT_INDEX_FIELD_NAME: u8 = 5;
+const ATT_INDEX_TYPE: u8 = 7;
+const ATT_INDEX_FOREIGN_KEY: u8 = 8;
+const ATT_INDEX_DESCRIPTION2: u8 = 9;
+const ATT_INDEX_EXPRESSION_SOURCE: u8 = 10;
+const ATT_INDEX_EXPRESSION_BLR: u8 = 11;
+
+// Data attributes
+const ATT_DATA_LENGTH: u8 = 1;
+const ATT_DATA_DATA: u8 = 2;
+const ATT_XDR_LENGTH: u8 = 3;
+
+// Blob and array attributes
+const ATT_BLOB_FIELD_NUMBER: u8 = 1;
+const ATT_BLOB_NUMBER_SEGMENTS: u8 = 3;
+const ATT_BLOB_DATA: u8 = 5;
+const ATT_ARRAY_RANGE_LOW: u8 = 7;
+const ATT_ARRAY_RANGE_HIGH: u8 = 8;
+
+// Generator attributes
+const ATT_GEN_GENERATOR: u8 = 1;
+const ATT_GEN_VALUE: u8 = 2;
+const ATT_GEN_VALUE_INT64: u8 = 3;
+const ATT_GEN_DESCRIPTION: u8 = 4;
+const ATT_GEN_SYSFLAG: u8 = 7;
+const ATT_GEN_INIT_VAL: u8 = 8;
+const ATT_GEN_ID_INCREMENT: u8 = 9;
+
+// Trigger attributes
+const ATT_TRIG_TYPE: u8 = 1;
+const ATT_TRIG_BLR: u8 = 2;
+const ATT_TRIG_SOURCE: u8 = 3;
+const ATT_TRIG_NAME: u8 = 4;
+const ATT_TRIG_RELATION_NAME: u8 = 5;
+const ATT_TRIG_SEQUENCE: u8 = 6;
+const ATT_TRIG_INACTIVE: u8 = 9;
+const ATT_TRIG_SOURCE2: u8 = 10;
+const ATT_TRIG_DESCRIPTION2: u8 = 11;
+const ATT_TRIG_VALID_BLR: u8 = 13;
+const ATT_TRIG_DEBUG_INFO: u8 = 14;
+
+// Procedure and parameter attributes
+const ATT_PROCEDURE_NAME: u8 = 1;
+const ATT_PROCEDURE_DESCRIPTION2: u8 = 3;
+const ATT_PROCEDURE_SOURCE: u8 = 4;
+const ATT_PROCEDURE_SOURCE2: u8 = 5;
+const ATT_PROCEDURE_BLR: u8 = 6;
+const ATT_PROCEDUREPRM_NAME: u8 = 1;
+const ATT_PROCEDUREPRM_DESCRIPTION2: u8 = 6;
+const ATT_PROCEDUREPRM_DEFAULT_VALUE: u8 = 7;
+const ATT_PROCEDUREPRM_DEFAULT_SOURCE: u8 = 8;
+
+// Security class and exception attributes
+const ATT_CLASS_ACL: u8 = 2;
+const ATT_CLASS_DESCRIPTION2: u8 = 4;
+const ATT_EXCEPTION_DESCRIPTION2: u8 = 4;
+
+/// Storage of the attribute values
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    /// Length of a byte followed by the value
+    Plain,
+    /// Length as an integer attribute followed by the BLR
+    Blr,
+    /// Total length as an integer attribute followed by segments,
+    /// each one with a length of 2 bytes
+    Source,
+}
+
+/// Encoding of the attributes holding blobs, most of them just
+/// have a short value
+fn encoding(rec: u8, att: u8) -> Encoding {
+    match (rec, att) {
+        (REC_DATABASE, ATT_DATABASE_DESCRIPTION2) => Encoding::Source,
+        (REC_RELATION, ATT_RELATION_VIEW_BLR) => Encoding::Blr,
+        (REC_RELATION, ATT_RELATION_DESCRIPTION2..=ATT_RELATION_EXT_DESCRIPTION2) => {
+            Encoding::Source
+        }
+        (
+            REC_GLOBAL_FIELD | REC_FIELD,
+            ATT_FIELD_DEFAULT_VALUE
+            | ATT_FIELD_MISSING_VALUE
+            | ATT_FIELD_COMPUTED_BLR
+            | ATT_FIELD_VALIDATION_BLR,
+        ) => Encoding::Blr,
+        (
+            REC_GLOBAL_FIELD | REC_FIELD,
+            ATT_FIELD_DESCRIPTION2..=ATT_FIELD_COMPUTED_SOURCE2
+            | ATT_FIELD_DEFAULT_SOURCE
+            | ATT_FIELD_MISSING_SOURCE,
+        ) => Encoding::Source,
+        (REC_INDEX, ATT_INDEX_DESCRIPTION2 | ATT_INDEX_EXPRESSION_SOURCE) => Encoding::Source,
+        (REC_INDEX, ATT_INDEX_EXPRESSION_BLR) => Encoding::Blr,
+        (REC_TRIGGER, ATT_TRIG_BLR | ATT_TRIG_VALID_BLR | ATT_TRIG_DEBUG_INFO) => Encoding::Blr,
+        (REC_TRIGGER, ATT_TRIG_SOURCE2 | ATT_TRIG_DESCRIPTION2) => Encoding::Source,
+        (REC_PROCEDURE, ATT_PROCEDURE_BLR) => Encoding::Blr,
+        (REC_PROCEDURE, ATT_PROCEDURE_DESCRIPTION2 | ATT_PROCEDURE_SOURCE2) => Encoding::Source,
+        (REC_PROCEDURE_PRM, ATT_PROCEDUREPRM_DEFAULT_VALUE) => Encoding::Blr,
+        (REC_PROCEDURE_PRM, ATT_PROCEDUREPRM_DESCRIPTION2 | ATT_PROCEDUREPRM_DEFAULT_SOURCE) => {
+            Encoding::Source
+        }
+        (REC_SECURITY_CLASS, ATT_CLASS_ACL) => Encoding::Blr,
+        (REC_SECURITY_CLASS, ATT_CLASS_DESCRIPTION2) => Encoding::Source,
+        (REC_EXCEPTION, ATT_EXCEPTION_DESCRIPTION2) => Encoding::Source,
+        (REC_GENERATOR | REC_GEN_ID, ATT_GEN_DESCRIPTION) => Encoding::Source,
+        _ => Encoding::Plain,
+    }
+}
+
+/// Logical backup made by gbak
+///
+/// The backup is a stream of records, like the relations and their
+/// fields, each one described by attributes made of an id, the
+/// length and the value. The data of each relation comes after
+/// the definitions, with a record for the message of each row
+/// followed by the records with the content of its blobs.
+#[derive(Debug)]
+pub struct Backup {
+    /// Version of the backup format
+    pub format: i32,
+    /// Rows stored with XDR, independent of the platform
+    pub transportable: bool,
+    /// Rows compressed with run length encoding
+    pub compressed: bool,
+    /// Name of the database backed up
+    pub database: String,
+    pub page_size: u32,
+    pub dialect: i32,
+    pub indexes: Vec<BackupIndex>,
+    pub generators: Vec<Generator>,
+    pub procedures: Vec<BackupProcedure>,
+    pub triggers: Vec<BackupTrigger>,
+    relations: Vec<Arc<BackupRelation>>,
+    none_charset: Charset,
+}
+
+/// Index definition, rebuilt by the restore
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupIndex {
+    pub name: String,
+    pub relation: String,
+    /// Field names of each segment
+    pub fields: Vec<String>,
+    pub unique: bool,
+    pub descending: bool,
+    pub inactive: bool,
+    /// Primary or unique key referenced by a foreign key
+    pub foreign_key: Option<String>,
+}
+
+/// Stored procedure definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupProcedure {
+    pub name: String,
+    pub source: Option<String>,
+    /// Names of the input and output parameters
+    pub parameters: Vec<String>,
+}
+
+/// Trigger definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupTrigger {
+    pub name: String,
+    /// Relation of the trigger, none for database triggers
+    pub relation: Option<String>,
+    pub tp: i64,
+    pub sequence: i32,
+    pub inactive: bool,
+    pub source: Option<String>,
+}
+
+/// Relation of the backup with its rows
+#[derive(Debug)]
+pub(crate) struct BackupRelation {
+    pub name: String,
+    pub is_system_table: bool,
+    pub columns: Vec<Column>,
+    pub records: Vec<BackupRecord>,
+}
+
+/// Values of a row, in the order of the columns
+#[derive(Debug)]
+pub(crate) struct BackupRecord {
+    values: Vec<Option<BackupValue>>,
+}
+
+#[derive(Debug)]
+enum BackupValue {
+    /// Value as stored on the records of the database
+    Field(Vec<u8>),
+    /// Content of the blob
+    Blob(Vec<u8>),
+    /// Each element of the array, as stored on the database
+    Array(Vec<Vec<u8>>),
+}
+
+impl Backup {
+    /// Read the backup from a file
+    pub fn open_file<P: AsRef<Path>>(fpath: P) -> Result<Backup, Error> {
+        let fpath = fpath.as_ref();
+        let file =
+            File::open(fpath).map_err(|e| Error::Other(format!("{}: {}", fpath.display(), e)))?;
+
+        Self::open(BufReader::new(file))
+    }
+
+    /// Check if the file is a gbak backup, which starts by the
+    /// backup header instead of the header page of a database
+    pub fn is_backup<P: AsRef<Path>>(fpath: P) -> Result<bool, Error> {
+        let mut start = [0u8; 2];
+        let mut file = File::open(fpath)?;
+
+        Ok(file.read(&mut start)? == 2
+            && start[0] == REC_BURP
+            && (1..=ATT_BACKUP_VOLUME).contains(&start[1]))
+    }
+
+    /// Read the backup from a stream of records
+    pub fn open<R: Read>(reader: R) -> Result<Backup, Error> {
+        let mut stream = BackupStream { reader };
+        let mut backup = Backup {
+            format: 0,
+            transportable: false,
+            compressed: false,
+            database: String::new(),
+            page_size: 0,
+            dialect: 1,
+            indexes: vec![],
+            generators: vec![],
+            procedures: vec![],
+            triggers: vec![],
+            relations: vec![],
+            none_charset: Charset::None,
+        };
+
+        let mut fields = HashMap::new();
+        let mut relations: Vec<BackupRelation> = vec![];
+        // Relation receiving the rows
+        let mut current = None;
+
+        loop {
+            let rec = stream.byte()?;
+            match rec {
+                REC_BURP => {
+                    for (att, value) in stream.attributes(rec)? {
+                        match att {
+                            ATT_BACKUP_FORMAT => backup.format = integer(&value) as i32,
+                            ATT_BACKUP_COMPRESS => backup.compressed = integer(&value) != 0,
+                            ATT_BACKUP_TRANSPORTABLE => backup.transportable = integer(&value) != 0,
+                            _ => {}
+                        }
+                    }
+                }
+                REC_DATABASE => {
+                    for (att, value) in stream.attributes(rec)? {
+                        match att {
+                            ATT_DATABASE_FILE_NAME => backup.database = text(&value),
+                            ATT_DATABASE_PAGE_SIZE => backup.page_size = integer(&value) as u32,
+                            ATT_DATABASE_SQL_DIALECT => backup.dialect = integer(&value) as i32,
+                            _ => {}
+                        }
+                    }
+                }
+                REC_GLOBAL_FIELD => {
+                    let mut col = empty_column();
+                    load_field(&mut col, &stream.attributes(rec)?)?;
+
+                    // The name of the global field is the source of
+                    // the relation fields
+                    col.source = std::mem::take(&mut col.name);
+                    fields.insert(col.source.clone(), col);
+                }
+                REC_RELATION => {
+                    let mut relation = BackupRelation {
+                        name: String::new(),
+                        is_system_table: false,
+                        columns: vec![],
+                        records: vec![],
+                    };
+                    for (att, value) in stream.attributes(rec)? {
+                        match att {
+                            ATT_RELATION_NAME => relation.name = text(&value),
+                            ATT_RELATION_SYSTEM_FLAG => {
+                                relation.is_system_table = integer(&value) != 0
+                            }
+                            _ => {}
+                        }
+                    }
+                    relation.is_system_table |= relation.name.contains('$');
+                    relations.push(relation);
+                }
+                REC_FIELD => {
+                    let attributes = stream.attributes(rec)?;
+                    let relation = relations
+                        .last_mut()
+                        .ok_or_else(|| Error::from("Field found before its relation"))?;
+
+                    // The type comes from the global field
+                    let source = attributes
+                        .iter()
+                        .find(|(att, _)| *att == ATT_FIELD_SOURCE)
+                        .map(|(_, value)| text(value))
+                        .unwrap_or_default();
+                    let mut col = fields.get(&source).cloned().unwrap_or_else(empty_column);
+                    load_field(&mut col, &attributes)?;
+
+                    relation.columns.push(col);
+                }
+                REC_INDEX => {
+                    let mut index = BackupIndex {
+                        name: String::new(),
+                        relation: relations.last().map(|r| r.name.clone()).unwrap_or_default(),
+                        fields: vec![],
+                        unique: false,
+                        descending: false,
+                        inactive: false,
+                        foreign_key: None,
+                    };
+                    for (att, value) in stream.attributes(rec)? {
+                        match att {
+                            ATT_INDEX_NAME => index.name = text(&value),
+                            ATT_INDEX_FIELD_NAME => index.fields.push(text(&value)),
+                            ATT_INDEX_UNIQUE_FLAG => index.unique = integer(&value) == 1,
+                            ATT_INDEX_INACTIVE => index.inactive = integer(&value) == 1,
+                            ATT_INDEX_TYPE => index.descending = integer(&value) == 1,
+                            ATT_INDEX_FOREIGN_KEY => {
+                                index.foreign_key = Some(text(&value)).filter(|f| !f.is_empty())
+                            }
+                            _ => {}
+                        }
+                    }
+                    backup.indexes.push(index);
+                }
+                REC_RELATION_DATA => {
+                    let name = stream
+                        .attributes(rec)?
+                        .into_iter()
+                        .find(|(att, _)| *att == ATT_RELATION_NAME)
+                        .map(|(_, value)| text(&value))
+                        .unwrap_or_default();
+
+                    current = Some(relations.iter().position(|r| r.name == name).ok_or_else(
+                        || Error::Other(format!("Data of the unknown relation {}", name)),
+                    )?);
+                }
+                REC_DATA | REC_BLOB | REC_ARRAY => {
+                    let relation = current
+                        .map(|i| &mut relations[i])
+                        .ok_or_else(|| Error::from("Data found outside of a relation"))?;
+
+                    match rec {
+                        REC_DATA => {
+                            let record = stream.data(&relation.columns, &backup)?;
+                            relation.records.push(record);
+                        }
+                        REC_BLOB => stream.blob(relation)?,
+                        _ => stream.array(relation, backup.transportable)?,
+                    }
+                }
+                REC_RELATION_END => current = None,
+                REC_GENERATOR | REC_GEN_ID => {
+                    let mut generator = Generator {
+                        name: String::new(),
+                        id: backup.generators.len() as u16,
+                        is_system_generator: false,
+                        value: 0,
+                        initial_value: 0,
+                        increment: 1,
+                    };
+                    for (att, value) in stream.attributes(rec)? {
+                        match att {
+                            ATT_GEN_GENERATOR => generator.name = text(&value),
+                            ATT_GEN_VALUE | ATT_GEN_VALUE_INT64 => {
+                                generator.value = integer(&value)
+                            }
+                            ATT_GEN_SYSFLAG => generator.is_system_generator = integer(&value) != 0,
+                            ATT_GEN_INIT_VAL => generator.initial_value = integer(&value),
+                            ATT_GEN_ID_INCREMENT => generator.increment = integer(&value) as i32,
+                            _ => {}
+                        }
+                    }
+                    backup.generators.push(generator);
+                }
+                REC_PROCEDURE => {
+                    let mut procedure = BackupProcedure {
+                        name: String::new(),
+                        source: None,
+                        parameters: vec![],
+                    };
+                    for (att, value) in stream.attributes(rec)? {
+                        match att {
+                            ATT_PROCEDURE_NAME => procedure.name = text(&value),
+                            ATT_PROCEDURE_SOURCE | ATT_PROCEDURE_SOURCE2 => {
+                                procedure.source = Some(source(&value))
+                            }
+                            _ => {}
+                        }
+                    }
+                    backup.procedures.push(procedure);
+                }
+                REC_PROCEDURE_PRM => {
+                    let attributes = stream.attributes(rec)?;
+                    let procedure = backup
+                        .procedures
+                        .last_mut()
+                        .ok_or_else(|| Error::from("Parameter found before its procedure"))?;
+
+                    if let Some((_, value)) = attributes
+                        .iter()
+                        .find(|(att, _)| *att == ATT_PROCEDUREPRM_NAME)
+                    {
+                        procedure.parameters.push(text(value));
+                    }
+                }
+                REC_TRIGGER => {
+                    let mut trigger = BackupTrigger {
+                        name: String::new(),
+                        relation: None,
+                        tp: 0,
+                        sequence: 0,
+                        inactive: false,
+                        source: None,
+                    };
+                    for (att, value) in stream.attributes(rec)? {
+                        match att {
+                            ATT_TRIG_NAME => trigger.name = text(&value),
+                            ATT_TRIG_RELATION_NAME => {
+                                trigger.relation = Some(text(&value)).filter(|r| !r.is_empty())
+                            }
+                            ATT_TRIG_TYPE => trigger.tp = integer(&value),
+                            ATT_TRIG_SEQUENCE => trigger.sequence = integer(&value) as i32,
+                            ATT_TRIG_INACTIVE => trigger.inactive = integer(&value) != 0,
+                            ATT_TRIG_SOURCE | ATT_TRIG_SOURCE2 => {
+                                trigger.source = Some(source(&value))
+                            }
+                            _ => {}
+                        }
+                    }
+                    backup.triggers.push(trigger);
+                }
+                // Records without attributes
+                REC_PROCEDURE_END | REC_FUNCTION_END => {}
+                REC_END => break,
+                _ => {
+                    stream.attributes(rec)?;
+                }
+            }
+        }
+
+        backup.relations = relations.into_iter().map(Arc::new).collect();
+
+        Ok(backup)
+    }
+
+    /// Charset used to decode the text of the columns declared
+    /// with NONE, which have no information about its encoding
+    pub fn set_none_charset(&mut self, charset: Charset) {
+        self.none_charset = charset;
+    }
+
+    /// Tables of the backup with their rows, numbered by their
+    /// order since the backup doesn't keep the relation ids
+    pub fn tables(&self) -> Result<Vec<Table>, Error> {
+        let tables = self
+            .relations
+            .iter()
+            .enumerate()
+            .map(|(i, relation)| Table::from_backup(i as u16, relation.clone(), self.none_charset))
+            .collect();
+
+        Ok(tables)
+    }
+
+    /// Run a read-only SELECT on the tables of the backup
+    #[cfg(feature = "sql")]
+    pub fn query(&self, sql: &str) -> Result<crate::QueryResult, Error> {
+        crate::query(&self.tables()?, sql)
+    }
+}
+
+impl BackupRecord {
+    /// Decode the values of the row, with the positions of the
+    /// columns on the record
+    pub(crate) fn load_row(&self, columns: &[Column], positions: &[usize]) -> Result<Row, Error> {
+        let mut raw = vec![];
+        let mut values = vec![];
+
+        for (col, pos) in columns.iter().zip(positions) {
+            if col.computed {
+                values.push(None);
+                continue;
+            }
+
+            raw.push(match self.field(*pos) {
+                Some(BackupValue::Array(elements)) => elements.concat(),
+                _ => self.bytes(*pos).unwrap_or_default().to_vec(),
+            });
+            values.push(self.value(col, *pos)?);
+        }
+
+        Ok(Row {
+            raw,
+            values,
+            db_key: None,
+        })
+    }
+
+    fn field(&self, pos: usize) -> Option<&BackupValue> {
+        self.values.get(pos).and_then(Option::as_ref)
+    }
+
+    /// Bytes of a field or blob content, none for arrays and nulls
+    pub(crate) fn bytes(&self, pos: usize) -> Option<&[u8]> {
+        match self.field(pos)? {
+            BackupValue::Field(bytes) | BackupValue::Blob(bytes) => Some(bytes),
+            BackupValue::Array(_) => None,
+        }
+    }
+
+    /// Decode the value of a column
+    pub(crate) fn value(&self, col: &Column, pos: usize) -> Result<Option<Value>, Error> {
+        let val = match self.field(pos) {
+            Some(BackupValue::Field(bytes)) => parse_value(col, bytes)?,
+            Some(BackupValue::Blob(content)) => Some(parse_blob_content(col, content.clone())?),
+            Some(BackupValue::Array(elements)) => {
+                let elements = elements
+                    .iter()
+                    .map(|bytes| parse_value(col, bytes))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if col.dimensions.is_empty() {
+                    Some(Value::Array(elements))
+                } else {
+                    Some(nest_array(&mut elements.into_iter(), &col.dimensions))
+                }
+            }
+            None => None,
+        };
+
+        Ok(val)
+    }
+}
+
+/// Column with the defaults of the missing attributes
+fn empty_column() -> Column {
+    Column {
+        name: String::new(),
+        id: 0,
+        position: 0,
+        source: String::new(),
+        size: 0,
+        scale: 0,
+        tp: ColumnType::Smallint,
+        sub_type: 0,
+        charset: Charset::None,
+        char_length: 0,
+        dimensions: vec![],
+        not_null: false,
+        computed: false,
+    }
+}
+
+/// Most dimensions of an array allowed by Firebird
+const MAX_DIMENSIONS: usize = 16;
+
+/// Fill the column with the attributes of a field
+fn load_field(col: &mut Column, attributes: &[(u8, Vec<u8>)]) -> Result<(), Error> {
+    for (att, value) in attributes {
+        match *att {
+            ATT_FIELD_NAME => col.name = text(value),
+            ATT_FIELD_SOURCE => col.source = text(value),
+            ATT_FIELD_TYPE => {
+                col.tp = ColumnType::try_from(integer(value) as i16)
+                    .map_err(|e| Error::from(e.to_string()))?
+            }
+            ATT_FIELD_SUB_TYPE => col.sub_type = integer(value) as i16,
+            ATT_FIELD_LENGTH => col.size = integer(value) as usize,
+            ATT_FIELD_SCALE => col.scale = integer(value) as i16,
+            ATT_FIELD_POSITION => col.position = integer(value) as usize,
+            ATT_FIELD_NUMBER => col.id = integer(value) as usize,
+            ATT_FIELD_COMPUTED_BLR => col.computed = true,
+            ATT_FIELD_COMPUTED_FLAG => col.computed |= integer(value) != 0,
+            ATT_FIELD_NULL_FLAG => col.not_null = integer(value) == 1,
+            ATT_FIELD_CHARACTER_LENGTH => col.char_length = integer(value) as usize,
+            ATT_FIELD_CHARACTER_SET => {
+                col.charset = Charset::try_from(integer(value) as i16)
+                    .map_err(|e| Error::from(e.to_string()))?
+            }
+            // The bounds come with the arrays of the rows
+            ATT_FIELD_DIMENSIONS => {
+                let count = usize::try_from(integer(value))
+                    .ok()
+                    .filter(|count| *count <= MAX_DIMENSIONS)
+                    .ok_or_else(|| {
+                        Error::Other(format!(
+                            "Field {} with {} dimensions",
+                            col.name,
+                            integer(value)
+                        ))
+                    })?;
+                col.dimensions = vec![(1, 1); count];
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Reader of the records and attributes of the backup
+struct BackupStream<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> BackupStream<R> {
+    fn byte(&mut self) -> Result<u8, Error> {
+        let mut byte = [0u8; 1];
+        self.reader.read_exact(&mut byte)?;
+
+        Ok(byte[0])
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        let mut bytes = vec![0u8; len];
+        self.reader.read_exact(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Integer value, preceded by its length
+    fn integer(&mut self) -> Result<i64, Error> {
+        let len = self.byte()?;
+
+        Ok(integer(&self.bytes(len.into())?))
+    }
+
+    /// Attributes of the record, until its end
+    fn attributes(&mut self, rec: u8) -> Result<Vec<(u8, Vec<u8>)>, Error> {
+        let mut attributes = vec![];
+
+        loop {
+            let att = self.byte()?;
+            if att == ATT_END {
+                return Ok(attributes);
+            }
+
+            let value = match encoding(rec, att) {
+                Encoding::Plain => {
+                    let len = self.byte()?;
+                    self.bytes(len.into())?
+                }
+                Encoding::Blr => {
+                    let len = self.integer()?;
+                    self.bytes(len as usize)?
+                }
+                Encoding::Source => {
+                    let mut len = self.integer()? as usize;
+                    let mut value = vec![];
+
+                    while len > 0 {
+                        let segment = LittleEndian::read_u16(&self.bytes(2)?) as usize;
+                        value.extend(self.bytes(segment)?);
+
+                        len = len.checked_sub(segment + 2).ok_or_else(|| {
+                            Error::Other(format!(
+                                "Attribute {} of the record {} with segments beyond its length",
+                                att, rec
+                            ))
+                        })?;
+                    }
+
+                    value
+                }
+            };
+            attributes.push((att, value));
+        }
+    }
+
+    /// Expand the run length encoding, where a positive count is
+    /// followed by the bytes to copy and a negative one by the
+    /// byte to repeat
+    fn decompress(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::with_capacity(len);
+
+        while data.len() < len {
+            let count = self.byte()? as i8;
+            if count > 0 {
+                data.extend(self.bytes(count as usize)?);
+            } else if count < 0 {
+                let byte = self.byte()?;
+                data.resize(data.len() + count.unsigned_abs() as usize, byte);
+            }
+        }
+
+        if data.len() > len {
+            return Err(Error::Overflow {
+                limit: len,
+                value: data.len(),
+                msg: "compressed data record".to_string(),
+            });
+        }
+
+        Ok(data)
+    }
+
+    /// Row of the relation, from the message with the values
+    /// followed by their null flags
+    fn data(&mut self, columns: &[Column], backup: &Backup) -> Result<BackupRecord, Error> {
+        let mut length = None;
+        let mut xdr_length = None;
+
+        loop {
+            match self.byte()? {
+                ATT_DATA_DATA => break,
+                ATT_DATA_LENGTH => length = Some(self.integer()? as usize),
+                ATT_XDR_LENGTH => xdr_length = Some(self.integer()? as usize),
+                _ => {
+                    self.integer()?;
+                }
+            }
+        }
+
+        let length = xdr_length
+            .or(length)
+            .ok_or_else(|| Error::from("Data record without length"))?;
+        let data = if backup.compressed {
+            self.decompress(length)?
+        } else {
+            self.bytes(length)?
+        };
+
+        let values = if backup.transportable {
+            xdr_message(columns, &data)?
+        } else {
+            native_message(columns, &data)?
+        };
+
+        Ok(BackupRecord { values })
+    }
+
+    /// Content of a blob of the last row
+    fn blob(&mut self, relation: &mut BackupRelation) -> Result<(), Error> {
+        let mut field = 0;
+        let mut segments = 0;
+
+        loop {
+            match self.byte()? {
+                ATT_BLOB_DATA => break,
+                ATT_BLOB_FIELD_NUMBER => field = self.integer()? as usize,
+                ATT_BLOB_NUMBER_SEGMENTS => segments = self.integer()?,
+                _ => {
+                    self.integer()?;
+                }
+            }
+        }
+
+        let mut content = vec![];
+        for _ in 0..segments {
+            let len = LittleEndian::read_u16(&self.bytes(2)?) as usize;
+            content.extend(self.bytes(len)?);
+        }
+
+        let (i, record) = last_record(relation, field)?;
+        record.values[i] = Some(BackupValue::Blob(content));
+
+        Ok(())
+    }
+
+    /// Elements of an array of the last row
+    fn array(&mut self, relation: &mut BackupRelation, transportable: bool) -> Result<(), Error> {
+        let mut field = 0;
+        let mut lower = vec![];
+        let mut upper = vec![];
+
+        loop {
+            match self.byte()? {
+                ATT_BLOB_DATA => break,
+                ATT_BLOB_FIELD_NUMBER => field = self.integer()? as usize,
+                ATT_ARRAY_RANGE_LOW => lower.push(self.integer()? as i32),
+                ATT_ARRAY_RANGE_HIGH => upper.push(self.integer()? as i32),
+                _ => {
+                    self.integer()?;
+                }
+            }
+        }
+
+        let len = LittleEndian::read_u32(&self.bytes(4)?) as usize;
+        let data = self.bytes(len)?;
+
+        let (i, _) = last_record(relation, field)?;
+        let col = &mut relation.columns[i];
+        col.dimensions = lower.into_iter().zip(upper).collect();
+
+        // Elements are decoded as single values of the column type
+        let mut element = col.clone();
+        element.dimensions = vec![];
+
+        let mut elements = vec![];
+        if transportable {
+            let mut pos = 0;
+            while pos < data.len() {
+                elements.push(xdr_field(&element, &data, &mut pos)?);
+            }
+        } else {
+            let size = element.element_size().max(1);
+            elements.extend(data.chunks_exact(size).map(|e| e.to_vec()));
+        }
+
+        let (_, record) = last_record(relation, field)?;
+        record.values[i] = Some(BackupValue::Array(elements));
+
+        Ok(())
+    }
+}
+
+/// Last row of the relation and the position of the field
+fn last_record(
+    relation: &mut BackupRelation,
+    field: usize,
+) -> Result<(usize, &mut BackupRecord), Error> {
+    let i = relation
+        .columns
+        .iter()
+        .position(|c| c.id == field)
+        .ok_or_else(|| {
+            Error::Other(format!(
+                "Field {} not found on the relation {}",
+                field, relation.name
+            ))
+        })?;
+    let record = relation.records.last_mut().ok_or_else(|| {
+        Error::Other(format!(
+            "Blob of the relation {} before its rows",
+            relation.name
+        ))
+    })?;
+
+    Ok((i, record))
+}
+
+/// Value of a field read from the message, the blobs and arrays
+/// are filled by the next records
+fn message_value(col: &Column, bytes: Vec<u8>) -> BackupValue {
+    if col.is_array() {
+        BackupValue::Array(vec![])
+    } else if col.tp == ColumnType::Blob {
+        BackupValue::Blob(vec![])
+    } else {
+        BackupValue::Field(bytes)
+    }
+}
+
+/// Size and alignment of the column on the message
+fn message_place(col: &Column) -> (usize, usize) {
+    if col.is_array() {
+        return (8, 4);
+    }
+
+    match col.tp {
+        ColumnType::Char => (col.size, 1),
+        ColumnType::Varchar => (col.size + 2, 2),
+        ColumnType::Smallint => (2, 2),
+        ColumnType::Integer | ColumnType::Float | ColumnType::Date | ColumnType::Time => (4, 4),
+        ColumnType::Timestamp | ColumnType::Blob => (8, 4),
+        ColumnType::Bigint | ColumnType::DoublePrecision => (8, 8),
+    }
+}
+
+fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], Error> {
+    let bytes = data
+        .get(*pos..*pos + len)
+        .ok_or_else(|| Error::from("Data record shorter than its fields"))?;
+    *pos += len;
+
+    Ok(bytes)
+}
+
+/// Values of the message on the layout of the platform
+fn native_message(columns: &[Column], data: &[u8]) -> Result<Vec<Option<BackupValue>>, Error> {
+    let stored: Vec<usize> = (0..columns.len())
+        .filter(|i| !columns[*i].computed)
+        .collect();
+
+    let mut pos: usize = 0;
+    let mut fields = vec![];
+    for i in stored.iter() {
+        let (size, align) = message_place(&columns[*i]);
+        pos = pos.div_ceil(align) * align;
+        fields.push(take(data, &mut pos, size)?.to_vec());
+    }
+
+    let mut values: Vec<Option<BackupValue>> = columns.iter().map(|_| None).collect();
+    for (i, bytes) in stored.into_iter().zip(fields) {
+        pos = pos.div_ceil(2) * 2;
+        if LittleEndian::read_i16(take(data, &mut pos, 2)?) == 0 {
+            values[i] = Some(message_value(&columns[i], bytes));
+        }
+    }
+
+    Ok(values)
+}
+
+/// Values of the message encoded with XDR, each one padded to 4
+/// bytes, converted to the layout of the records
+fn xdr_message(columns: &[Column], data: &[u8]) -> Result<Vec<Option<BackupValue>>, Error> {
+    let stored: Vec<usize> = (0..columns.len())
+        .filter(|i| !columns[*i].computed)
+        .collect();
+
+    let mut pos = 0;
+    let mut fields = vec![];
+    for i in stored.iter() {
+        fields.push(xdr_field(&columns[*i], data, &mut pos)?);
+    }
+
+    let mut values: Vec<Option<BackupValue>> = columns.iter().map(|_| None).collect();
+    for (i, bytes) in stored.into_iter().zip(fields) {
+        if BigEndian::read_i32(take(data, &mut pos, 4)?) == 0 {
+            values[i] = Some(message_value(&columns[i], bytes));
+        }
+    }
+
+    Ok(values)
+}
+
+/// Single XDR value, as the bytes stored on the database records
+fn xdr_field(col: &Column, data: &[u8], pos: &mut usize) -> Result<Vec<u8>, Error> {
+    let padded = |len: usize| len.div_ceil(4) * 4;
+
+    if col.is_array() {
+        return Ok(take(data, pos, 8)?.to_vec());
+    }
+
+    let bytes = match col.tp {
+        ColumnType::Char => {
+            let bytes = take(data, pos, padded(col.size))?;
+            bytes[..col.size].to_vec()
+        }
+        ColumnType::Varchar => {
+            let len = BigEndian::read_u32(take(data, pos, 4)?) as usize;
+            let bytes = take(data, pos, padded(len))?;
+
+            let mut native = vec![0u8; col.size.max(len) + 2];
+            LittleEndian::write_u16(&mut native[0..2], len as u16);
+            native[2..len + 2].copy_from_slice(&bytes[..len]);
+            native
+        }
+        ColumnType::Smallint => {
+            let val = BigEndian::read_i32(take(data, pos, 4)?) as i16;
+            val.to_le_bytes().to_vec()
+        }
+        ColumnType::Integer | ColumnType::Date | ColumnType::Time | ColumnType::Float => {
+            let mut bytes = take(data, pos, 4)?.to_vec();
+            bytes.reverse();
+            bytes
+        }
+        ColumnType::Bigint | ColumnType::DoublePrecision => {
+            let mut bytes = take(data, pos, 8)?.to_vec();
+            bytes.reverse();
+            bytes
+        }
+        ColumnType::Timestamp => {
+            let bytes = take(data, pos, 8)?;
+            let mut native = vec![0u8; 8];
+            LittleEndian::write_i32(&mut native[0..4], BigEndian::read_i32(&bytes[0..4]));
+            LittleEndian::write_u32(&mut native[4..8], BigEndian::read_u32(&bytes[4..8]));
+            native
+        }
+        // Blob ids aren't kept, the content comes next
+        ColumnType::Blob => take(data, pos, 8)?.to_vec(),
+    };
+
+    Ok(bytes)
+}
+
+/// Little endian integer of any length
+fn integer(bytes: &[u8]) -> i64 {
+    // Sign extended from the last byte
+    let start = match bytes.last() {
+        Some(b) if b & 0x80 != 0 => -1,
+        _ => 0,
+    };
+
+    bytes
+        .iter()
+        .take(8)
+        .rev()
+        .fold(start, |val, b| (val << 8) | *b as i64)
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim().to_string()
+}
+
+fn source(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).to_string()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    pub fn decompress_runs() -> Result<(), Error> {
+        // 3 literal bytes, 5 zeros and 1 literal byte
+        let bytes: &[u8] = &[3, b'a', b'b', b'c', (-5i8) as u8, 0, 1, b'd'];
+        let mut stream = BackupStream { reader: bytes };
+
+        assert_eq!(b"abc\0\0\0\0\0d".to_vec(), stream.decompress(9)?);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn integer_attributes() {
+        assert_eq!(10, integer(&[10, 0, 0, 0]));
+        assert_eq!(-2, integer(&[0xfe, 0xff]));
+        assert_eq!(1 << 40, integer(&[0, 0, 0, 0, 0, 1, 0, 0]));
+        assert_eq!(0, integer(&[]));
+    }
+
+    #[test]
+    pub fn field_dimensions() -> Result<(), Error> {
+        let mut col = empty_column();
+        load_field(&mut col, &[(ATT_FIELD_DIMENSIONS, vec![2])])?;
+        assert_eq!(2, col.dimensions.len());
+
+        for value in [vec![0xff], vec![17], vec![0, 0, 0, 0x40]] {
+            assert!(load_field(&mut col, &[(ATT_FIELD_DIMENSIONS, value)]).is_err());
+        }
+
+        Ok(())
+    }
+}
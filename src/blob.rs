@@ -0,0 +1,523 @@
+//! Firebird blobs representation and reading
+
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read};
+
+use crate::data::*;
+use crate::page::*;
+use crate::record::*;
+use crate::source::Source;
+use crate::Error;
+
+/// Size of the blob header, before the data or the page vector
+const BLH_SIZE: usize = 28;
+
+/// Size of the blob page header, before the data or the page vector
+const BLP_SIZE: usize = 28;
+
+/// Blob page with the numbers of other blob pages
+const BLP_POINTERS: u8 = 0x01;
+
+/// Blob id, the reference stored on the record to the blob content
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BlobId {
+    /// Relation where the blob is stored
+    pub relation: u16,
+    /// Record number of the blob inside of the relation
+    pub number: u64,
+}
+
+impl BlobId {
+    /// Parse the blob id from the 8 bytes of the record
+    pub fn from_bytes(bytes: &[u8]) -> Result<BlobId, Error> {
+        if bytes.len() < 8 {
+            return Err(Error::Other(format!(
+                "Blob id with {} bytes, expected 8",
+                bytes.len()
+            )));
+        }
+
+        // The record number has 40 bits, the upper byte
+        // is stored right before the lower 4 bytes
+        let number = ((bytes[3] as u64) << 32) | LittleEndian::read_u32(&bytes[4..8]) as u64;
+
+        Ok(BlobId {
+            relation: LittleEndian::read_u16(&bytes[0..2]),
+            number,
+        })
+    }
+
+    /// Data page sequence and line where the blob header is stored
+    pub fn location(&self, page_size: u16) -> (u32, usize) {
+        let max = DataPage::max_records(page_size) as u64;
+
+        ((self.number / max) as u32, (self.number % max) as usize)
+    }
+}
+
+impl fmt::Display for BlobId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Same format used by isql
+        write!(f, "{:x}:{:x}", self.relation, self.number)
+    }
+}
+
+/// Blob Header
+///
+/// Every blob starts with a record on a data page of the relation.
+/// Small blobs (level 0) have its content right after the header,
+/// the bigger ones keep there a vector of blob pages numbers (level
+/// 1) or a vector of blob pages with other blob pages numbers (level 2).
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct BlobHeader {
+    /// First data page number
+    pub lead_page: u32,
+    /// Number of data pages
+    pub max_sequence: u32,
+    /// Longest segment
+    pub max_segment: u16,
+    /// Same flags of the record header
+    pub flags: u16,
+    /// Number of address levels
+    pub level: u8,
+    /// Total number of segments
+    pub count: u32,
+    /// Total length of data, without the segments sizes
+    pub length: u32,
+    /// Blob sub-type
+    pub sub_type: i16,
+    /// Blob charset
+    pub charset: u8,
+    unused: u8,
+}
+
+impl BlobHeader {
+    pub fn from_bytes(bytes: &[u8]) -> Result<BlobHeader, Error> {
+        if bytes.len() < BLH_SIZE {
+            return Err(Error::Other(format!(
+                "Blob with {} bytes is smaller than its header",
+                bytes.len()
+            )));
+        }
+
+        // The header is at any offset of the record, so it's read
+        // field by field instead of casted
+        let hdr = BlobHeader {
+            lead_page: LittleEndian::read_u32(&bytes[0..4]),
+            max_sequence: LittleEndian::read_u32(&bytes[4..8]),
+            max_segment: LittleEndian::read_u16(&bytes[8..10]),
+            flags: LittleEndian::read_u16(&bytes[10..12]),
+            level: bytes[12],
+            count: LittleEndian::read_u32(&bytes[16..20]),
+            length: LittleEndian::read_u32(&bytes[20..24]),
+            sub_type: LittleEndian::read_i16(&bytes[24..26]),
+            charset: bytes[26],
+            unused: bytes[27],
+        };
+
+        if hdr.flags & RHD_BLOB == 0 {
+            return Err(Error::Other("Record isn't a blob".to_string()));
+        }
+
+        Ok(hdr)
+    }
+
+    /// Stream blobs don't have the segments sizes on content
+    pub fn is_stream(&self) -> bool {
+        self.flags & RHD_STREAM_BLOB != 0
+    }
+}
+
+/// Blob Page
+///
+/// Blobs that doesn't fit on a data page are stored on its own
+/// pages. A blob page hold a slice of blob content or, with a
+/// level 2 blob, a vector of other blob pages numbers.
+#[derive(Debug, Clone)]
+pub struct BlobPage {
+    pub pag: Page,
+    /// The first page of the blob
+    pub lead_page: u32,
+    /// Sequence number of this page inside of the blob
+    pub sequence: u32,
+    /// Bytes used on this page
+    pub length: u16,
+    /// Content of this page
+    pub data: Vec<u8>,
+}
+
+impl BlobPage {
+    /// Parse the BlobPage from bytes
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<BlobPage, Error> {
+        let pag = Page::from_bytes(&bytes)?;
+        if pag.ptype != 0x08 {
+            return Err(Error::InvalidPage {
+                tpe: pag.ptype,
+                expected: 0x08,
+                desc: "blob".to_string(),
+            });
+        }
+        if bytes.len() < BLP_SIZE {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: BLP_SIZE,
+                msg: "blob page header".to_string(),
+            });
+        }
+
+        let length = LittleEndian::read_u16(&bytes[24..26]);
+        let end = BLP_SIZE + length as usize;
+        if end > bytes.len() {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: end,
+                msg: "blob page length".to_string(),
+            });
+        }
+
+        Ok(BlobPage {
+            pag,
+            lead_page: LittleEndian::read_u32(&bytes[16..20]),
+            sequence: LittleEndian::read_u32(&bytes[20..24]),
+            length,
+            data: bytes[BLP_SIZE..end].to_vec(),
+        })
+    }
+
+    /// Check if this page holds other blob pages numbers
+    pub fn is_pointers(&self) -> bool {
+        self.pag.flags & BLP_POINTERS != 0
+    }
+}
+
+/// Lazy reader of a blob content
+///
+/// The blob pages are only read from the database when needed,
+/// so big blobs can be streamed. The segments of a segmented blob
+/// are returned concatenated.
+pub struct BlobReader {
+    pub header: BlobHeader,
+    buffer: Source,
+    page_size: u16,
+    /// Blob pages numbers of the header, with data (level 1) or
+    /// with pointers (level 2)
+    pages: VecDeque<u32>,
+    /// Data pages numbers of the current pointer page (level 2)
+    data_pages: VecDeque<u32>,
+    /// Content of the current page
+    chunk: Vec<u8>,
+    chunk_pos: usize,
+    /// Bytes remaining on the current segment
+    segment: usize,
+}
+
+impl BlobReader {
+    /// Prepare the reader of a blob, from the data page of its
+    /// location
+    pub(crate) fn open(
+        id: &BlobId,
+        data: &DataPage,
+        buffer: Source,
+        page_size: u16,
+    ) -> Result<BlobReader, Error> {
+        let (_, line) = id.location(page_size);
+
+        let idx = data
+            .records
+            .get(line)
+            .filter(|idx| idx.length > 0)
+            .ok_or_else(|| Error::Other(format!("Blob {} not found", id)))?;
+
+        let raw = data.get_record_raw(*idx)?;
+        let header = BlobHeader::from_bytes(raw)?;
+        let content = &raw[BLH_SIZE..];
+
+        let mut reader = BlobReader {
+            header,
+            buffer,
+            page_size,
+            pages: VecDeque::new(),
+            data_pages: VecDeque::new(),
+            chunk: vec![],
+            chunk_pos: 0,
+            segment: 0,
+        };
+
+        match header.level {
+            0 => reader.chunk = content.to_vec(),
+            1 | 2 => reader.pages = read_pages_vector(content),
+            level => {
+                return Err(Error::Other(format!(
+                    "Blob {} with unsupported level {}",
+                    id, level
+                )))
+            }
+        }
+
+        Ok(reader)
+    }
+
+    /// Load the content of the next blob page
+    fn next_chunk(&mut self) -> Result<bool, Error> {
+        if self.header.level == 2 && self.data_pages.is_empty() {
+            match self.pages.pop_front() {
+                Some(pageno) => {
+                    let page = self.read_blob_page(pageno)?;
+                    if !page.is_pointers() {
+                        return Err(Error::Other(format!(
+                            "Blob page {} should have pointers to other pages",
+                            pageno
+                        )));
+                    }
+                    self.data_pages = read_pages_vector(&page.data);
+                }
+                None => return Ok(false),
+            }
+        }
+
+        let next = match self.header.level {
+            1 => self.pages.pop_front(),
+            2 => self.data_pages.pop_front(),
+            _ => None,
+        };
+
+        match next {
+            Some(pageno) => {
+                self.chunk = self.read_blob_page(pageno)?.data;
+                self.chunk_pos = 0;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn read_blob_page(&self, pageno: u32) -> Result<BlobPage, Error> {
+        let poip = read_page(&self.buffer, self.page_size, pageno)?;
+
+        BlobPage::from_bytes(poip)
+    }
+
+    /// Read the content of pages, segments sizes included
+    fn read_raw(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        while self.chunk_pos >= self.chunk.len() {
+            if !self.next_chunk()? {
+                return Ok(0);
+            }
+        }
+
+        let size = buf.len().min(self.chunk.len() - self.chunk_pos);
+        buf[..size].copy_from_slice(&self.chunk[self.chunk_pos..self.chunk_pos + size]);
+        self.chunk_pos += size;
+
+        Ok(size)
+    }
+
+    /// Read the content of pages, failing if the blob ends before
+    fn read_raw_exact(&mut self, buf: &mut [u8]) -> Result<bool, Error> {
+        let mut readed = 0;
+        while readed < buf.len() {
+            let size = self.read_raw(&mut buf[readed..])?;
+            if size == 0 {
+                if readed == 0 {
+                    return Ok(false);
+                }
+                return Err(Error::Other(
+                    "Blob ended in the middle of a segment".to_string(),
+                ));
+            }
+            readed += size;
+        }
+
+        Ok(true)
+    }
+
+    fn read_content(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.header.is_stream() {
+            return self.read_raw(buf);
+        }
+
+        // Segmented blobs store the size of every segment
+        // before its content
+        while self.segment == 0 {
+            let mut bsize = [0u8; 2];
+            if !self.read_raw_exact(&mut bsize)? {
+                return Ok(0);
+            }
+            self.segment = LittleEndian::read_u16(&bsize) as usize;
+        }
+
+        let size = buf.len().min(self.segment);
+        let size = self.read_raw(&mut buf[..size])?;
+        if size == 0 {
+            return Err(Error::Other(
+                "Blob ended in the middle of a segment".to_string(),
+            ));
+        }
+        self.segment -= size;
+
+        Ok(size)
+    }
+}
+
+impl Read for BlobReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_content(buf).map_err(|e| match e {
+            Error::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        })
+    }
+}
+
+/// Read a vector of pages numbers
+fn read_pages_vector(bytes: &[u8]) -> VecDeque<u32> {
+    bytes.chunks_exact(4).map(LittleEndian::read_u32).collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::source::SharedSource;
+
+    const PAGE_SIZE: u16 = 4096;
+
+    fn blob_page(flags: u8, data: &[u8]) -> Vec<u8> {
+        let mut page = vec![0u8; PAGE_SIZE.into()];
+        page[0] = 0x08;
+        page[1] = flags;
+        LittleEndian::write_u16(&mut page[24..26], data.len() as u16);
+        page[BLP_SIZE..BLP_SIZE + data.len()].copy_from_slice(data);
+        page
+    }
+
+    fn blob_header(level: u8, length: u32, pages: &[u32]) -> Vec<u8> {
+        let mut rec = vec![0u8; BLH_SIZE];
+        LittleEndian::write_u16(&mut rec[10..12], RHD_BLOB);
+        rec[12] = level;
+        LittleEndian::write_u32(&mut rec[20..24], length);
+        for page in pages {
+            let mut bpage = [0u8; 4];
+            LittleEndian::write_u32(&mut bpage, *page);
+            rec.extend_from_slice(&bpage);
+        }
+        rec
+    }
+
+    /// Database file with the blob pages and a data page with the
+    /// blob headers, one of level 1 and other of level 2
+    fn blob_levels() -> Result<(PathBuf, DataPage), Error> {
+        // The size of the second segment is splitted between pages
+        let content = b"\x06\x00Hello \x0e\x00Firebird blobs";
+
+        let mut pointers = vec![0u8; 8];
+        LittleEndian::write_u32(&mut pointers[0..4], 1);
+        LittleEndian::write_u32(&mut pointers[4..8], 2);
+
+        let fpath = std::env::temp_dir().join("rfirebird_blob_levels.fdb");
+        let mut file = File::create(&fpath)?;
+        file.write_all(&vec![0u8; PAGE_SIZE.into()])?;
+        file.write_all(&blob_page(0, &content[..9]))?;
+        file.write_all(&blob_page(0, &content[9..]))?;
+        file.write_all(&blob_page(BLP_POINTERS, &pointers))?;
+        drop(file);
+
+        let mut data = vec![0u8; PAGE_SIZE.into()];
+        data[0] = 0x05;
+        LittleEndian::write_u16(&mut data[20..22], 200);
+        LittleEndian::write_u16(&mut data[22..24], 2);
+
+        let mut offset = 1024;
+        for (line, rec) in [blob_header(1, 20, &[1, 2]), blob_header(2, 20, &[3])]
+            .iter()
+            .enumerate()
+        {
+            let idx = 24 + line * 4;
+            LittleEndian::write_u16(&mut data[idx..idx + 2], offset as u16);
+            LittleEndian::write_u16(&mut data[idx + 2..idx + 4], rec.len() as u16);
+            data[offset..offset + rec.len()].copy_from_slice(rec);
+            offset += rec.len();
+        }
+
+        Ok((fpath, DataPage::from_bytes(data)?))
+    }
+
+    #[test]
+    pub fn blob_id_from_bytes() -> Result<(), Error> {
+        let id = BlobId::from_bytes(&[0x81, 0x00, 0x00, 0x00, 0xea, 0x01, 0x00, 0x00])?;
+        assert_eq!(129, id.relation);
+        assert_eq!(490, id.number);
+        assert_eq!("81:1ea", id.to_string());
+
+        let id = BlobId::from_bytes(&[0x81, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00])?;
+        assert_eq!(1 << 32, id.number);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn blob_id_location() -> Result<(), Error> {
+        let id = BlobId::from_bytes(&[0x81, 0x00, 0x00, 0x00, 0xea, 0x01, 0x00, 0x00])?;
+        assert_eq!((1, 10), id.location(8192));
+
+        let id = BlobId::from_bytes(&[0x81, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00])?;
+        assert_eq!((0, 4), id.location(8192));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn blob_header_from_bytes() -> Result<(), Error> {
+        // Starting on an odd offset, like inside of a record
+        let mut rec = vec![0u8];
+        rec.extend(blob_header(1, 20, &[7]));
+        LittleEndian::write_u32(&mut rec[17..21], 3);
+        rec[27] = 4;
+
+        let hdr = BlobHeader::from_bytes(&rec[1..])?;
+        assert_eq!(1, hdr.level);
+        assert_eq!(3, hdr.count);
+        assert_eq!(20, hdr.length);
+        assert_eq!(4, hdr.charset);
+        assert!(!hdr.is_stream());
+
+        assert!(BlobHeader::from_bytes(&rec[1..BLH_SIZE]).is_err());
+
+        let page = BlobPage::from_bytes(blob_page(0, b"abc"))?;
+        assert_eq!(b"abc".to_vec(), page.data);
+        assert!(BlobPage::from_bytes(vec![]).is_err());
+        assert!(BlobPage::from_bytes(blob_page(0, b"abc")[..BLP_SIZE - 2].to_vec()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn reading_levels_1_and_2() -> Result<(), Error> {
+        let (fpath, data) = blob_levels()?;
+        let buffer = SharedSource::new(File::open(fpath)?);
+
+        for number in [0, 1] {
+            let id = BlobId {
+                relation: 200,
+                number,
+            };
+            let mut reader = BlobReader::open(&id, &data, buffer.clone(), PAGE_SIZE)?;
+
+            let mut content = String::new();
+            reader.read_to_string(&mut content)?;
+            assert_eq!("Hello Firebird blobs", content);
+        }
+
+        Ok(())
+    }
+}
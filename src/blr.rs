@@ -0,0 +1,1097 @@
+//! Binary Language Representation (BLR) decoding
+//!
+//! The BLR is the compiled form of the views, triggers, procedures,
+//! defaults and check constraints stored on the system tables.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::Error;
+
+const BLR_VERSION4: u8 = 4;
+const BLR_VERSION5: u8 = 5;
+const BLR_EOC: u8 = 76;
+const BLR_END: u8 = 255;
+
+/// Decode the BLR into the same readable text shown by isql
+/// with `SET BLOB ALL`
+pub fn print_blr(bytes: &[u8]) -> Result<String, Error> {
+    let mut printer = BlrPrinter {
+        bytes,
+        pos: 0,
+        lines: vec![],
+    };
+
+    printer.print()?;
+
+    Ok(printer
+        .lines
+        .iter()
+        .map(|l| l.trim_end())
+        .collect::<Vec<&str>>()
+        .join("\n"))
+}
+
+struct BlrPrinter<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    lines: Vec<String>,
+}
+
+impl<'a> BlrPrinter<'a> {
+    fn print(&mut self) -> Result<(), Error> {
+        let version = self.byte()?;
+        match version {
+            BLR_VERSION4 => self.line(0, "blr_version4"),
+            BLR_VERSION5 => self.line(0, "blr_version5"),
+            _ => return Err(Error::Other(format!("Unsupported BLR version {}", version))),
+        }
+
+        self.verb(0)?;
+
+        if self.byte()? != BLR_EOC {
+            return Err(self.error("expected blr_eoc"));
+        }
+        self.lines.push("blr_eoc".to_string());
+
+        Ok(())
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        Error::Other(format!("Invalid BLR at offset {}: {}", self.pos, msg))
+    }
+
+    fn peek(&self) -> Result<u8, Error> {
+        self.bytes
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| self.error("unexpected end"))
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        let b = self.peek()?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn take(&mut self, size: usize) -> Result<&'a [u8], Error> {
+        if self.pos + size > self.bytes.len() {
+            return Err(self.error("unexpected end"));
+        }
+
+        let bytes = &self.bytes[self.pos..self.pos + size];
+        self.pos += size;
+        Ok(bytes)
+    }
+
+    /// Start a new line with the indentation of level
+    fn line(&mut self, level: usize, st: &str) {
+        self.lines.push(format!("{}{}, ", "   ".repeat(level), st));
+    }
+
+    /// Start a new line for operands only
+    fn indent(&mut self, level: usize) {
+        self.lines.push("   ".repeat(level));
+    }
+
+    /// Append to the current line
+    fn append(&mut self, st: &str) {
+        if let Some(line) = self.lines.last_mut() {
+            line.push_str(st);
+            line.push_str(", ");
+        }
+    }
+
+    fn print_byte(&mut self) -> Result<u8, Error> {
+        let b = self.byte()?;
+        self.append(&b.to_string());
+        Ok(b)
+    }
+
+    fn print_word(&mut self) -> Result<u16, Error> {
+        let bytes = self.take(2)?;
+        self.append(&format!("{},{}", bytes[0], bytes[1]));
+        Ok(LittleEndian::read_u16(bytes))
+    }
+
+    fn print_bytes(&mut self, size: usize) -> Result<(), Error> {
+        let bytes = self.take(size)?;
+        let st = bytes
+            .iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        self.append(&st);
+        Ok(())
+    }
+
+    fn print_chars(&mut self, size: usize) -> Result<(), Error> {
+        if size == 0 {
+            return Ok(());
+        }
+
+        let bytes = self.take(size)?;
+        let st = bytes
+            .iter()
+            .map(|b| {
+                if b.is_ascii_graphic() || *b == b' ' {
+                    format!("'{}'", *b as char)
+                } else {
+                    b.to_string()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        self.append(&st);
+        Ok(())
+    }
+
+    /// Names are stored with its size on the first byte
+    fn print_name(&mut self) -> Result<(), Error> {
+        let size = self.print_byte()?;
+        self.print_chars(size.into())
+    }
+
+    fn print_dtype(&mut self) -> Result<u8, Error> {
+        let dtype = self.byte()?;
+        let name =
+            dtype_name(dtype).ok_or_else(|| self.error(&format!("unknown data type {}", dtype)))?;
+        self.append(name);
+
+        match dtype {
+            // text, varying and cstring
+            14 | 37 | 40 => {
+                self.print_word()?;
+            }
+            // text2, varying2 and cstring2
+            15 | 38 | 41 => {
+                self.print_word()?;
+                self.print_word()?;
+            }
+            // short, long, quad and int64
+            7 | 8 | 9 | 16 => {
+                self.print_byte()?;
+            }
+            // blob2
+            17 => {
+                self.print_word()?;
+                self.print_word()?;
+            }
+            // domain_name and domain_name2
+            18 | 19 => {
+                self.print_byte()?;
+                self.print_name()?;
+                if dtype == 19 {
+                    self.print_word()?;
+                }
+            }
+            // not_nullable
+            20 => {
+                self.print_dtype()?;
+            }
+            // column_name and column_name2
+            21 | 22 => {
+                self.print_byte()?;
+                self.print_name()?;
+                self.print_name()?;
+                if dtype == 22 {
+                    self.print_word()?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(dtype)
+    }
+
+    fn print_literal(&mut self) -> Result<(), Error> {
+        let dtype = self.print_dtype()?;
+
+        match dtype {
+            // text and text2
+            14 | 15 => {
+                let size = LittleEndian::read_u16(&self.bytes[self.pos - 2..self.pos]);
+                self.print_chars(size.into())?;
+            }
+            // short
+            7 => self.print_bytes(2)?,
+            // long, float, date and time
+            8 | 10 | 12 | 13 => self.print_bytes(4)?,
+            // quad, int64 and timestamp
+            9 | 16 | 35 => self.print_bytes(8)?,
+            // double are stored as text
+            27 => {
+                let size = self.print_word()?;
+                self.print_chars(size.into())?;
+            }
+            // bool
+            23 => self.print_bytes(1)?,
+            _ => {
+                return Err(self.error(&format!("unsupported literal of type {}", dtype)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verbs until the blr_end
+    fn verbs_until_end(&mut self, level: usize) -> Result<(), Error> {
+        while self.peek()? != BLR_END {
+            self.verb(level)?;
+        }
+        self.pos += 1;
+        self.line(level, "blr_end");
+
+        Ok(())
+    }
+
+    fn verbs(&mut self, level: usize, count: usize) -> Result<(), Error> {
+        for _ in 0..count {
+            self.verb(level)?;
+        }
+
+        Ok(())
+    }
+
+    /// Conditions used by blr_abort and blr_error_handler
+    fn print_condition(&mut self, level: usize) -> Result<(), Error> {
+        let code = self.byte()?;
+        let name = match code {
+            0 => "blr_gds_code",
+            1 => "blr_sql_code",
+            2 => "blr_exception",
+            3 => "blr_trigger_code",
+            4 => "blr_default_code",
+            5 => "blr_raise",
+            6 => "blr_exception_msg",
+            7 => "blr_exception_params",
+            8 => "blr_sql_state",
+            _ => return Err(self.error(&format!("unknown condition {}", code))),
+        };
+        self.append(name);
+
+        match code {
+            0 | 2 | 3 | 8 => self.print_name()?,
+            1 => {
+                self.print_word()?;
+            }
+            6 => {
+                self.print_name()?;
+                self.verb(level + 1)?;
+            }
+            7 => {
+                self.print_name()?;
+                let count = self.print_word()?;
+                self.verbs(level + 1, count.into())?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Body of blr_rse and its variations
+    fn print_rse(&mut self, level: usize) -> Result<(), Error> {
+        let count = self.print_byte()?;
+        self.verbs(level + 1, count.into())?;
+
+        loop {
+            let clause = self.byte()?;
+            match clause {
+                BLR_END => {
+                    self.line(level + 1, "blr_end");
+                    break;
+                }
+                // blr_boolean, blr_first, blr_skip
+                71 | 68 | 175 => {
+                    self.line(level + 1, verb_name(clause).unwrap_or_default());
+                    self.verb(level + 2)?;
+                }
+                // blr_sort, blr_project
+                70 | 69 => {
+                    self.line(level + 1, verb_name(clause).unwrap_or_default());
+                    self.print_sort(level + 1, clause == 70)?;
+                }
+                // blr_plan
+                139 => {
+                    self.line(level + 1, "blr_plan");
+                    self.verb(level + 2)?;
+                }
+                // blr_writelock
+                179 => self.line(level + 1, "blr_writelock"),
+                // blr_join_type
+                80 => {
+                    self.line(level + 1, "blr_join_type");
+                    self.print_byte()?;
+                }
+                _ => {
+                    return Err(self.error(&format!("unknown rse clause {}", clause)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn print_sort(&mut self, level: usize, with_order: bool) -> Result<(), Error> {
+        let count = self.print_byte()?;
+
+        for _ in 0..count {
+            if with_order {
+                // blr_nullsfirst or blr_nullslast
+                if let 178 | 180 = self.peek()? {
+                    let nulls = self.byte()?;
+                    self.line(level + 1, verb_name(nulls).unwrap_or_default());
+                }
+
+                let order = self.byte()?;
+                match order {
+                    72 => self.line(level + 1, "blr_ascending"),
+                    73 => self.line(level + 1, "blr_descending"),
+                    _ => return Err(self.error(&format!("unknown sort order {}", order))),
+                }
+                self.verb(level + 2)?;
+            } else {
+                self.verb(level + 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn print_map(&mut self, level: usize) -> Result<(), Error> {
+        if self.byte()? != 77 {
+            return Err(self.error("expected blr_map"));
+        }
+        self.line(level, "blr_map");
+
+        let count = self.print_word()?;
+        for _ in 0..count {
+            self.indent(level + 1);
+            self.print_word()?;
+            self.verb(level + 2)?;
+        }
+
+        Ok(())
+    }
+
+    fn print_exec_stmt(&mut self, level: usize) -> Result<(), Error> {
+        let mut inputs = 0;
+        let mut outputs = 0;
+
+        loop {
+            let code = self.byte()?;
+            let name = match code {
+                1 => "blr_exec_stmt_inputs",
+                2 => "blr_exec_stmt_outputs",
+                3 => "blr_exec_stmt_sql",
+                4 => "blr_exec_stmt_proc_block",
+                5 => "blr_exec_stmt_data_src",
+                6 => "blr_exec_stmt_user",
+                7 => "blr_exec_stmt_pwd",
+                8 => "blr_exec_stmt_tran",
+                9 => "blr_exec_stmt_tran_clone",
+                10 => "blr_exec_stmt_privs",
+                11 => "blr_exec_stmt_in_params",
+                12 => "blr_exec_stmt_in_params2",
+                13 => "blr_exec_stmt_out_params",
+                14 => "blr_exec_stmt_role",
+                BLR_END => "blr_end",
+                _ => return Err(self.error(&format!("unknown execute statement {}", code))),
+            };
+            self.line(level + 1, name);
+
+            match code {
+                1 => inputs = self.print_word()?,
+                2 => outputs = self.print_word()?,
+                3 | 4 | 5 | 6 | 7 | 14 => self.verb(level + 2)?,
+                8 | 9 => {
+                    self.print_byte()?;
+                }
+                11 => self.verbs(level + 2, inputs.into())?,
+                12 => {
+                    for _ in 0..inputs {
+                        self.indent(level + 2);
+                        self.print_name()?;
+                        self.verb(level + 3)?;
+                    }
+                }
+                13 => self.verbs(level + 2, outputs.into())?,
+                BLR_END => break,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verb(&mut self, level: usize) -> Result<(), Error> {
+        let code = self.byte()?;
+        let name = verb_name(code).ok_or_else(|| {
+            self.pos -= 1;
+            self.error(&format!("unknown verb {}", code))
+        })?;
+        self.line(level, name);
+
+        let sub = level + 1;
+
+        match code {
+            // Without operands
+            44 | 45 | 83 | 134 | 135 | 142 | 155 | 160 | 161 | 162 | 174 | 179 => {}
+
+            // One verb
+            9 | 11 | 20 | 38 | 59 | 60 | 61 | 62 | 71 | 84 | 85 | 86 | 87 | 93 | 94 | 95 | 96
+            | 103 | 127 | 139 | 152 | 151 | 158 | 176 | 177 | 181 | 201 => self.verb(sub)?,
+
+            // Two verbs
+            1 | 6 | 15 | 27 | 29 | 30 | 31 | 34 | 35 | 36 | 37 | 39 | 42 | 46 | 47 | 48 | 49
+            | 50 | 51 | 52 | 53 | 54 | 55 | 57 | 58 | 63 | 145 | 163 | 170 | 171 | 190 => {
+                self.verbs(sub, 2)?
+            }
+
+            // Three verbs
+            19 | 40 | 43 | 56 | 105 | 106 | 108 | 200 => self.verbs(sub, 3)?,
+
+            // Byte, like a context
+            5 | 18 | 22 | 154 | 168 | 169 | 197 | 209 => {
+                self.print_byte()?;
+            }
+
+            // Word, like a variable id
+            26 | 184 => {
+                self.print_word()?;
+            }
+
+            // Only a name
+            143 | 210 => self.print_name()?,
+
+            // blr_count: rse and an optional value
+            28 => self.verb(sub)?,
+
+            // blr_begin, blr_select
+            2 | 13 => self.verbs_until_end(sub)?,
+
+            // blr_dcl_variable
+            3 => {
+                self.print_word()?;
+                self.print_dtype()?;
+            }
+
+            // blr_message
+            4 => {
+                self.print_byte()?;
+                let count = self.print_word()?;
+                for _ in 0..count {
+                    self.indent(sub);
+                    self.print_dtype()?;
+                }
+            }
+
+            // blr_for
+            7 => {
+                if self.peek()? == 155 {
+                    self.verb(sub)?;
+                }
+                self.verbs(sub, 2)?;
+            }
+
+            // blr_if
+            8 => {
+                self.verbs(sub, 2)?;
+                if self.peek()? == BLR_END {
+                    self.pos += 1;
+                    self.line(sub, "blr_end");
+                } else {
+                    self.verb(sub)?;
+                }
+            }
+
+            // blr_modify
+            10 => {
+                self.print_byte()?;
+                self.print_byte()?;
+                self.verb(sub)?;
+            }
+
+            // blr_modify2
+            172 => {
+                self.print_byte()?;
+                self.print_byte()?;
+                self.verbs(sub, 2)?;
+            }
+
+            // blr_receive, blr_send, blr_label
+            12 | 14 | 17 => {
+                self.print_byte()?;
+                self.verb(sub)?;
+            }
+
+            // blr_literal
+            21 => self.print_literal()?,
+
+            // blr_field
+            23 => {
+                self.print_byte()?;
+                self.print_name()?;
+            }
+
+            // blr_fid
+            24 => {
+                self.print_byte()?;
+                self.print_word()?;
+            }
+
+            // blr_parameter
+            25 => {
+                self.print_byte()?;
+                self.print_word()?;
+            }
+
+            // blr_parameter2
+            41 => {
+                self.print_byte()?;
+                self.print_word()?;
+                self.print_word()?;
+            }
+
+            // blr_parameter3
+            88 => {
+                self.print_byte()?;
+                self.print_word()?;
+                self.print_word()?;
+                self.print_word()?;
+            }
+
+            // blr_rse, blr_rs_stream, blr_scrollable
+            67 | 119 | 109 => self.print_rse(level)?,
+
+            // blr_relation
+            74 => {
+                self.print_name()?;
+                self.print_byte()?;
+            }
+
+            // blr_rid
+            75 => {
+                self.print_word()?;
+                self.print_byte()?;
+            }
+
+            // blr_relation2
+            146 => {
+                self.print_name()?;
+                self.print_name()?;
+                self.print_byte()?;
+            }
+
+            // blr_rid2
+            147 => {
+                self.print_word()?;
+                self.print_name()?;
+                self.print_byte()?;
+            }
+
+            // blr_union, blr_recurse
+            76 | 185 => {
+                self.print_byte()?;
+                let count = self.print_byte()?;
+                for _ in 0..count {
+                    self.verb(sub)?;
+                    self.print_map(sub)?;
+                }
+            }
+
+            // blr_aggregate
+            79 => {
+                self.print_byte()?;
+                self.verb(sub)?;
+                if self.peek()? == 78 {
+                    self.pos += 1;
+                    self.line(sub, "blr_group_by");
+                    self.print_sort(sub, false)?;
+                }
+                self.print_map(sub)?;
+            }
+
+            // blr_function, blr_sys_function
+            100 | 186 => {
+                self.print_name()?;
+                let count = self.print_byte()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_function2
+            194 => {
+                self.print_name()?;
+                self.print_name()?;
+                let count = self.print_byte()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_gen_id, blr_set_generator
+            101 | 150 => {
+                self.print_name()?;
+                self.verb(sub)?;
+            }
+
+            // blr_index
+            107 => {
+                self.verb(sub)?;
+                let count = self.print_byte()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_exec_proc
+            120 => {
+                self.print_name()?;
+                let inputs = self.print_word()?;
+                self.verbs(sub, inputs.into())?;
+                let outputs = self.print_word()?;
+                self.verbs(sub, outputs.into())?;
+            }
+
+            // blr_exec_proc2
+            193 => {
+                self.print_name()?;
+                self.print_name()?;
+                let inputs = self.print_word()?;
+                self.verbs(sub, inputs.into())?;
+                let outputs = self.print_word()?;
+                self.verbs(sub, outputs.into())?;
+            }
+
+            // blr_exec_pid
+            126 => {
+                self.print_word()?;
+                let inputs = self.print_word()?;
+                self.verbs(sub, inputs.into())?;
+                let outputs = self.print_word()?;
+                self.verbs(sub, outputs.into())?;
+            }
+
+            // blr_procedure
+            124 => {
+                self.print_name()?;
+                self.print_byte()?;
+                let count = self.print_word()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_pid
+            125 => {
+                self.print_word()?;
+                self.print_byte()?;
+                let count = self.print_word()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_pid2
+            132 => {
+                self.print_word()?;
+                self.print_name()?;
+                self.print_byte()?;
+                let count = self.print_word()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_procedure2, blr_procedure3
+            133 | 192 => {
+                self.print_name()?;
+                self.print_name()?;
+                self.print_byte()?;
+                let count = self.print_word()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_procedure4
+            198 => {
+                self.print_name()?;
+                self.print_name()?;
+                self.print_name()?;
+                self.print_byte()?;
+                let count = self.print_word()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_abort
+            128 => self.print_condition(level)?,
+
+            // blr_block
+            129 => {
+                self.verb(sub)?;
+                self.verbs_until_end(sub)?;
+            }
+
+            // blr_error_handler
+            130 => {
+                let count = self.print_word()?;
+                for _ in 0..count {
+                    self.print_condition(level)?;
+                }
+                self.verb(sub)?;
+            }
+
+            // blr_cast
+            131 => {
+                self.print_dtype()?;
+                self.verb(sub)?;
+            }
+
+            // blr_merge, blr_join
+            140 | 141 => {
+                let count = self.print_byte()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_indices
+            144 => {
+                let count = self.print_byte()?;
+                for _ in 0..count {
+                    self.print_name()?;
+                }
+            }
+
+            // blr_extract, blr_strlen
+            159 | 182 => {
+                self.print_byte()?;
+                self.verb(sub)?;
+            }
+
+            // blr_exec_into
+            164 => {
+                let outputs = self.print_word()?;
+                self.verb(sub)?;
+                if self.print_byte()? == 0 {
+                    self.verb(sub)?;
+                }
+                self.verbs(sub, outputs.into())?;
+            }
+
+            // blr_user_savepoint
+            165 => {
+                self.print_byte()?;
+                self.print_name()?;
+            }
+
+            // blr_dcl_cursor
+            166 => {
+                self.print_word()?;
+                self.verb(sub)?;
+                let count = self.print_word()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_cursor_stmt
+            167 => {
+                let op = self.print_byte()?;
+                self.print_word()?;
+                match op {
+                    // blr_cursor_fetch
+                    2 => self.verb(sub)?,
+                    // blr_cursor_fetch_scroll
+                    3 => {
+                        self.print_byte()?;
+                        self.verbs(sub, 2)?;
+                    }
+                    _ => {}
+                }
+            }
+
+            // blr_trim
+            183 => {
+                self.print_byte()?;
+                // blr_trim_characters
+                if self.print_byte()? == 1 {
+                    self.verb(sub)?;
+                }
+                self.verb(sub)?;
+            }
+
+            // blr_auto_trans
+            187 => {
+                self.print_byte()?;
+                self.verb(sub)?;
+            }
+
+            // blr_similar
+            188 => {
+                self.verbs(sub, 2)?;
+                if self.print_byte()? != 0 {
+                    self.verb(sub)?;
+                }
+            }
+
+            // blr_exec_stmt
+            189 => self.print_exec_stmt(level)?,
+
+            // blr_derived_expr
+            191 => {
+                let count = self.print_byte()?;
+                for _ in 0..count {
+                    self.print_byte()?;
+                }
+                self.verb(sub)?;
+            }
+
+            // blr_coalesce
+            202 => {
+                let count = self.print_byte()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            // blr_decode
+            203 => {
+                self.verb(sub)?;
+                let count = self.print_byte()?;
+                self.verbs(sub, count.into())?;
+                let count = self.print_byte()?;
+                self.verbs(sub, count.into())?;
+            }
+
+            _ => return Err(self.error(&format!("unsupported verb {}", name))),
+        }
+
+        Ok(())
+    }
+}
+
+fn verb_name(code: u8) -> Option<&'static str> {
+    let name = match code {
+        1 => "blr_assignment",
+        2 => "blr_begin",
+        3 => "blr_dcl_variable",
+        4 => "blr_message",
+        5 => "blr_erase",
+        6 => "blr_fetch",
+        7 => "blr_for",
+        8 => "blr_if",
+        9 => "blr_loop",
+        10 => "blr_modify",
+        11 => "blr_handler",
+        12 => "blr_receive",
+        13 => "blr_select",
+        14 => "blr_send",
+        15 => "blr_store",
+        17 => "blr_label",
+        18 => "blr_leave",
+        19 => "blr_store2",
+        20 => "blr_post",
+        21 => "blr_literal",
+        22 => "blr_dbkey",
+        23 => "blr_field",
+        24 => "blr_fid",
+        25 => "blr_parameter",
+        26 => "blr_variable",
+        27 => "blr_average",
+        28 => "blr_count",
+        29 => "blr_maximum",
+        30 => "blr_minimum",
+        31 => "blr_total",
+        34 => "blr_add",
+        35 => "blr_subtract",
+        36 => "blr_multiply",
+        37 => "blr_divide",
+        38 => "blr_negate",
+        39 => "blr_concatenate",
+        40 => "blr_substring",
+        41 => "blr_parameter2",
+        42 => "blr_from",
+        43 => "blr_via",
+        44 => "blr_user_name",
+        45 => "blr_null",
+        46 => "blr_equiv",
+        47 => "blr_eql",
+        48 => "blr_neq",
+        49 => "blr_gtr",
+        50 => "blr_geq",
+        51 => "blr_lss",
+        52 => "blr_leq",
+        53 => "blr_containing",
+        54 => "blr_matching",
+        55 => "blr_starting",
+        56 => "blr_between",
+        57 => "blr_or",
+        58 => "blr_and",
+        59 => "blr_not",
+        60 => "blr_any",
+        61 => "blr_missing",
+        62 => "blr_unique",
+        63 => "blr_like",
+        67 => "blr_rse",
+        68 => "blr_first",
+        69 => "blr_project",
+        70 => "blr_sort",
+        71 => "blr_boolean",
+        72 => "blr_ascending",
+        73 => "blr_descending",
+        74 => "blr_relation",
+        75 => "blr_rid",
+        76 => "blr_union",
+        77 => "blr_map",
+        78 => "blr_group_by",
+        79 => "blr_aggregate",
+        80 => "blr_join_type",
+        83 => "blr_agg_count",
+        84 => "blr_agg_max",
+        85 => "blr_agg_min",
+        86 => "blr_agg_total",
+        87 => "blr_agg_average",
+        88 => "blr_parameter3",
+        93 => "blr_agg_count2",
+        94 => "blr_agg_count_distinct",
+        95 => "blr_agg_total_distinct",
+        96 => "blr_agg_average_distinct",
+        100 => "blr_function",
+        101 => "blr_gen_id",
+        103 => "blr_upcase",
+        105 => "blr_value_if",
+        106 => "blr_matching2",
+        107 => "blr_index",
+        108 => "blr_ansi_like",
+        109 => "blr_scrollable",
+        119 => "blr_rs_stream",
+        120 => "blr_exec_proc",
+        124 => "blr_procedure",
+        125 => "blr_pid",
+        126 => "blr_exec_pid",
+        127 => "blr_singular",
+        128 => "blr_abort",
+        129 => "blr_block",
+        130 => "blr_error_handler",
+        131 => "blr_cast",
+        132 => "blr_pid2",
+        133 => "blr_procedure2",
+        134 => "blr_start_savepoint",
+        135 => "blr_end_savepoint",
+        139 => "blr_plan",
+        140 => "blr_merge",
+        141 => "blr_join",
+        142 => "blr_sequential",
+        143 => "blr_navigational",
+        144 => "blr_indices",
+        145 => "blr_retrieve",
+        146 => "blr_relation2",
+        147 => "blr_rid2",
+        150 => "blr_set_generator",
+        151 => "blr_ansi_any",
+        152 => "blr_exists",
+        154 => "blr_record_version",
+        155 => "blr_stall",
+        158 => "blr_ansi_all",
+        159 => "blr_extract",
+        160 => "blr_current_date",
+        161 => "blr_current_timestamp",
+        162 => "blr_current_time",
+        163 => "blr_post_arg",
+        164 => "blr_exec_into",
+        165 => "blr_user_savepoint",
+        166 => "blr_dcl_cursor",
+        167 => "blr_cursor_stmt",
+        168 => "blr_current_timestamp2",
+        169 => "blr_current_time2",
+        170 => "blr_agg_list",
+        171 => "blr_agg_list_distinct",
+        172 => "blr_modify2",
+        174 => "blr_current_role",
+        175 => "blr_skip",
+        176 => "blr_exec_sql",
+        177 => "blr_internal_info",
+        178 => "blr_nullsfirst",
+        179 => "blr_writelock",
+        180 => "blr_nullslast",
+        181 => "blr_lowcase",
+        182 => "blr_strlen",
+        183 => "blr_trim",
+        184 => "blr_init_variable",
+        185 => "blr_recurse",
+        186 => "blr_sys_function",
+        187 => "blr_auto_trans",
+        188 => "blr_similar",
+        189 => "blr_exec_stmt",
+        190 => "blr_stmt_expr",
+        191 => "blr_derived_expr",
+        192 => "blr_procedure3",
+        193 => "blr_exec_proc2",
+        194 => "blr_function2",
+        197 => "blr_continue_loop",
+        198 => "blr_procedure4",
+        200 => "blr_substring_similar",
+        201 => "blr_bool_as_value",
+        202 => "blr_coalesce",
+        203 => "blr_decode",
+        209 => "blr_record_version2",
+        210 => "blr_gen_id2",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+fn dtype_name(dtype: u8) -> Option<&'static str> {
+    let name = match dtype {
+        7 => "blr_short",
+        8 => "blr_long",
+        9 => "blr_quad",
+        10 => "blr_float",
+        11 => "blr_d_float",
+        12 => "blr_sql_date",
+        13 => "blr_sql_time",
+        14 => "blr_text",
+        15 => "blr_text2",
+        16 => "blr_int64",
+        17 => "blr_blob2",
+        18 => "blr_domain_name",
+        19 => "blr_domain_name2",
+        20 => "blr_not_nullable",
+        21 => "blr_column_name",
+        22 => "blr_column_name2",
+        23 => "blr_bool",
+        27 => "blr_double",
+        35 => "blr_timestamp",
+        37 => "blr_varying",
+        38 => "blr_varying2",
+        40 => "blr_cstring",
+        41 => "blr_cstring2",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    #[test]
+    pub fn print_default_value() -> Result<(), Error> {
+        // DEFAULT 0
+        let blr = vec![5, 21, 8, 0, 0, 0, 0, 0, 76];
+
+        assert_eq!(
+            "blr_version5,\nblr_literal, blr_long, 0, 0,0,0,0,\nblr_eoc",
+            print_blr(&blr)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn print_check_constraint() -> Result<(), Error> {
+        // CHECK (VALUE > 0)
+        let blr = vec![5, 49, 45, 21, 8, 0, 0, 0, 0, 0, 76];
+
+        assert_eq!(
+            "blr_version5,\nblr_gtr,\n   blr_null,\n   blr_literal, blr_long, 0, 0,0,0,0,\nblr_eoc",
+            print_blr(&blr)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn print_invalid() {
+        assert!(print_blr(&[5, 49, 45]).is_err());
+        assert!(print_blr(&[5, 99, 76]).is_err());
+        assert!(print_blr(&[]).is_err());
+    }
+}
@@ -0,0 +1,447 @@
+//! Character sets definitions and decoding
+
+use encoding_rs::Encoding;
+use num_enum::TryFromPrimitive;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Error;
+
+/// Firebird character set, identified by RDB$CHARACTER_SET_ID
+#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Clone, Copy)]
+#[repr(i16)]
+pub enum Charset {
+    None = 0,
+    Octets = 1,
+    Ascii = 2,
+    UnicodeFss = 3,
+    Utf8 = 4,
+    Sjis0208 = 5,
+    Eucj0208 = 6,
+    Dos737 = 9,
+    Dos437 = 10,
+    Dos850 = 11,
+    Dos865 = 12,
+    Dos860 = 13,
+    Dos863 = 14,
+    Dos775 = 15,
+    Dos858 = 16,
+    Dos862 = 17,
+    Dos864 = 18,
+    Next = 19,
+    Iso8859_1 = 21,
+    Iso8859_2 = 22,
+    Iso8859_3 = 23,
+    Iso8859_4 = 34,
+    Iso8859_5 = 35,
+    Iso8859_6 = 36,
+    Iso8859_7 = 37,
+    Iso8859_8 = 38,
+    Iso8859_9 = 39,
+    Iso8859_13 = 40,
+    Ksc5601 = 44,
+    Dos852 = 45,
+    Dos857 = 46,
+    Dos861 = 47,
+    Dos866 = 48,
+    Dos869 = 49,
+    Cyrl = 50,
+    Win1250 = 51,
+    Win1251 = 52,
+    Win1252 = 53,
+    Win1253 = 54,
+    Win1254 = 55,
+    Big5 = 56,
+    Gb2312 = 57,
+    Win1255 = 58,
+    Win1256 = 59,
+    Win1257 = 60,
+    Koi8r = 63,
+    Koi8u = 64,
+    Win1258 = 65,
+    Tis620 = 66,
+    Gbk = 67,
+    Cp943c = 68,
+    Gb18030 = 69,
+}
+
+impl Charset {
+    /// Encoding used to decode the charset, when it isn't
+    /// a plain byte to char conversion
+    fn encoding(&self) -> Option<&'static Encoding> {
+        let label: &[u8] = match self {
+            Charset::Sjis0208 | Charset::Cp943c => b"shift_jis",
+            Charset::Eucj0208 => b"euc-jp",
+            Charset::Iso8859_2 => b"iso-8859-2",
+            Charset::Iso8859_3 => b"iso-8859-3",
+            Charset::Iso8859_4 => b"iso-8859-4",
+            Charset::Iso8859_5 => b"iso-8859-5",
+            Charset::Iso8859_6 => b"iso-8859-6",
+            Charset::Iso8859_7 => b"iso-8859-7",
+            Charset::Iso8859_8 => b"iso-8859-8",
+            Charset::Iso8859_9 => b"iso-8859-9",
+            Charset::Iso8859_13 => b"iso-8859-13",
+            Charset::Ksc5601 => b"euc-kr",
+            Charset::Dos866 => b"ibm866",
+            Charset::Win1250 => b"windows-1250",
+            Charset::Win1251 => b"windows-1251",
+            Charset::Win1252 => b"windows-1252",
+            Charset::Win1253 => b"windows-1253",
+            Charset::Win1254 => b"windows-1254",
+            Charset::Big5 => b"big5",
+            Charset::Gb2312 | Charset::Gbk => b"gbk",
+            Charset::Win1255 => b"windows-1255",
+            Charset::Win1256 => b"windows-1256",
+            Charset::Win1257 => b"windows-1257",
+            Charset::Koi8r => b"koi8-r",
+            Charset::Koi8u => b"koi8-u",
+            Charset::Win1258 => b"windows-1258",
+            Charset::Tis620 => b"windows-874",
+            Charset::Gb18030 => b"gb18030",
+            _ => return None,
+        };
+
+        Encoding::for_label(label)
+    }
+
+    /// Upper half of the DOS code pages, the lower half is ASCII
+    fn code_page(&self) -> Option<&'static str> {
+        let upper = match self {
+            Charset::Dos437 => DOS437,
+            Charset::Dos737 => DOS737,
+            Charset::Dos775 => DOS775,
+            Charset::Dos850 => DOS850,
+            Charset::Dos852 => DOS852,
+            Charset::Dos857 => DOS857,
+            Charset::Dos858 => DOS858,
+            Charset::Dos860 => DOS860,
+            Charset::Dos861 => DOS861,
+            Charset::Dos862 => DOS862,
+            Charset::Dos863 => DOS863,
+            Charset::Dos864 => DOS864,
+            Charset::Dos865 => DOS865,
+            Charset::Dos869 => DOS869,
+            _ => return None,
+        };
+
+        Some(upper)
+    }
+
+    /// Decode the bytes of a text stored with this charset
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, Error> {
+        Ok(self.decode_borrowed(bytes)?.into_owned())
+    }
+
+    /// Decode the bytes of a text stored with this charset,
+    /// borrowing them when they are already valid UTF-8
+    pub fn decode_borrowed<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>, Error> {
+        match self {
+            // Without a charset we can only hope for a UTF-8
+            Charset::None | Charset::UnicodeFss | Charset::Utf8 => std::str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .map_err(|e| Error::Other(format!("Found an invalid {} string: {}", self, e))),
+            Charset::Ascii if !bytes.is_ascii() => {
+                Err(Error::Other(format!("Found an invalid {} string", self)))
+            }
+            Charset::Ascii | Charset::Iso8859_1 => match std::str::from_utf8(bytes) {
+                Ok(st) if bytes.is_ascii() => Ok(Cow::Borrowed(st)),
+                _ => Ok(Cow::Owned(bytes.iter().map(|b| *b as char).collect())),
+            },
+            Charset::Octets => Err(Error::Other(format!(
+                "Charset {} holds binary data, not text",
+                self
+            ))),
+            _ => match self.encoding() {
+                Some(encoding) => {
+                    let (st, had_errors) = encoding.decode_without_bom_handling(bytes);
+                    if had_errors {
+                        return Err(Error::Other(format!("Found an invalid {} string", self)));
+                    }
+                    Ok(st)
+                }
+                None if bytes.is_ascii() => Ok(Cow::Borrowed(
+                    std::str::from_utf8(bytes).map_err(|e| Error::Other(e.to_string()))?,
+                )),
+                None => match self.code_page() {
+                    Some(upper) => {
+                        let upper: Vec<char> = upper.chars().collect();
+                        bytes
+                            .iter()
+                            .map(|b| match b {
+                                0..=0x7f => Some(*b as char),
+                                _ => Some(upper[*b as usize - 0x80])
+                                    .filter(|c| *c != char::REPLACEMENT_CHARACTER),
+                            })
+                            .collect::<Option<String>>()
+                            .map(Cow::Owned)
+                            .ok_or_else(|| {
+                                Error::Other(format!("Found an invalid {} string", self))
+                            })
+                    }
+                    // Without a table only the ASCII is kept, like
+                    // NEXT and CYRL
+                    None => Ok(Cow::Owned(
+                        bytes
+                            .iter()
+                            .map(|b| match b {
+                                0..=0x7f => *b as char,
+                                _ => char::REPLACEMENT_CHARACTER,
+                            })
+                            .collect(),
+                    )),
+                },
+            },
+        }
+    }
+
+    /// Encode a text to the bytes stored with this charset
+    pub fn encode(&self, st: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            Charset::None | Charset::UnicodeFss | Charset::Utf8 => Ok(st.as_bytes().to_vec()),
+            Charset::Ascii if !st.is_ascii() => {
+                Err(Error::Other(format!("Text not representable in {}", self)))
+            }
+            Charset::Ascii | Charset::Iso8859_1 => st
+                .chars()
+                .map(|c| u8::try_from(c as u32).ok())
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(|| Error::Other(format!("Text not representable in {}", self))),
+            Charset::Octets => Err(Error::Other(format!(
+                "Charset {} holds binary data, not text",
+                self
+            ))),
+            _ => match self.encoding() {
+                Some(encoding) => {
+                    let (bytes, _, had_errors) = encoding.encode(st);
+                    if had_errors {
+                        return Err(Error::Other(format!("Text not representable in {}", self)));
+                    }
+                    Ok(bytes.into_owned())
+                }
+                None => {
+                    let upper = self.code_page().unwrap_or_default();
+                    st.chars()
+                        .map(|c| match c {
+                            '\0'..='\x7f' => Some(c as u8),
+                            _ => upper.chars().position(|u| u == c).map(|p| p as u8 + 0x80),
+                        })
+                        .collect::<Option<Vec<u8>>>()
+                        .ok_or_else(|| Error::Other(format!("Text not representable in {}", self)))
+                }
+            },
+        }
+    }
+}
+
+const DOS437: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}"
+);
+const DOS737: &str = concat!(
+    "ΑΒΓΔΕΖΗΘΙΚΛΜΝΞΟΠΡΣΤΥΦΧΨΩαβγδεζηθικλμνξοπρσςτυφχψ░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀ωάέήϊίόύϋώΆΈΉΊΌΎΏ±≥≤ΪΫ÷≈°∙·√ⁿ²■\u{a0}"
+);
+const DOS775: &str = concat!(
+    "ĆüéāäģåćłēŖŗīŹÄÅÉæÆōöĢ¢ŚśÖÜø£Ø×¤ĀĪóŻżź”¦©®¬½¼Ł«»░▒▓│┤ĄČĘĖ╣║╗╝ĮŠ┐",
+    "└┴┬├─┼ŲŪ╚╔╩╦╠═╬Žąčęėįšųūž┘┌█▄▌▐▀ÓßŌŃõÕµńĶķĻļņĒŅ’\u{ad}±“¾¶§÷„°∙·¹³²■\u{a0}"
+);
+const DOS850: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜø£Ø×ƒáíóúñÑªº¿®¬½¼¡«»░▒▓│┤ÁÂÀ©╣║╗╝¢¥┐",
+    "└┴┬├─┼ãÃ╚╔╩╦╠═╬¤ðÐÊËÈıÍÎÏ┘┌█▄¦Ì▀ÓßÔÒõÕµþÞÚÛÙýÝ¯´\u{ad}±‗¾¶§÷¸°¨·¹³²■\u{a0}"
+);
+const DOS852: &str = concat!(
+    "ÇüéâäůćçłëŐőîŹÄĆÉĹĺôöĽľŚśÖÜŤťŁ×čáíóúĄąŽžĘę¬źČş«»░▒▓│┤ÁÂĚŞ╣║╗╝Żż┐",
+    "└┴┬├─┼Ăă╚╔╩╦╠═╬¤đĐĎËďŇÍÎě┘┌█▄ŢŮ▀ÓßÔŃńňŠšŔÚŕŰýÝţ´\u{ad}˝˛ˇ˘§÷¸°¨˙űŘř■\u{a0}"
+);
+const DOS857: &str = concat!(
+    "ÇüéâäàåçêëèïîıÄÅÉæÆôöòûùİÖÜø£ØŞşáíóúñÑĞğ¿®¬½¼¡«»░▒▓│┤ÁÂÀ©╣║╗╝¢¥┐",
+    "└┴┬├─┼ãÃ╚╔╩╦╠═╬¤ºªÊËÈ\u{fffd}ÍÎÏ┘┌█▄¦Ì▀ÓßÔÒõÕµ\u{fffd}×ÚÛÙìÿ¯´\u{ad}±\u{fffd}¾¶§÷¸°¨·¹³²■\u{a0}"
+);
+const DOS858: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜø£Ø×ƒáíóúñÑªº¿®¬½¼¡«»░▒▓│┤ÁÂÀ©╣║╗╝¢¥┐",
+    "└┴┬├─┼ãÃ╚╔╩╦╠═╬¤ðÐÊËÈ€ÍÎÏ┘┌█▄¦Ì▀ÓßÔÒõÕµþÞÚÛÙýÝ¯´\u{ad}±‗¾¶§÷¸°¨·¹³²■\u{a0}"
+);
+const DOS860: &str = concat!(
+    "ÇüéâãàÁçêÊèÍÔìÃÂÉÀÈôõòÚùÌÕÜ¢£Ù₧ÓáíóúñÑªº¿Ò¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}"
+);
+const DOS861: &str = concat!(
+    "ÇüéâäàåçêëèÐðÞÄÅÉæÆôöþûÝýÖÜø£Ø₧ƒáíóúÁÍÓÚ¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}"
+);
+const DOS862: &str = concat!(
+    "אבגדהוזחטיךכלםמןנסעףפץצקרשת¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}"
+);
+const DOS863: &str = concat!(
+    "ÇüéâÂà¶çêëèïî‗À§ÉÈÊôËÏûù¤ÔÜ¢£ÙÛƒ¦´óú¨¸³¯Î⌐¬½¼¾«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}"
+);
+const DOS864: &str = concat!(
+    "°·∙√▒─│┼┤┬├┴┐┌└┘β∞φ±½¼≈«»ﻷﻸ\u{fffd}\u{fffd}ﻻﻼ\u{fffd}\u{a0}\u{ad}ﺂ£¤ﺄ\u{fffd}\u{fffd}ﺎﺏﺕﺙ،ﺝﺡﺥ٠١٢٣٤٥٦٧٨٩ﻑ؛ﺱﺵﺹ؟",
+    "¢ﺀﺁﺃﺅﻊﺋﺍﺑﺓﺗﺛﺟﺣﺧﺩﺫﺭﺯﺳﺷﺻﺿﻁﻅﻋﻏ¦¬÷×ﻉـﻓﻗﻛﻟﻣﻧﻫﻭﻯﻳﺽﻌﻎﻍﻡﹽّﻥﻩﻬﻰﻲﻐﻕﻵﻶﻝﻙﻱ■\u{fffd}"
+);
+const DOS865: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜø£Ø₧ƒáíóúñÑªº¿⌐¬½¼¡«¤░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}"
+);
+const DOS869: &str = concat!(
+    "\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{fffd}Ά\u{fffd}·¬¦‘’Έ―ΉΊΪΌ\u{fffd}\u{fffd}ΎΫ©Ώ²³ά£έήίϊΐόύΑΒΓΔΕΖΗ½ΘΙ«»░▒▓│┤ΚΛΜΝ╣║╗╝ΞΟ┐",
+    "└┴┬├─┼ΠΡ╚╔╩╦╠═╬ΣΤΥΦΧΨΩαβγ┘┌█▄δε▀ζηθικλμνξοπρσςτ΄\u{ad}±υφχ§ψ΅°¨ωϋΰώ■\u{a0}"
+);
+
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Charset::None => "NONE",
+            Charset::Octets => "OCTETS",
+            Charset::Ascii => "ASCII",
+            Charset::UnicodeFss => "UNICODE_FSS",
+            Charset::Utf8 => "UTF8",
+            Charset::Sjis0208 => "SJIS_0208",
+            Charset::Eucj0208 => "EUCJ_0208",
+            Charset::Dos737 => "DOS737",
+            Charset::Dos437 => "DOS437",
+            Charset::Dos850 => "DOS850",
+            Charset::Dos865 => "DOS865",
+            Charset::Dos860 => "DOS860",
+            Charset::Dos863 => "DOS863",
+            Charset::Dos775 => "DOS775",
+            Charset::Dos858 => "DOS858",
+            Charset::Dos862 => "DOS862",
+            Charset::Dos864 => "DOS864",
+            Charset::Next => "NEXT",
+            Charset::Iso8859_1 => "ISO8859_1",
+            Charset::Iso8859_2 => "ISO8859_2",
+            Charset::Iso8859_3 => "ISO8859_3",
+            Charset::Iso8859_4 => "ISO8859_4",
+            Charset::Iso8859_5 => "ISO8859_5",
+            Charset::Iso8859_6 => "ISO8859_6",
+            Charset::Iso8859_7 => "ISO8859_7",
+            Charset::Iso8859_8 => "ISO8859_8",
+            Charset::Iso8859_9 => "ISO8859_9",
+            Charset::Iso8859_13 => "ISO8859_13",
+            Charset::Ksc5601 => "KSC_5601",
+            Charset::Dos852 => "DOS852",
+            Charset::Dos857 => "DOS857",
+            Charset::Dos861 => "DOS861",
+            Charset::Dos866 => "DOS866",
+            Charset::Dos869 => "DOS869",
+            Charset::Cyrl => "CYRL",
+            Charset::Win1250 => "WIN1250",
+            Charset::Win1251 => "WIN1251",
+            Charset::Win1252 => "WIN1252",
+            Charset::Win1253 => "WIN1253",
+            Charset::Win1254 => "WIN1254",
+            Charset::Big5 => "BIG_5",
+            Charset::Gb2312 => "GB_2312",
+            Charset::Win1255 => "WIN1255",
+            Charset::Win1256 => "WIN1256",
+            Charset::Win1257 => "WIN1257",
+            Charset::Koi8r => "KOI8R",
+            Charset::Koi8u => "KOI8U",
+            Charset::Win1258 => "WIN1258",
+            Charset::Tis620 => "TIS620",
+            Charset::Gbk => "GBK",
+            Charset::Cp943c => "CP943C",
+            Charset::Gb18030 => "GB18030",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Charset {
+    type Err = Error;
+
+    /// Charset by its Firebird name, like `WIN1252`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        (0..=69)
+            .filter_map(|id| Charset::try_from(id).ok())
+            .find(|c| c.to_string().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| Error::Other(format!("Charset {} not found", name)))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    #[test]
+    pub fn decode_single_byte() -> Result<(), Error> {
+        // Açaí
+        let bytes = vec![0x41, 0xe7, 0x61, 0xed];
+
+        assert_eq!("Açaí", Charset::Win1252.decode(&bytes)?);
+        assert_eq!("Açaí", Charset::Iso8859_1.decode(&bytes)?);
+        assert!(Charset::Utf8.decode(&bytes).is_err());
+        assert!(Charset::Ascii.decode(&bytes).is_err());
+
+        assert_eq!(bytes, Charset::Win1252.encode("Açaí")?);
+        assert_eq!(bytes, Charset::Iso8859_1.encode("Açaí")?);
+        assert!(Charset::Ascii.encode("Açaí").is_err());
+        assert!(Charset::Win1252.encode("Açaí 日本").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn decode_code_pages() -> Result<(), Error> {
+        // Açaí
+        let bytes = vec![0x41, 0x87, 0x61, 0xa1];
+
+        assert_eq!("Açaí", Charset::Dos437.decode(&bytes)?);
+        assert_eq!("Açaí", Charset::Dos850.decode(&bytes)?);
+        assert_eq!(bytes, Charset::Dos850.encode("Açaí")?);
+        assert_eq!(
+            "Ωμέγα",
+            Charset::Dos737.decode(&[0x97, 0xa3, 0xe2, 0x9a, 0x98])?
+        );
+        assert_eq!("\u{20ac}", Charset::Dos858.decode(&[0xd5])?);
+
+        // Without a table only the ASCII is decoded
+        assert_eq!("Ol\u{fffd}", Charset::Next.decode(&[0x4f, 0x6c, 0xe1])?);
+        assert_eq!(b"Ola".to_vec(), Charset::Cyrl.encode("Ola")?);
+        assert!(Charset::Cyrl.encode("Olá").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn charset_from_name() -> Result<(), Error> {
+        assert_eq!(Charset::Win1252, "WIN1252".parse()?);
+        assert_eq!(Charset::Iso8859_1, "iso8859_1".parse()?);
+        assert_eq!(Charset::Gb18030, "GB18030".parse()?);
+        assert!("LATIN1".parse::<Charset>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn decode_multi_byte() -> Result<(), Error> {
+        let bytes = "Açaí".as_bytes();
+
+        assert_eq!("Açaí", Charset::Utf8.decode(bytes)?);
+        assert_eq!("Açaí", Charset::UnicodeFss.decode(bytes)?);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn decode_borrowing() -> Result<(), Error> {
+        let borrowed = |st: Cow<str>| matches!(st, Cow::Borrowed(_));
+
+        assert!(borrowed(Charset::Utf8.decode_borrowed("Açaí".as_bytes())?));
+        assert!(borrowed(Charset::Win1252.decode_borrowed(b"Acai")?));
+        assert!(borrowed(Charset::Iso8859_1.decode_borrowed(b"Acai")?));
+
+        let st = Charset::Win1252.decode_borrowed(&[0x41, 0xe7, 0x61, 0xed])?;
+        assert!(!borrowed(st.clone()));
+        assert_eq!("Açaí", st);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+//! Consistency check between the indexes and the table data
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::index::*;
+use crate::key::*;
+use crate::record::RHD_DELETED;
+use crate::row::*;
+use crate::table::*;
+use crate::Error;
+
+/// Result of the check of a single index
+#[derive(Debug)]
+pub struct IndexCheck {
+    pub index: String,
+    /// Amount of leaf entries of the index
+    pub entries: usize,
+    /// Amount of rows of the table
+    pub rows: usize,
+    pub issues: Vec<IndexIssue>,
+    /// Entries of old versions and deleted records, waiting for
+    /// the garbage collection
+    pub garbage: usize,
+    /// Reason when the index can't be checked, like the
+    /// expression or inactive indexes
+    pub skipped: Option<String>,
+}
+
+/// Inconsistency found between an index and the table
+#[derive(Debug, PartialEq)]
+pub enum IndexIssue {
+    /// Row without an entry on the index
+    MissingEntry {
+        db_key: DbKey,
+        key: Vec<Option<Value>>,
+    },
+    /// Entry pointing to a record that isn't a row of the table
+    DanglingEntry {
+        record_number: u64,
+        key: Vec<Option<Value>>,
+    },
+    /// Entry with a key different of the one of the row
+    KeyMismatch {
+        db_key: DbKey,
+        entry: Vec<Option<Value>>,
+        row: Vec<Option<Value>>,
+    },
+}
+
+impl fmt::Display for IndexIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IndexIssue::MissingEntry { db_key, key } => {
+                write!(f, "row {} without entry for key ", db_key)?;
+                write_key(f, key)
+            }
+            IndexIssue::DanglingEntry { record_number, key } => {
+                write!(f, "entry ")?;
+                write_key(f, key)?;
+                write!(f, " points to the missing record {}", record_number)
+            }
+            IndexIssue::KeyMismatch { db_key, entry, row } => {
+                write!(f, "entry ")?;
+                write_key(f, entry)?;
+                write!(f, " of row {} doesn't match its key ", db_key)?;
+                write_key(f, row)
+            }
+        }
+    }
+}
+
+fn write_key(f: &mut fmt::Formatter, key: &[Option<Value>]) -> fmt::Result {
+    write!(f, "(")?;
+    for (i, val) in key.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        match val {
+            Some(val) => write!(f, "{}", val)?,
+            None => write!(f, "NULL")?,
+        }
+    }
+    write!(f, ")")
+}
+
+/// Entry of the index not yet matched with a row
+struct PendingEntry {
+    key: Vec<u8>,
+    segments: Vec<Vec<u8>>,
+    matched: bool,
+}
+
+/// Check the leaf entries of the index against the rows of the table
+pub fn check_index(table: &Table, index: &Index) -> Result<IndexCheck, Error> {
+    let mut check = IndexCheck {
+        index: index.name.clone(),
+        entries: 0,
+        rows: 0,
+        issues: vec![],
+        garbage: 0,
+        skipped: None,
+    };
+
+    check.skipped = if index.inactive {
+        Some("inactive index".to_string())
+    } else if index.root == 0 {
+        Some("index isn't built".to_string())
+    } else if index.segments.is_empty() {
+        Some("expression index".to_string())
+    } else {
+        index
+            .keys
+            .iter()
+            .find(|k| !is_supported(k.itype))
+            .map(|k| format!("keys of type {} not supported", k.itype))
+    };
+    if check.skipped.is_some() {
+        return Ok(check);
+    }
+
+    let mut ptable = table.prepare()?;
+
+    let mut pending: HashMap<u64, Vec<PendingEntry>> = HashMap::new();
+    for entry in index.entries()? {
+        let entry = entry?;
+        check.entries += 1;
+
+        pending
+            .entry(entry.record_number)
+            .or_default()
+            .push(PendingEntry {
+                segments: index.key_segments(&entry.key)?,
+                key: entry.key,
+                matched: false,
+            });
+    }
+
+    let mut rows = HashMap::new();
+    while let Some(row) = ptable.read()? {
+        check.rows += 1;
+
+        let db_key = match row.db_key {
+            Some(db_key) => db_key,
+            None => continue,
+        };
+
+        let key = index.row_key(&row, &ptable.columns)?;
+        let found = pending
+            .get_mut(&db_key.record_number)
+            .and_then(|entries| {
+                entries
+                    .iter_mut()
+                    .find(|e| !e.matched && compare_segments(&e.segments, &key).is_eq())
+            })
+            .map(|e| e.matched = true)
+            .is_some();
+
+        let values = index.row_values(&row, &ptable.columns)?;
+        rows.insert(db_key.record_number, (db_key, values, found));
+    }
+
+    let mut numbers: Vec<u64> = pending.keys().copied().collect();
+    numbers.sort_unstable();
+
+    for number in numbers {
+        let row = rows.get_mut(&number);
+
+        let unmatched: Vec<&PendingEntry> =
+            pending[&number].iter().filter(|e| !e.matched).collect();
+        if unmatched.is_empty() {
+            continue;
+        }
+
+        // Old versions keep their entries until the garbage collection
+        let rec = ptable.record(number)?;
+        if rec
+            .as_ref()
+            .is_some_and(|rec| rec.b_page != 0 || rec.flags & RHD_DELETED != 0)
+        {
+            check.garbage += unmatched.len();
+            continue;
+        }
+
+        for entry in unmatched {
+            let entry_key = index.decode_key(&entry.key, &ptable.columns)?;
+
+            check.issues.push(match &row {
+                Some((db_key, values, _)) => IndexIssue::KeyMismatch {
+                    db_key: *db_key,
+                    entry: entry_key,
+                    row: values.clone(),
+                },
+                None => IndexIssue::DanglingEntry {
+                    record_number: number,
+                    key: entry_key,
+                },
+            });
+        }
+
+        // The mismatch already reports the row
+        if let Some(row) = row {
+            row.2 = true;
+        }
+    }
+
+    let mut rows: Vec<_> = rows.into_values().filter(|r| !r.2).collect();
+    rows.sort_by_key(|r| r.0.record_number);
+
+    for (db_key, key, _) in rows {
+        check.issues.push(IndexIssue::MissingEntry { db_key, key });
+    }
+
+    Ok(check)
+}
@@ -3,19 +3,88 @@
 use num_enum::TryFromPrimitive;
 use std::fmt;
 
+use crate::charset::*;
+
 /// Column definion
 #[derive(Debug, PartialEq, Clone)]
 pub struct Column {
     pub name: String,
+    /// Field id, the order of the column on the records
+    pub id: usize,
     pub position: usize,
     pub source: String,
     pub size: usize,
     pub scale: i16,
     pub tp: ColumnType,
+    /// Sub-type of the column, 1 for text blobs
+    pub sub_type: i16,
+    /// Character set of text and text blobs
+    pub charset: Charset,
+    /// Size in characters of the text columns, smaller than the
+    /// size in bytes for multi-byte charsets
+    pub char_length: usize,
+    /// Lower and upper bounds of each dimension, empty when
+    /// the column isn't an array
+    pub dimensions: Vec<(i32, i32)>,
     pub not_null: bool,
     pub computed: bool,
 }
 
+impl Column {
+    /// Amount of bytes used by the column on the record
+    pub fn storage_size(&self) -> usize {
+        if self.is_array() {
+            // Arrays are stored as blobs
+            return 8;
+        }
+
+        self.element_size()
+    }
+
+    /// Amount of bytes used by a single value of the column type,
+    /// the same of the array elements
+    pub fn element_size(&self) -> usize {
+        match self.tp {
+            // 2 bytes for varying size info + regular field size
+            ColumnType::Varchar => self.size + 2,
+            _ => self.size,
+        }
+    }
+
+    /// Check if the column is a blob with text content
+    pub fn is_text_blob(&self) -> bool {
+        self.tp == ColumnType::Blob && self.sub_type == 1 && self.charset != Charset::Octets
+    }
+
+    /// Check if the column is a CHAR or VARCHAR
+    pub fn is_text(&self) -> bool {
+        matches!(self.tp, ColumnType::Char | ColumnType::Varchar)
+    }
+
+    /// Check if the column is an array of its type
+    pub fn is_array(&self) -> bool {
+        !self.dimensions.is_empty()
+    }
+
+    /// Blob holding BLR, like the RDB$VIEW_BLR
+    pub fn is_blr_blob(&self) -> bool {
+        self.tp == ColumnType::Blob && self.sub_type == 2
+    }
+
+    /// Alignment of the column on the record
+    pub fn alignment(&self) -> usize {
+        if self.is_array() {
+            return 8;
+        }
+
+        match self.tp {
+            ColumnType::Char => 1,
+            ColumnType::Varchar => 2,
+            _ => self.size.clamp(1, 8),
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, TryFromPrimitive, Clone)]
 #[repr(i16)]
 pub enum ColumnType {
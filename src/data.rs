@@ -1,11 +1,10 @@
 //! Firebird data page representation
 
-use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
-use std::ptr;
+use byteorder::{ByteOrder, LittleEndian};
 
 use crate::page::*;
 use crate::record::*;
+use crate::source::{PageBytes, PageSource};
 use crate::Error;
 
 /// Data Page
@@ -28,19 +27,13 @@ pub struct DataPage {
     pub count: u16,
     /// Counts upwards from the low address to the higher address as each new record fragment is added.
     pub records: Vec<DataPageRecord>,
-    /// Raw content of this page
-    pub raw: Vec<u8>,
+    /// Raw content of this page, borrowed from the database when
+    /// kept on memory
+    pub raw: PageBytes,
 }
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-struct DataPageRep {
-    pub pag: Page,
-    pub sequence: u32,
-    pub relation: u16,
-    pub count: u16,
-    pub records: [DataPageRecord; 512], // TODO: fix this hardcoded array size
-}
+/// Size of the data page header with its first record index entry
+const DPG_SIZE: usize = 28;
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -53,33 +46,50 @@ pub struct DataPageRecord {
 
 impl DataPage {
     /// Parse the DataPage from bytes
-    pub fn from_bytes(bytes: Vec<u8>) -> Result<DataPage, Error> {
-        if bytes[0] != 0x05 {
+    pub fn from_bytes<B: Into<PageBytes>>(bytes: B) -> Result<DataPage, Error> {
+        let bytes = bytes.into();
+
+        // The bytes can be borrowed from any memory, so the
+        // header is read field by field
+        let pag = Page::from_bytes(&bytes)?;
+        if pag.ptype != 0x05 {
             return Err(Error::InvalidPage {
-                tpe: bytes[0],
+                tpe: pag.ptype,
                 expected: 0x05,
                 desc: "data".to_string(),
             });
         }
+        if bytes.len() < DPG_SIZE - 4 {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: DPG_SIZE - 4,
+                msg: "data page header".to_string(),
+            });
+        }
 
-        let rdata: DataPageRep = unsafe { ptr::read(bytes.as_ptr() as *const _) };
-
-        if rdata.count > 512 {
+        let count = LittleEndian::read_u16(&bytes[22..24]);
+        let end = DPG_SIZE - 4 + count as usize * 4;
+        if end > bytes.len() {
             return Err(Error::Overflow {
-                limit: 512,
-                value: rdata.count as usize,
-                msg: "supported records".to_string(),
+                limit: (bytes.len() - (DPG_SIZE - 4)) / 4,
+                value: count as usize,
+                msg: "records of the data page".to_string(),
             });
         }
 
-        let mut records = rdata.records.to_vec();
-        records.truncate(rdata.count as usize);
+        let records = bytes[DPG_SIZE - 4..end]
+            .chunks_exact(4)
+            .map(|idx| DataPageRecord {
+                offset: LittleEndian::read_u16(&idx[0..2]),
+                length: LittleEndian::read_u16(&idx[2..4]),
+            })
+            .collect();
 
         let data = DataPage {
-            pag: rdata.pag,
-            sequence: rdata.sequence,
-            relation: rdata.relation,
-            count: rdata.count,
+            pag,
+            sequence: LittleEndian::read_u32(&bytes[16..20]),
+            relation: LittleEndian::read_u16(&bytes[20..22]),
+            count,
             records,
             raw: bytes,
         };
@@ -87,37 +97,56 @@ impl DataPage {
         Ok(data)
     }
 
+    /// Max amount of records that a data page can hold
+    pub fn max_records(page_size: u16) -> usize {
+        // Every record needs at least an entry on the page
+        // index plus its own header
+        (page_size as usize - DPG_SIZE) / (4 + RHD_SIZE)
+    }
+
+    /// Unused bytes of the page, without the record index entries
+    /// and the aligned record fragments
+    pub fn free_space(&self) -> usize {
+        let used = self
+            .records
+            .iter()
+            .map(|r| (r.length as usize + 3) & !3)
+            .sum::<usize>();
+
+        self.raw
+            .len()
+            .saturating_sub(DPG_SIZE - 4 + self.records.len() * 4 + used)
+    }
+
     /// Load all data pages of buffer
-    pub fn load(header: HeaderPage, buffer: &mut BufReader<File>) -> Result<Vec<DataPage>, Error> {
-        let mut pgt = [0 as u8; 1];
+    pub fn load(header: HeaderPage, buffer: &mut dyn PageSource) -> Result<Vec<DataPage>, Error> {
+        let count = buffer.size()? / header.page_size as u64;
         let mut pages = vec![];
 
-        while buffer.read(&mut pgt)? > 0 {
-            // We only need the Data Page — Type 0x05
-            if pgt[0] == 0x05 {
-                buffer.seek_relative(-1)?;
+        // Skip the header page
+        for pageno in 1..count as u32 {
+            let bytes = buffer.page_bytes(header.page_size, pageno)?;
 
-                let mut poip = vec![0 as u8; header.page_size.into()];
-                buffer.read_exact(&mut poip)?;
-
-                let data = DataPage::from_bytes(poip.clone())?;
+            // We only need the Data Page — Type 0x05
+            if bytes[0] == 0x05 {
+                let data = DataPage::from_bytes(bytes)?;
                 pages.push(data);
-            } else {
-                buffer.consume((header.page_size - 1).into());
             }
         }
 
         Ok(pages)
     }
 
-    /// Read all records of this data page
-    pub fn get_records(&self) -> Result<Vec<RecordHeader>, Error> {
+    /// Read all the current records of this data page
+    pub fn get_records(&self) -> Result<Vec<RecordHeader<'_>>, Error> {
         let mut records = vec![];
 
-        for idx in self.records.clone() {
-            let orec = self.get_record(idx)?;
+        for idx in self.records.iter() {
+            let orec = self.get_record(*idx)?;
             if let Some(rec) = orec {
-                records.push(rec);
+                if rec.is_primary() {
+                    records.push(rec);
+                }
             }
         }
 
@@ -125,15 +154,29 @@ impl DataPage {
     }
 
     /// Read a specific record
-    pub fn get_record(&self, idx: DataPageRecord) -> Result<Option<RecordHeader>, Error> {
+    pub fn get_record(&self, idx: DataPageRecord) -> Result<Option<RecordHeader<'_>>, Error> {
         if idx.length == 0 {
             return Ok(None);
         }
 
-        let rpoip = self.raw[idx.offset as usize..(idx.offset + idx.length) as usize].to_vec();
-
-        let rec = RecordHeader::from_bytes(rpoip)?;
+        let rec = RecordHeader::from_bytes(self.get_record_raw(idx)?)?;
 
         Ok(Some(rec))
     }
+
+    /// Raw bytes of a specific record, including its header
+    pub fn get_record_raw(&self, idx: DataPageRecord) -> Result<&[u8], Error> {
+        let start = idx.offset as usize;
+        let end = start + idx.length as usize;
+
+        if end > self.raw.len() {
+            return Err(Error::Overflow {
+                limit: self.raw.len(),
+                value: end,
+                msg: "record end on the data page".to_string(),
+            });
+        }
+
+        Ok(&self.raw[start..end])
+    }
 }
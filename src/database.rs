@@ -1,46 +1,347 @@
 //! Firebird database representation
 
-use std::cell::RefCell;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
-use std::rc::Rc;
+use std::io::{BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
 
-use crate::page::HeaderPage;
+use crate::charset::Charset;
+use crate::data::DataPage;
+use crate::file::DatabaseFile;
+use crate::generator::Generator;
+use crate::page::*;
+use crate::pip::*;
+use crate::scn::ScnPage;
+use crate::shadow::*;
+use crate::source::*;
 use crate::Error;
 use crate::Table;
 
 /// The Firebird database
 pub struct Database {
     pub header: HeaderPage,
-    buffer: Rc<RefCell<BufReader<File>>>,
+    /// Variable data of the header page
+    pub clumplets: Vec<HeaderClumplet>,
+    buffer: Source,
+    none_charset: Charset,
 }
 
 impl Database {
-    /// Read the database from a buffer
-    pub fn open(buffer: Rc<RefCell<BufReader<File>>>) -> Result<Database, Error> {
+    /// Read the database from a source of pages
+    pub fn open<S: PageSource + 'static>(source: S) -> Result<Database, Error> {
+        let buffer = SharedSource::new(source);
+
+        let mut page = vec![0u8; 1024];
+        buffer.lock().read_exact(&mut page)?;
+
         let header = {
-            let mut tag = [0 as u8; 1024];
-            buffer.borrow_mut().read_exact(&mut tag)?;
+            let mut tag = [0u8; 1024];
+            tag.copy_from_slice(&page);
 
             HeaderPage::from_bytes(tag)?
         };
 
-        buffer
-            .borrow_mut()
-            .consume((header.page_size - 1024).into());
+        page.resize(header.page_size.into(), 0);
+        buffer.lock().read_exact(&mut page[1024..])?;
+        let clumplets = HeaderClumplet::parse(&page)?;
 
-        Ok(Self { header, buffer })
+        Ok(Self {
+            header,
+            clumplets,
+            buffer,
+            none_charset: Charset::None,
+        })
     }
 
-    /// Read the database from a file with RO mode
+    /// Read the database from a file with RO mode, the secondary
+    /// files not found are looked for on the directory of `fpath`
     pub fn open_file(fpath: &str) -> Result<Database, Error> {
-        let f = File::open(fpath).map_err(|e| e.to_string())?;
+        let f = DatabaseFile::open(fpath)?;
+        let bfr = BufReader::new(f);
+
+        Database::open(bfr)
+    }
+
+    /// Read the database from a file with RO mode, with the paths of
+    /// the secondary files translated by `remap`
+    pub fn open_file_with<F>(fpath: &str, remap: F) -> Result<Database, Error>
+    where
+        F: Fn(&str) -> PathBuf,
+    {
+        let f = DatabaseFile::open_with(fpath, remap)?;
+        let bfr = BufReader::new(f);
+
+        Database::open(bfr)
+    }
+
+    /// Read the database restored from a chain of nbackup files, the
+    /// backup of level 0 followed by the incremental backups
+    pub fn open_backup_chain(fpaths: &[&str]) -> Result<Database, Error> {
+        let f = DatabaseFile::open_backup_chain(fpaths)?;
         let bfr = BufReader::new(f);
 
-        Database::open(Rc::new(RefCell::new(bfr)))
+        Database::open(bfr)
+    }
+
+    /// Read the database from its bytes on memory, the data pages
+    /// are borrowed from them
+    pub fn open_bytes<T: AsRef<[u8]> + Send + Sync + 'static>(bytes: T) -> Result<Database, Error> {
+        Database::open(MemorySource::new(bytes))
+    }
+
+    /// Read the database from any reader, like an entry of an
+    /// archive or a decrypted stream
+    pub fn open_reader<R: Read + Seek + Send + 'static>(reader: R) -> Result<Database, Error> {
+        Database::open(BufReader::new(ReaderSource(reader)))
+    }
+
+    /// Read the database from a memory mapped file, without its
+    /// secondary files, with the data pages borrowed from the mapping
+    #[cfg(feature = "mmap")]
+    pub fn open_mmap(fpath: &str) -> Result<Database, Error> {
+        let f = std::fs::File::open(fpath)?;
+        // Safety: the file is only read, changes made by another
+        // process are seen as on the reads of the file
+        let map = unsafe { memmap2::Mmap::map(&f)? };
+
+        Database::open_bytes(map)
+    }
+
+    /// Open a shadow file of a database, failing when its header
+    /// isn't flagged as shadow
+    pub fn open_shadow(fpath: &str) -> Result<Database, Error> {
+        let db = Database::open_file(fpath)?;
+        if !db.header.is_shadow() {
+            return Err(Error::Other(format!("{}: not a shadow file", fpath)));
+        }
+
+        Ok(db)
     }
 
-    pub fn tables(&mut self) -> Result<Vec<Table>, Error> {
-        Table::load(self.header, &mut self.buffer.borrow_mut())
+    /// Name of the database copied by this shadow, from the header
+    pub fn shadowed_file(&self) -> Option<&str> {
+        if !self.header.is_shadow() {
+            return None;
+        }
+
+        self.clumplets.iter().find_map(|c| match c {
+            HeaderClumplet::RootFileName(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Database pages read from the nbackup delta file
+    pub fn delta_pages(&self) -> Vec<u32> {
+        self.buffer
+            .lock()
+            .delta()
+            .map(|delta| delta.changed_pages())
+            .unwrap_or_default()
+    }
+
+    /// Paths of the files of the database, starting by the primary
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.buffer
+            .lock()
+            .files()
+            .into_iter()
+            .map(Path::to_path_buf)
+            .collect()
+    }
+
+    /// Amount of pages kept on the cache shared by the database, its
+    /// tables and indexes, 1024 by default and 0 disables it
+    pub fn set_cache_size(&self, pages: usize) {
+        self.buffer.set_cache_size(pages);
+    }
+
+    /// Charset used to decode the text of the columns declared
+    /// with NONE, which have no information about its encoding
+    pub fn set_none_charset(&mut self, charset: Charset) {
+        self.none_charset = charset;
+    }
+
+    pub fn tables(&self) -> Result<Vec<Table>, Error> {
+        Table::load(self.header, self.buffer.clone(), self.none_charset)
+    }
+
+    /// Run a read-only SELECT on the tables of the database
+    #[cfg(feature = "sql")]
+    pub fn query(&self, sql: &str) -> Result<crate::QueryResult, Error> {
+        crate::query(&self.tables()?, sql)
+    }
+
+    /// Shadows declared on the database
+    pub fn shadows(&self) -> Result<Vec<Shadow>, Error> {
+        Shadow::load(self.tables()?)
+    }
+
+    /// Pages differing between the database and its shadow
+    ///
+    /// The header pages are left out, the one of the shadow has
+    /// its own flags and clumplets.
+    pub fn compare_shadow(&self, shadow: &Database) -> Result<Vec<PageDifference>, Error> {
+        let page_size = self.header.page_size;
+        if shadow.header.page_size != page_size {
+            return Err(Error::Other(format!(
+                "Shadow with page size {}, expected {}",
+                shadow.header.page_size, page_size
+            )));
+        }
+
+        let count = self.page_count()? as u32;
+        let shadow_count = shadow.page_count()? as u32;
+        let mut differences = vec![];
+
+        for pageno in 1..count.max(shadow_count) {
+            let page = if pageno < count {
+                Some(self.buffer.lock().page_bytes(page_size, pageno)?.into_vec())
+            } else {
+                None
+            };
+            let shadow_page = if pageno < shadow_count {
+                Some(
+                    shadow
+                        .buffer
+                        .lock()
+                        .page_bytes(page_size, pageno)?
+                        .into_vec(),
+                )
+            } else {
+                None
+            };
+
+            if page != shadow_page {
+                let page_type =
+                    |page: Option<Vec<u8>>| page.map(|p| PageType::try_from(p[0])).transpose();
+
+                differences.push(PageDifference {
+                    pageno,
+                    database: page_type(page)?,
+                    shadow: page_type(shadow_page)?,
+                });
+            }
+        }
+
+        Ok(differences)
+    }
+
+    /// Load the generators with their current values
+    pub fn generators(&self) -> Result<Vec<Generator>, Error> {
+        Generator::load(self.header, self.buffer.clone())
+    }
+
+    /// Read and parse a page of the database, whatever its type
+    pub fn page(&self, pageno: u32) -> Result<AnyPage, Error> {
+        let bytes = self.buffer.page(self.header.page_size, pageno)?;
+
+        // The data pages can keep the bytes borrowed
+        if bytes[0] == 0x05 {
+            return Ok(AnyPage::Data(DataPage::from_bytes(bytes)?));
+        }
+
+        AnyPage::from_bytes(bytes.into_vec())
+    }
+
+    /// Amount of pages of the database files
+    pub fn page_count(&self) -> Result<usize, Error> {
+        let len = self.buffer.lock().size()?;
+
+        Ok((len / self.header.page_size as u64) as usize)
+    }
+
+    /// Allocation state of every page of the database
+    pub fn page_allocation(&self) -> Result<Vec<PageAllocation>, Error> {
+        let page_size = self.header.page_size;
+        let per_pip = PageInventoryPage::pages_per_pip(page_size);
+        let count = self.page_count()?;
+
+        let mut pages = Vec::with_capacity(count);
+        let mut sequence = 0;
+
+        while pages.len() < count {
+            let pageno = PageInventoryPage::pip_page(page_size, sequence);
+            let bytes = read_page(&self.buffer, page_size, pageno)?;
+            let pip = PageInventoryPage::from_bytes(bytes)?;
+
+            let first = sequence as usize * per_pip;
+            for slot in 0..per_pip.min(count - first) {
+                pages.push(PageAllocation {
+                    pageno: (first + slot) as u32,
+                    is_free: pip.is_free(slot),
+                    is_extent_full: pip.is_extent_full(slot),
+                });
+            }
+
+            sequence += 1;
+        }
+
+        Ok(pages)
+    }
+
+    /// SCN of every page of the database, from the SCN pages
+    pub fn page_scns(&self) -> Result<Vec<u32>, Error> {
+        let page_size = self.header.page_size;
+        let count = self.page_count()?;
+
+        let mut scns = Vec::with_capacity(count);
+        let mut sequence = 0;
+
+        while scns.len() < count {
+            let pageno = ScnPage::scn_page(page_size, sequence);
+            let bytes = read_page(&self.buffer, page_size, pageno)?;
+            let scn = ScnPage::from_bytes(bytes)?;
+
+            let left = count - scns.len();
+            scns.extend(scn.scns.into_iter().take(left));
+
+            sequence += 1;
+        }
+
+        Ok(scns)
+    }
+
+    /// Pages changed after the given SCN
+    pub fn changed_pages(&self, since: u32) -> Result<Vec<u32>, Error> {
+        let pages = self
+            .page_scns()?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, scn)| *scn > since)
+            .map(|(pageno, _)| pageno as u32)
+            .collect();
+
+        Ok(pages)
+    }
+
+    /// Free pages and fill levels of the data pages
+    pub fn space_usage(&self) -> Result<SpaceUsage, Error> {
+        let page_size = self.header.page_size;
+        let mut usage = SpaceUsage {
+            page_size,
+            ..Default::default()
+        };
+
+        for page in self.page_allocation()? {
+            usage.pages += 1;
+
+            if page.is_free {
+                usage.free_pages += 1;
+                continue;
+            }
+
+            // Every page is read, better to leave the cache out
+            let bytes = self.buffer.lock().page_bytes(page_size, page.pageno)?;
+            if bytes[0] != 0x05 {
+                continue;
+            }
+
+            let free = DataPage::from_bytes(bytes)?.free_space();
+            let fill = (page_size as usize - free) * 100 / page_size as usize;
+
+            usage.data_pages += 1;
+            usage.data_free_space += free;
+            usage.fill_distribution[(fill / 20).min(4)] += 1;
+        }
+
+        Ok(usage)
     }
 }
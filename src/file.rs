@@ -0,0 +1,354 @@
+//! Firebird database files, presented as a single page space
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::nbackup::*;
+use crate::page::*;
+use crate::Error;
+
+/// Physical file holding a range of the database
+#[derive(Debug)]
+struct FilePart {
+    file: File,
+    path: PathBuf,
+    /// First logical byte of the range
+    start: u64,
+    /// End of the range, None for the last file
+    end: Option<u64>,
+    /// Position of the range on the file, the secondary files
+    /// start with its own header page
+    offset: u64,
+}
+
+/// Database stored on one or more files
+///
+/// The header page of each file names the next file and the last
+/// page stored on the current one. The pages of all the files are
+/// read as a single contiguous space, with the pages changed on the
+/// delta file when locked by nbackup, or on the incremental backups
+/// when reading a chain of nbackup files.
+#[derive(Debug)]
+pub struct DatabaseFile {
+    parts: Vec<FilePart>,
+    delta: Option<DeltaFile>,
+    /// Incremental backups applied, from the level 1
+    increments: Vec<IncrementalBackup>,
+    pos: u64,
+}
+
+impl DatabaseFile {
+    /// Open the database and its secondary files, looking for them
+    /// on the directory of the first file when moved
+    pub fn open<P: AsRef<Path>>(fpath: P) -> Result<DatabaseFile, Error> {
+        let dir = fpath
+            .as_ref()
+            .parent()
+            .map(|d| d.to_path_buf())
+            .unwrap_or_default();
+
+        Self::open_with(fpath, |name| {
+            let path = PathBuf::from(name);
+            match path.file_name() {
+                Some(fname) if !path.exists() => dir.join(fname),
+                _ => path,
+            }
+        })
+    }
+
+    /// Open the database and its secondary files, with the names
+    /// found on the headers translated by `remap`. The delta file of
+    /// a database locked by nbackup is named on the header or, by
+    /// default, the primary file name with the `.delta` suffix.
+    pub fn open_with<P, F>(fpath: P, remap: F) -> Result<DatabaseFile, Error>
+    where
+        P: AsRef<Path>,
+        F: Fn(&str) -> PathBuf,
+    {
+        let mut parts: Vec<FilePart> = vec![];
+        let mut path = fpath.as_ref().to_path_buf();
+        let mut start = 0;
+        let mut delta = None;
+
+        loop {
+            let mut file = File::open(&path)
+                .map_err(|e| Error::Other(format!("{}: {}", path.display(), e)))?;
+
+            let mut tag = [0u8; 1024];
+            file.read_exact(&mut tag)?;
+            let header = HeaderPage::from_bytes(tag)?;
+
+            if header.sequence as usize != parts.len() {
+                return Err(Error::Other(format!(
+                    "{}: expected the file {} of the database, found {}",
+                    path.display(),
+                    parts.len(),
+                    header.sequence
+                )));
+            }
+
+            let page_size = header.page_size as u64;
+            let mut page = vec![0u8; page_size as usize];
+            file.seek(SeekFrom::Start(0))?;
+            file.read_exact(&mut page)?;
+
+            let mut next = None;
+            let mut last = None;
+            let mut difference = None;
+            for clumplet in HeaderClumplet::parse(&page)? {
+                match clumplet {
+                    HeaderClumplet::File(name) => next = Some(name),
+                    HeaderClumplet::LastPage(pageno) => last = Some(pageno as u64),
+                    HeaderClumplet::DifferenceFile(name) => difference = Some(name),
+                    _ => {}
+                }
+            }
+
+            if parts.is_empty() && header.backup_state() != BackupState::Normal {
+                let dpath = match difference {
+                    Some(name) => remap(&name),
+                    None => {
+                        let mut name = path.clone().into_os_string();
+                        name.push(".delta");
+                        PathBuf::from(name)
+                    }
+                };
+                delta = Some(DeltaFile::open(dpath, header.page_size)?);
+            }
+
+            let offset = if parts.is_empty() { 0 } else { page_size };
+            let end = match (&next, last) {
+                (Some(_), Some(last)) => Some((last + 1) * page_size),
+                _ => None,
+            };
+
+            parts.push(FilePart {
+                file,
+                path,
+                start,
+                end,
+                offset,
+            });
+
+            match (next, end) {
+                (Some(name), Some(end)) => {
+                    path = remap(&name);
+                    start = end;
+                }
+                _ => break,
+            }
+
+            if parts.iter().any(|p| p.path == path) {
+                return Err(Error::Other(format!(
+                    "{}: the database files form a cycle",
+                    path.display()
+                )));
+            }
+        }
+
+        Ok(DatabaseFile {
+            parts,
+            delta,
+            increments: vec![],
+            pos: 0,
+        })
+    }
+
+    /// Open a chain of nbackup files, the backup of level 0 and the
+    /// incremental backups over it, in any order. Each backup must
+    /// follow the one of the previous level, by their ids.
+    pub fn open_backup_chain<P: AsRef<Path>>(fpaths: &[P]) -> Result<DatabaseFile, Error> {
+        let mut base = None;
+        let mut increments = vec![];
+
+        for fpath in fpaths {
+            let fpath = fpath.as_ref();
+            if IncrementalBackup::is_incremental(fpath)? {
+                increments.push(IncrementalBackup::open(fpath)?);
+            } else if base.replace(fpath).is_some() {
+                return Err(Error::from("More than one backup of level 0"));
+            }
+        }
+
+        let base = base.ok_or_else(|| Error::from("Backup of level 0 not found"))?;
+        let mut dbfile = Self::open(base)?;
+
+        let mut page = vec![0u8; 1024];
+        dbfile.read_exact(&mut page)?;
+        let mut tag = [0u8; 1024];
+        tag.copy_from_slice(&page);
+        let header = HeaderPage::from_bytes(tag)?;
+
+        page.resize(header.page_size.into(), 0);
+        dbfile.read_exact(&mut page[1024..])?;
+        dbfile.pos = 0;
+
+        let mut guid = HeaderClumplet::parse(&page)?
+            .into_iter()
+            .find_map(|c| match c {
+                HeaderClumplet::BackupGuid(guid) => Some(guid),
+                _ => None,
+            });
+
+        increments.sort_by_key(|inc| inc.level);
+        for (i, inc) in increments.iter().enumerate() {
+            if inc.level as usize != i + 1 {
+                return Err(Error::Other(format!(
+                    "Expected the backup of level {}, found {}",
+                    i + 1,
+                    inc.level
+                )));
+            }
+            if Some(inc.prev_guid) != guid {
+                return Err(Error::Other(format!(
+                    "Backup of level {} doesn't follow the backup of level {}",
+                    inc.level, i
+                )));
+            }
+            if inc.page_size != header.page_size as u32 {
+                return Err(Error::Other(format!(
+                    "Backup of level {} with page size {}, expected {}",
+                    inc.level, inc.page_size, header.page_size
+                )));
+            }
+
+            guid = Some(inc.backup_guid);
+        }
+
+        dbfile.increments = increments;
+
+        Ok(dbfile)
+    }
+
+    /// Paths of the files of the database, starting by the primary
+    pub fn files(&self) -> Vec<&Path> {
+        self.parts.iter().map(|p| p.path.as_path()).collect()
+    }
+
+    /// Delta file overlaid on the database files
+    pub fn delta(&self) -> Option<&DeltaFile> {
+        self.delta.as_ref()
+    }
+
+    /// Size of the whole database, in bytes
+    pub fn len(&self) -> Result<u64, Error> {
+        let last = &self.parts[self.parts.len() - 1];
+        let flen = last.file.metadata()?.len();
+        let len = last.start + flen.saturating_sub(last.offset);
+
+        // Pages allocated later only exist on the delta file or on
+        // the incremental backups
+        let delta_len = self.delta.as_ref().map_or(0, |delta| {
+            let last = delta.changed_pages().last().map_or(0, |p| *p as u64 + 1);
+            last * delta.page_size() as u64
+        });
+        let increments_len = self.increments.iter().fold(0, |len, inc| {
+            let last = inc.changed_pages().last().map_or(0, |p| *p as u64 + 1);
+            len.max(last * inc.page_size as u64)
+        });
+
+        Ok(len.max(delta_len).max(increments_len))
+    }
+
+    /// Page size of the files overlaid on the database files
+    fn overlay_page_size(&self) -> Option<u64> {
+        match (&self.delta, self.increments.first()) {
+            (Some(delta), _) => Some(delta.page_size() as u64),
+            (None, Some(inc)) => Some(inc.page_size as u64),
+            (None, None) => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        Ok(self.len()? == 0)
+    }
+}
+
+impl From<File> for DatabaseFile {
+    fn from(file: File) -> Self {
+        DatabaseFile {
+            parts: vec![FilePart {
+                file,
+                path: PathBuf::new(),
+                start: 0,
+                end: None,
+                offset: 0,
+            }],
+            delta: None,
+            increments: vec![],
+            pos: 0,
+        }
+    }
+}
+
+impl Read for DatabaseFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let pos = self.pos;
+        let mut buf = buf;
+
+        if let Some(page_size) = self.overlay_page_size() {
+            let pageno = (pos / page_size) as u32;
+            let offset = (pos % page_size) as usize;
+
+            // The newest version of the page comes first
+            for inc in self.increments.iter_mut().rev() {
+                if let Some(size) = inc.read_at(pageno, offset, buf)? {
+                    self.pos += size as u64;
+                    return Ok(size);
+                }
+            }
+
+            if let Some(delta) = self.delta.as_mut() {
+                if let Some(size) = delta.read_at(pageno, offset, buf)? {
+                    self.pos += size as u64;
+                    return Ok(size);
+                }
+            }
+
+            // Stop at the end of the page, the next one may be changed
+            let max = buf.len().min(page_size as usize - offset);
+            buf = &mut buf[..max];
+        }
+
+        let part = match self
+            .parts
+            .iter_mut()
+            .find(|p| p.end.is_none_or(|end| pos < end))
+        {
+            Some(part) => part,
+            None => return Ok(0),
+        };
+
+        let max = match part.end {
+            Some(end) => buf.len().min((end - pos) as usize),
+            None => buf.len(),
+        };
+
+        part.file
+            .seek(SeekFrom::Start(part.offset + pos - part.start))?;
+        let size = part.file.read(&mut buf[..max])?;
+        self.pos += size as u64;
+
+        Ok(size)
+    }
+}
+
+impl Seek for DatabaseFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+            SeekFrom::End(n) => {
+                let len = self.len().map_err(|e| io::Error::other(e.to_string()))?;
+                len.checked_add_signed(n)
+            }
+        };
+
+        self.pos = new.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative position")
+        })?;
+
+        Ok(self.pos)
+    }
+}
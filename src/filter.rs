@@ -0,0 +1,867 @@
+//! Filters of the rows, checked while reading the tables
+
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::ops::Not;
+use std::str::{Chars, FromStr};
+
+use crate::row::*;
+use crate::Error;
+
+/// Condition on the values of the columns of a row
+///
+/// Built from [`Filter::column`], or parsed from an expression like
+/// `JOB_COUNTRY = 'USA' AND SALARY > 50000`. The comparisons with
+/// null are unknown, like on SQL, and the rows are kept only when
+/// the whole condition is true.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Compare(String, Operator, Value),
+    In(String, Vec<Value>),
+    /// Pattern with `%` for any text and `_` for any character
+    Like(String, String),
+    StartingWith(String, String),
+    IsNull(String),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// Column of a filter being built
+pub struct FilterColumn(String);
+
+impl Filter {
+    /// Start a condition on the column
+    pub fn column(name: &str) -> FilterColumn {
+        FilterColumn(name.trim().to_string())
+    }
+
+    pub fn and(self, other: Filter) -> Filter {
+        Filter::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Filter) -> Filter {
+        Filter::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Names of the columns used by the filter
+    pub fn columns(&self) -> Vec<&str> {
+        match self {
+            Filter::Compare(name, ..)
+            | Filter::In(name, _)
+            | Filter::Like(name, _)
+            | Filter::StartingWith(name, _)
+            | Filter::IsNull(name) => vec![name.as_str()],
+            Filter::And(left, right) | Filter::Or(left, right) => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+            Filter::Not(filter) => filter.columns(),
+        }
+    }
+
+    /// Check the filter with the values of the columns given by
+    /// `value`, asked only when needed
+    ///
+    /// None is the unknown result of the comparisons with null.
+    pub(crate) fn eval<F>(&self, value: &mut F) -> Result<Option<bool>, Error>
+    where
+        F: FnMut(&str) -> Result<Option<Value>, Error> + ?Sized,
+    {
+        let result = match self {
+            Filter::Compare(name, op, expected) => match value(name)? {
+                Some(val) => Some(op.matches(compare_values(&val, expected)?)),
+                None => None,
+            },
+            Filter::In(name, expected) => match value(name)? {
+                Some(val) => {
+                    let mut found = false;
+                    for expected in expected {
+                        if compare_values(&val, expected)? == Ordering::Equal {
+                            found = true;
+                            break;
+                        }
+                    }
+                    Some(found)
+                }
+                None => None,
+            },
+            Filter::Like(name, pattern) => {
+                value(name)?.map(|val| like(text(&val).trim_end_matches(' '), pattern))
+            }
+            Filter::StartingWith(name, prefix) => {
+                value(name)?.map(|val| text(&val).starts_with(prefix.as_str()))
+            }
+            Filter::IsNull(name) => Some(value(name)?.is_none()),
+            Filter::And(left, right) => match left.eval(value)? {
+                Some(false) => Some(false),
+                left => match right.eval(value)? {
+                    Some(false) => Some(false),
+                    Some(true) => left,
+                    None => None,
+                },
+            },
+            Filter::Or(left, right) => match left.eval(value)? {
+                Some(true) => Some(true),
+                left => match right.eval(value)? {
+                    Some(true) => Some(true),
+                    Some(false) => left,
+                    None => None,
+                },
+            },
+            Filter::Not(filter) => filter.eval(value)?.map(|r| !r),
+        };
+
+        Ok(result)
+    }
+}
+
+impl Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter::Not(Box::new(self))
+    }
+}
+
+impl FilterColumn {
+    fn compare<V: Into<Value>>(self, op: Operator, value: V) -> Filter {
+        Filter::Compare(self.0, op, value.into())
+    }
+
+    pub fn eq<V: Into<Value>>(self, value: V) -> Filter {
+        self.compare(Operator::Equal, value)
+    }
+
+    pub fn ne<V: Into<Value>>(self, value: V) -> Filter {
+        self.compare(Operator::NotEqual, value)
+    }
+
+    pub fn lt<V: Into<Value>>(self, value: V) -> Filter {
+        self.compare(Operator::Less, value)
+    }
+
+    pub fn le<V: Into<Value>>(self, value: V) -> Filter {
+        self.compare(Operator::LessOrEqual, value)
+    }
+
+    pub fn gt<V: Into<Value>>(self, value: V) -> Filter {
+        self.compare(Operator::Greater, value)
+    }
+
+    pub fn ge<V: Into<Value>>(self, value: V) -> Filter {
+        self.compare(Operator::GreaterOrEqual, value)
+    }
+
+    pub fn is_in<V: Into<Value>, I: IntoIterator<Item = V>>(self, values: I) -> Filter {
+        Filter::In(self.0, values.into_iter().map(Into::into).collect())
+    }
+
+    pub fn like(self, pattern: &str) -> Filter {
+        Filter::Like(self.0, pattern.to_string())
+    }
+
+    pub fn starting_with(self, prefix: &str) -> Filter {
+        Filter::StartingWith(self.0, prefix.to_string())
+    }
+
+    pub fn is_null(self) -> Filter {
+        Filter::IsNull(self.0)
+    }
+
+    pub fn is_not_null(self) -> Filter {
+        !Filter::IsNull(self.0)
+    }
+}
+
+impl Operator {
+    pub fn matches(&self, ord: Ordering) -> bool {
+        match self {
+            Operator::Equal => ord == Ordering::Equal,
+            Operator::NotEqual => ord != Ordering::Equal,
+            Operator::Less => ord == Ordering::Less,
+            Operator::LessOrEqual => ord != Ordering::Greater,
+            Operator::Greater => ord == Ordering::Greater,
+            Operator::GreaterOrEqual => ord != Ordering::Less,
+        }
+    }
+}
+
+/// Text of the value for the LIKE and STARTING WITH
+fn text(value: &Value) -> std::borrow::Cow<'_, str> {
+    match value {
+        Value::String(st) => st.into(),
+        value => value.to_string().into(),
+    }
+}
+
+/// Match the text with a LIKE pattern
+pub(crate) fn like(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    let (mut t, mut p) = (0, 0);
+    // Last `%` found and the text position it's matching up to
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '_' || *c == text[t] => {
+                t += 1;
+                p += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    backtrack = Some((bp, bt + 1));
+                    p = bp + 1;
+                    t = bt + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '%')
+}
+
+/// Number of any type, for comparing them
+pub(crate) enum Number {
+    /// Integer with the amount of decimal digits
+    Exact(i128, u32),
+    Approx(f64),
+}
+
+impl Number {
+    pub fn from_value(value: &Value) -> Option<Number> {
+        let num = match value {
+            Value::SmallInt(v) => Number::Exact(*v as i128, 0),
+            Value::Int(v) => Number::Exact(*v as i128, 0),
+            Value::BigInt(v) => Number::Exact(*v as i128, 0),
+            Value::Decimal(v, scale) if *scale < 0 => {
+                Number::Exact(*v as i128, scale.unsigned_abs() as u32)
+            }
+            Value::Decimal(v, scale) => match 10i128
+                .checked_pow(*scale as u32)
+                .and_then(|pow| (*v as i128).checked_mul(pow))
+            {
+                Some(v) => Number::Exact(v, 0),
+                None => Number::Approx(*v as f64 * 10f64.powi(*scale as i32)),
+            },
+            Value::Float(v) => Number::Approx(*v as f64),
+            Value::Double(v) => Number::Approx(*v),
+            _ => return None,
+        };
+
+        Some(num)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Exact(v, digits) => *v as f64 / 10f64.powi(*digits as i32),
+            Number::Approx(v) => *v,
+        }
+    }
+
+    fn compare(&self, other: &Number) -> Ordering {
+        match (self, other) {
+            (Number::Exact(a, da), Number::Exact(b, db)) => {
+                let digits = (*da).max(*db);
+                let scale = |v: i128, d: u32| {
+                    10i128
+                        .checked_pow(digits - d)
+                        .and_then(|pow| v.checked_mul(pow))
+                };
+                match (scale(*a, *da), scale(*b, *db)) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    _ => Number::Approx(self.to_f64()).compare(&Number::Approx(other.to_f64())),
+                }
+            }
+            _ => {
+                let (a, b) = (self.to_f64(), other.to_f64());
+                a.partial_cmp(&b).unwrap_or_else(|| a.total_cmp(&b))
+            }
+        }
+    }
+}
+
+/// Compare two values, converting the numbers between its types and
+/// the text to the type of the other value
+///
+/// The trailing spaces of the text are ignored, like on the CHAR
+/// columns.
+pub(crate) fn compare_values(a: &Value, b: &Value) -> Result<Ordering, Error> {
+    if let (Some(x), Some(y)) = (Number::from_value(a), Number::from_value(b)) {
+        return Ok(x.compare(&y));
+    }
+
+    let ord = match (a, b) {
+        (Value::String(x), Value::String(y)) => {
+            x.trim_end_matches(' ').cmp(y.trim_end_matches(' '))
+        }
+        (Value::Bytes(x), Value::Bytes(y)) | (Value::Blr(x), Value::Blr(y)) => x.cmp(y),
+        (Value::Time(x), Value::Time(y)) => x.cmp(y),
+        (Value::Date(_) | Value::Timestamp(..), Value::Date(_) | Value::Timestamp(..)) => {
+            moment(a).cmp(&moment(b))
+        }
+        (Value::String(st), other) => {
+            return compare_values(&parse_as(st, other)?, other);
+        }
+        (other, Value::String(st)) => {
+            return compare_values(other, &parse_as(st, other)?);
+        }
+        _ => return Err(Error::Other(format!("Can't compare {:?} with {:?}", a, b))),
+    };
+
+    Ok(ord)
+}
+
+/// Date and time of the dates and timestamps
+fn moment(value: &Value) -> (i32, u32) {
+    match value {
+        Value::Timestamp(date, time) => (*date, *time),
+        Value::Date(date) => (*date, 0),
+        _ => (0, 0),
+    }
+}
+
+/// Parse the text as a value of the same type of `like`
+pub(crate) fn parse_as(text: &str, like: &Value) -> Result<Value, Error> {
+    let text = text.trim();
+    let value = match like {
+        Value::SmallInt(_)
+        | Value::Int(_)
+        | Value::BigInt(_)
+        | Value::Decimal(..)
+        | Value::Float(_)
+        | Value::Double(_) => parse_number(text),
+        Value::Date(_) => parse_date(text).map(Value::Date),
+        Value::Time(_) => parse_time(text).map(Value::Time),
+        Value::Timestamp(..) => parse_timestamp(text),
+        _ => None,
+    };
+
+    value.ok_or_else(|| Error::Other(format!("Can't compare '{}' with {:?}", text, like)))
+}
+
+/// Integer, decimal or floating point number
+pub(crate) fn parse_number(text: &str) -> Option<Value> {
+    if let Ok(num) = text.parse::<i64>() {
+        return Some(Value::BigInt(num));
+    }
+
+    if let Some((int, frac)) = text.split_once('.') {
+        let digits = int.trim_start_matches(['-', '+']);
+        if !digits.is_empty()
+            && frac.len() <= 18
+            && digits
+                .chars()
+                .chain(frac.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            if let Ok(num) = format!("{}{}", int, frac).parse::<i64>() {
+                return Some(Value::Decimal(num, -(frac.len() as i16)));
+            }
+        }
+    }
+
+    text.parse::<f64>().ok().map(Value::Double)
+}
+
+/// Days of a date like 2024-01-31
+fn parse_date(text: &str) -> Option<i32> {
+    let mut parts = text.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+
+    civil_days(year, month, day)
+}
+
+/// Time of the day like 13:45, 13:45:10 or 13:45:10.1234
+fn parse_time(text: &str) -> Option<u32> {
+    let (hms, frac) = match text.split_once('.') {
+        Some((hms, frac)) if !frac.is_empty() && frac.len() <= 4 => (hms, frac),
+        Some(_) => return None,
+        None => (text, "0"),
+    };
+
+    let mut parts = hms.split(':');
+    let hours: u32 = parts.next()?.parse().ok()?;
+    let minutes: u32 = parts.next()?.parse().ok()?;
+    let seconds: u32 = match parts.next() {
+        Some(seconds) => seconds.parse().ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() || hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+
+    let fraction: u32 = frac.parse().ok()?;
+    let fraction = fraction * 10u32.pow(4 - frac.len() as u32);
+
+    Some((hours * 3600 + minutes * 60 + seconds) * 10000 + fraction)
+}
+
+/// Date with an optional time, separated by a space or a T
+fn parse_timestamp(text: &str) -> Option<Value> {
+    match text.split_once([' ', 'T']) {
+        Some((date, time)) => Some(Value::Timestamp(
+            parse_date(date)?,
+            parse_time(time.trim())?,
+        )),
+        None => Some(Value::Timestamp(parse_date(text)?, 0)),
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    /// Filter from an expression with the syntax of the SQL conditions
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(st)?.into_iter().peekable(),
+        };
+
+        let filter = parser.or()?;
+        match parser.tokens.next() {
+            Some(token) => Err(Error::Other(format!(
+                "Unexpected {:?} on the filter",
+                token
+            ))),
+            None => Ok(filter),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Names and keywords
+    Word(String),
+    /// Names between double quotes
+    Quoted(String),
+    Text(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+fn tokenize(st: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = vec![];
+    let mut chars = st.chars().peekable();
+
+    while let Some(c) = chars.peek().copied() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let token = match c {
+            '\'' => Token::Text(quoted(&mut chars, '\'')?),
+            '"' => Token::Quoted(quoted(&mut chars, '"')?),
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let mut num = String::new();
+                num.push(c);
+                chars.next();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '.') {
+                    num.push(c);
+                }
+                Token::Number(num)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+                {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            _ => {
+                chars.next();
+                let symbol = match (c, chars.peek()) {
+                    ('<', Some('=')) => "<=",
+                    ('<', Some('>')) => "<>",
+                    ('>', Some('=')) => ">=",
+                    ('!', Some('=')) => "<>",
+                    ('=', _) => "=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    ('(', _) => "(",
+                    (')', _) => ")",
+                    (',', _) => ",",
+                    _ => {
+                        return Err(Error::Other(format!(
+                            "Unexpected character {} on the filter",
+                            c
+                        )))
+                    }
+                };
+                if symbol.len() > 1 {
+                    chars.next();
+                }
+                Token::Symbol(symbol)
+            }
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Text between quotes, with the quote doubled inside of it
+fn quoted(chars: &mut Peekable<Chars>, quote: char) -> Result<String, Error> {
+    chars.next();
+
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some(c) if c == quote => {
+                if chars.next_if_eq(&quote).is_none() {
+                    return Ok(text);
+                }
+                text.push(quote);
+            }
+            Some(c) => text.push(c),
+            None => return Err(Error::from("Unterminated quotes on the filter")),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    /// Consume the keyword when it's the next token
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.tokens
+            .next_if(|t| matches!(t, Token::Word(w) if w.eq_ignore_ascii_case(keyword)))
+            .is_some()
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        if !self.keyword(keyword) {
+            return Err(self.unexpected(keyword));
+        }
+        Ok(())
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        self.tokens
+            .next_if(|t| matches!(t, Token::Symbol(s) if *s == symbol))
+            .is_some()
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), Error> {
+        if !self.symbol(symbol) {
+            return Err(self.unexpected(symbol));
+        }
+        Ok(())
+    }
+
+    fn unexpected(&mut self, expected: &str) -> Error {
+        match self.tokens.peek() {
+            Some(token) => Error::Other(format!(
+                "Expected {} on the filter, found {:?}",
+                expected, token
+            )),
+            None => Error::Other(format!("Expected {} at the end of the filter", expected)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, Error> {
+        let mut filter = self.and()?;
+        while self.keyword("OR") {
+            filter = filter.or(self.and()?);
+        }
+
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, Error> {
+        let mut filter = self.not()?;
+        while self.keyword("AND") {
+            filter = filter.and(self.not()?);
+        }
+
+        Ok(filter)
+    }
+
+    fn not(&mut self) -> Result<Filter, Error> {
+        if self.keyword("NOT") {
+            return Ok(!self.not()?);
+        }
+        if self.symbol("(") {
+            let filter = self.or()?;
+            self.expect_symbol(")")?;
+            return Ok(filter);
+        }
+
+        self.condition()
+    }
+
+    /// Condition on a column
+    fn condition(&mut self) -> Result<Filter, Error> {
+        let name = match self.tokens.next() {
+            Some(Token::Word(name)) => name,
+            Some(Token::Quoted(name)) => name,
+            Some(token) => {
+                return Err(Error::Other(format!(
+                    "Expected a column on the filter, found {:?}",
+                    token
+                )))
+            }
+            None => return Err(Error::from("Expected a column at the end of the filter")),
+        };
+        let column = || Filter::column(&name);
+
+        let ops = [
+            ("=", Operator::Equal),
+            ("<>", Operator::NotEqual),
+            ("<", Operator::Less),
+            ("<=", Operator::LessOrEqual),
+            (">", Operator::Greater),
+            (">=", Operator::GreaterOrEqual),
+        ];
+        for (symbol, op) in ops {
+            if self.symbol(symbol) {
+                return Ok(column().compare(op, self.value()?));
+            }
+        }
+
+        if self.keyword("IS") {
+            let negated = self.keyword("NOT");
+            self.expect_keyword("NULL")?;
+            let filter = column().is_null();
+            return Ok(if negated { !filter } else { filter });
+        }
+        if self.keyword("STARTING") {
+            self.keyword("WITH");
+            return Ok(column().starting_with(&self.text()?));
+        }
+
+        let negated = self.keyword("NOT");
+        let filter = if self.keyword("IN") {
+            self.expect_symbol("(")?;
+            let mut values = vec![self.value()?];
+            while self.symbol(",") {
+                values.push(self.value()?);
+            }
+            self.expect_symbol(")")?;
+            column().is_in(values)
+        } else if self.keyword("LIKE") {
+            column().like(&self.text()?)
+        } else if self.keyword("BETWEEN") {
+            let low = self.value()?;
+            self.expect_keyword("AND")?;
+            let high = self.value()?;
+            column().ge(low).and(column().le(high))
+        } else {
+            return Err(self.unexpected("a comparison"));
+        };
+
+        Ok(if negated { !filter } else { filter })
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        match self.tokens.next() {
+            Some(Token::Text(text)) => Ok(Value::String(text)),
+            Some(Token::Number(num)) => parse_number(&num)
+                .ok_or_else(|| Error::Other(format!("Invalid number {} on the filter", num))),
+            Some(token) => Err(Error::Other(format!(
+                "Expected a value on the filter, found {:?}",
+                token
+            ))),
+            None => Err(Error::from("Expected a value at the end of the filter")),
+        }
+    }
+
+    fn text(&mut self) -> Result<String, Error> {
+        match self.tokens.next() {
+            Some(Token::Text(text)) => Ok(text),
+            Some(token) => Err(Error::Other(format!(
+                "Expected a text on the filter, found {:?}",
+                token
+            ))),
+            None => Err(Error::from("Expected a text at the end of the filter")),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    #[test]
+    pub fn parsing_filters() -> Result<(), Error> {
+        let filter: Filter =
+            "job_country = 'USA' AND (salary >= 50000.50 OR dept_no IN ('600', '621'))".parse()?;
+        assert_eq!(
+            Filter::column("job_country").eq("USA").and(
+                Filter::column("salary")
+                    .ge(Value::Decimal(5000050, -2))
+                    .or(Filter::column("dept_no").is_in(["600", "621"]))
+            ),
+            filter
+        );
+
+        let filter: Filter =
+            "NOT \"First Name\" LIKE 'Jo%' or phone_ext is not null and x starting with 'it''s'"
+                .parse()?;
+        assert_eq!(
+            (!Filter::column("First Name").like("Jo%")).or(Filter::column("phone_ext")
+                .is_not_null()
+                .and(Filter::column("x").starting_with("it's"))),
+            filter
+        );
+
+        let filter: Filter = "a between -1 and 2.5e3 and b not in (1) and c != 3".parse()?;
+        assert_eq!(
+            Filter::column("a")
+                .ge(-1i64)
+                .and(Filter::column("a").le(2500f64))
+                .and(!Filter::column("b").is_in([1i64]))
+                .and(Filter::column("c").ne(3i64)),
+            filter
+        );
+        assert_eq!(vec!["a", "a", "b", "c"], filter.columns());
+
+        for invalid in [
+            "",
+            "a",
+            "a = ",
+            "a = 'x",
+            "a = 1 b = 2",
+            "(a = 1",
+            "a like 1",
+            "a in ()",
+            "a ; 1",
+            "1 = a",
+        ] {
+            assert!(invalid.parse::<Filter>().is_err(), "{}", invalid);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn matching_patterns() {
+        assert!(like("Nelson", "N%"));
+        assert!(like("Nelson", "%son"));
+        assert!(like("Nelson", "N_l%n"));
+        assert!(like("Nelson", "%"));
+        assert!(like("", "%%"));
+        assert!(like("aXbXc", "%X%X%"));
+        assert!(!like("Nelson", "n%"));
+        assert!(!like("Nelson", "Nel"));
+        assert!(!like("Nelson", "Nelson_"));
+        assert!(!like("", "_"));
+    }
+
+    #[test]
+    pub fn comparing_values() -> Result<(), Error> {
+        let cmp = |a: Value, b: Value| compare_values(&a, &b);
+
+        assert_eq!(Ordering::Equal, cmp(Value::Int(10), Value::BigInt(10))?);
+        assert_eq!(
+            Ordering::Equal,
+            cmp(Value::Decimal(1050, -2), Value::Double(10.5))?
+        );
+        assert_eq!(
+            Ordering::Less,
+            cmp(Value::Decimal(1050, -2), Value::SmallInt(11))?
+        );
+        assert_eq!(
+            Ordering::Greater,
+            cmp(Value::Decimal(3, 2), Value::Int(299))?
+        );
+        assert_eq!(
+            Ordering::Greater,
+            cmp(Value::Decimal(1, 40), Value::Int(1))?
+        );
+        assert_eq!(
+            Ordering::Less,
+            cmp(Value::Decimal(-1, 40), Value::Decimal(1, -40))?
+        );
+        assert_eq!(Ordering::Less, cmp(Value::Decimal(1, -40), Value::Int(1))?);
+        assert_eq!(
+            Ordering::Greater,
+            cmp(Value::Decimal(1, -40), Value::Int(0))?
+        );
+        assert_eq!(
+            Ordering::Less,
+            cmp(Value::Decimal(1, i16::MIN), Value::Decimal(1, -2))?
+        );
+        assert_eq!(Ordering::Equal, cmp("USA  ".into(), "USA".into())?);
+        assert_eq!(Ordering::Less, cmp("Nelson".into(), "Young".into())?);
+        assert_eq!(Ordering::Greater, cmp(Value::Int(5), "4.5".into())?);
+        assert_eq!(Ordering::Less, cmp("4.5".into(), Value::Int(5))?);
+
+        // 1988-12-28
+        let date = Value::Date(47523);
+        assert_eq!(Ordering::Equal, cmp(date.clone(), "1988-12-28".into())?);
+        assert_eq!(Ordering::Less, cmp(date.clone(), "1989-01-01".into())?);
+        assert_eq!(
+            Ordering::Less,
+            cmp(date.clone(), Value::Timestamp(47523, 1))?
+        );
+        assert_eq!(
+            Ordering::Equal,
+            cmp(
+                Value::Timestamp(47523, 493500000),
+                "1988-12-28 13:42:30".into()
+            )?
+        );
+        assert_eq!(
+            Ordering::Greater,
+            cmp(Value::Time(493500001), "13:42:30".into())?
+        );
+
+        assert!(cmp(date.clone(), "1988-02-30".into()).is_err());
+        assert!(cmp(Value::Int(1), "one".into()).is_err());
+        assert!(cmp(date, Value::Int(1)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn evaluating_nulls() -> Result<(), Error> {
+        let mut value = |name: &str| -> Result<Option<Value>, Error> {
+            Ok(match name {
+                "A" => Some(Value::Int(1)),
+                _ => None,
+            })
+        };
+
+        let eval = |filter: &str, value: &mut dyn FnMut(&str) -> Result<Option<Value>, Error>| {
+            filter.parse::<Filter>()?.eval(value)
+        };
+
+        assert_eq!(None, eval("B = 1", &mut value)?);
+        assert_eq!(None, eval("NOT B = 1", &mut value)?);
+        assert_eq!(Some(true), eval("B IS NULL", &mut value)?);
+        assert_eq!(Some(false), eval("A IS NULL", &mut value)?);
+        assert_eq!(Some(false), eval("B = 1 AND A = 2", &mut value)?);
+        assert_eq!(None, eval("B = 1 AND A = 1", &mut value)?);
+        assert_eq!(Some(true), eval("B = 1 OR A = 1", &mut value)?);
+        assert_eq!(None, eval("B = 1 OR A = 2", &mut value)?);
+        assert_eq!(Some(true), eval("A IN (3, 2, 1)", &mut value)?);
+
+        Ok(())
+    }
+}
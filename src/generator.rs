@@ -0,0 +1,174 @@
+//! Firebird generators (sequences) representation
+
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use crate::data::DataPage;
+use crate::page::*;
+use crate::source::Source;
+use crate::Error;
+
+/// Size of the generator page header, before the values
+const GPG_SIZE: usize = 24;
+
+/// Generator Page
+///
+/// The current values of the generators are stored as an array of
+/// 64-bit integers, indexed by the RDB$GENERATOR_ID. When a page is
+/// full, the next values continue on the page of the next sequence.
+#[derive(Debug, Clone)]
+pub struct GeneratorPage {
+    pub pag: Page,
+    /// Sequence number of the page
+    pub sequence: u32,
+    pub values: Vec<i64>,
+}
+
+impl GeneratorPage {
+    /// Parse the GeneratorPage from bytes
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<GeneratorPage, Error> {
+        let pag = Page::from_bytes(&bytes)?;
+        if pag.ptype != 0x09 {
+            return Err(Error::InvalidPage {
+                tpe: pag.ptype,
+                expected: 0x09,
+                desc: "generator".to_string(),
+            });
+        }
+        if bytes.len() < GPG_SIZE {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: GPG_SIZE,
+                msg: "generator page header".to_string(),
+            });
+        }
+
+        let values = bytes[GPG_SIZE..]
+            .chunks_exact(8)
+            .map(LittleEndian::read_i64)
+            .collect();
+
+        Ok(GeneratorPage {
+            pag,
+            sequence: LittleEndian::read_u32(&bytes[16..20]),
+            values,
+        })
+    }
+
+    /// Amount of generators that fit on a single page
+    pub fn values_per_page(page_size: u16) -> usize {
+        (page_size as usize - GPG_SIZE) / 8
+    }
+}
+
+/// Generator, also known as sequence
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generator {
+    pub name: String,
+    pub id: u16,
+    pub is_system_generator: bool,
+    /// Value stored on the generator page
+    pub value: i64,
+    /// Value used when the sequence is restarted
+    pub initial_value: i64,
+    pub increment: i32,
+}
+
+impl Generator {
+    /// Load all generators of database with their current values
+    pub(crate) fn load(header: HeaderPage, buffer: Source) -> Result<Vec<Generator>, Error> {
+        let pages = DataPage::load(header, &mut **buffer.lock())?;
+
+        let mut gpages = HashMap::new();
+        let mut generators = vec![];
+
+        for data in pages.iter() {
+            match data.relation {
+                // RDB$PAGES table
+                0 => {
+                    for rec in data.get_records()? {
+                        let rec_data = rec.read()?;
+
+                        if rec_data.len() < 18 {
+                            continue;
+                        }
+
+                        // RDB$PAGE_TYPE field
+                        if LittleEndian::read_i16(&rec_data[16..18]) != 0x09 {
+                            continue;
+                        }
+
+                        // RDB$PAGE_SEQUENCE and RDB$PAGE_NUMBER fields
+                        let sequence = LittleEndian::read_u32(&rec_data[12..16]);
+                        let pageno = LittleEndian::read_u32(&rec_data[4..8]);
+                        gpages.insert(sequence, pageno);
+                    }
+                }
+                // RDB$GENERATORS table
+                20 => {
+                    for rec in data.get_records()? {
+                        let rec_data = rec.read()?;
+
+                        if rec_data.len() < 40 {
+                            continue;
+                        }
+
+                        // RDB$GENERATOR_NAME field
+                        let bname = &rec_data[4..35];
+                        let name = String::from_utf8_lossy(bname).trim().to_string();
+                        // RDB$GENERATOR_ID field
+                        let id = LittleEndian::read_u16(&rec_data[36..38]);
+                        // RDB$SYSTEM_FLAG field
+                        let is_system_generator = LittleEndian::read_i16(&rec_data[38..40]) != 0;
+
+                        // RDB$INITIAL_VALUE and RDB$GENERATOR_INCREMENT
+                        // fields, missing on older databases
+                        let (initial_value, increment) = if rec_data.len() >= 124 {
+                            (
+                                LittleEndian::read_i64(&rec_data[112..120]),
+                                LittleEndian::read_i32(&rec_data[120..124]),
+                            )
+                        } else {
+                            (0, 1)
+                        };
+
+                        generators.push(Generator {
+                            name,
+                            id,
+                            is_system_generator,
+                            value: 0,
+                            initial_value,
+                            increment,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let per_page = GeneratorPage::values_per_page(header.page_size);
+        let mut loaded: HashMap<u32, GeneratorPage> = HashMap::new();
+
+        for gen in generators.iter_mut() {
+            let sequence = (gen.id as usize / per_page) as u32;
+
+            let gpg = match loaded.entry(sequence) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    let pageno = gpages.get(&sequence).ok_or_else(|| {
+                        Error::Other(format!("Generator page {} not found", sequence))
+                    })?;
+                    let bytes = read_page(&buffer, header.page_size, *pageno)?;
+                    e.insert(GeneratorPage::from_bytes(bytes)?)
+                }
+            };
+
+            gen.value = gpg.values[gen.id as usize % per_page];
+        }
+
+        generators.sort_by_key(|g| g.id);
+
+        Ok(generators)
+    }
+}
@@ -0,0 +1,830 @@
+//! Firebird indexes representation and reading
+
+use byteorder::{ByteOrder, LittleEndian};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use crate::column::Column;
+use crate::key::*;
+use crate::page::*;
+use crate::row::{Row, Value};
+use crate::source::Source;
+use crate::Error;
+
+/// Size of the index root page header, before the indexes
+const IRT_SIZE: usize = 20;
+
+/// Size of each index on the index root page
+const IRT_REPEAT_SIZE: usize = 12;
+
+/// Size of each key descriptor of an index
+const IRTD_SIZE: usize = 8;
+
+/// Size of the B-tree page header, before the jump nodes
+const BTR_SIZE: usize = 39;
+
+const IRT_UNIQUE: u8 = 1;
+const IRT_DESCENDING: u8 = 2;
+const IRT_IN_PROGRESS: u8 = 4;
+const IRT_FOREIGN: u8 = 8;
+const IRT_PRIMARY: u8 = 16;
+const IRT_EXPRESSION: u8 = 32;
+
+const BTN_END_LEVEL: u8 = 1;
+const BTN_END_BUCKET: u8 = 2;
+const BTN_ZERO_PREFIX_ZERO_LENGTH: u8 = 3;
+const BTN_ZERO_LENGTH: u8 = 4;
+const BTN_ONE_LENGTH: u8 = 5;
+
+/// Index Root Page
+///
+/// Every table has one index root page, with the root B-tree page
+/// of each one of its indexes and the description of their keys.
+#[derive(Debug, Clone)]
+pub struct IndexRootPage {
+    pub pag: Page,
+    /// Relation of the indexes
+    pub relation: u16,
+    /// Amount of index slots, including the unused ones
+    pub count: u16,
+    pub indexes: Vec<IndexRoot>,
+}
+
+/// Index slot of the index root page
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexRoot {
+    /// Root B-tree page, 0 for unused slots
+    pub root: u32,
+    /// Transaction creating the index, when still in progress
+    pub transaction: u32,
+    pub flags: u8,
+    pub keys: Vec<IndexKeyDescriptor>,
+}
+
+impl IndexRoot {
+    pub fn is_unique(&self) -> bool {
+        self.flags & IRT_UNIQUE != 0
+    }
+
+    pub fn is_descending(&self) -> bool {
+        self.flags & IRT_DESCENDING != 0
+    }
+
+    /// Check if the index is still being created
+    pub fn is_in_progress(&self) -> bool {
+        self.flags & IRT_IN_PROGRESS != 0
+    }
+
+    pub fn is_foreign(&self) -> bool {
+        self.flags & IRT_FOREIGN != 0
+    }
+
+    pub fn is_primary(&self) -> bool {
+        self.flags & IRT_PRIMARY != 0
+    }
+
+    /// Check if the key is computed by an expression
+    pub fn is_expression(&self) -> bool {
+        self.flags & IRT_EXPRESSION != 0
+    }
+}
+
+/// Description of each segment of the index key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexKeyDescriptor {
+    /// Field id of the segment
+    pub field: u16,
+    /// Type of the key, like numeric or string
+    pub itype: u16,
+    pub selectivity: f32,
+}
+
+impl IndexRootPage {
+    /// Parse the IndexRootPage from bytes
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<IndexRootPage, Error> {
+        let pag = Page::from_bytes(&bytes)?;
+        if pag.ptype != 0x06 {
+            return Err(Error::InvalidPage {
+                tpe: pag.ptype,
+                expected: 0x06,
+                desc: "index root".to_string(),
+            });
+        }
+        if bytes.len() < IRT_SIZE {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: IRT_SIZE,
+                msg: "index root page header".to_string(),
+            });
+        }
+
+        let relation = LittleEndian::read_u16(&bytes[16..18]);
+        let count = LittleEndian::read_u16(&bytes[18..20]);
+
+        let end = IRT_SIZE + count as usize * IRT_REPEAT_SIZE;
+        if end > bytes.len() {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: end,
+                msg: "index root page count".to_string(),
+            });
+        }
+
+        let mut indexes = vec![];
+        for i in 0..count as usize {
+            let rpt = &bytes[IRT_SIZE + i * IRT_REPEAT_SIZE..];
+
+            let desc = LittleEndian::read_u16(&rpt[8..10]) as usize;
+            let count = rpt[10] as usize;
+
+            let end = desc + count * IRTD_SIZE;
+            if end > bytes.len() {
+                return Err(Error::Overflow {
+                    limit: bytes.len(),
+                    value: end,
+                    msg: "index key descriptors".to_string(),
+                });
+            }
+
+            let keys = bytes[desc..end]
+                .chunks(IRTD_SIZE)
+                .map(|k| IndexKeyDescriptor {
+                    field: LittleEndian::read_u16(&k[0..2]),
+                    itype: LittleEndian::read_u16(&k[2..4]),
+                    selectivity: LittleEndian::read_f32(&k[4..8]),
+                })
+                .collect();
+
+            indexes.push(IndexRoot {
+                root: LittleEndian::read_u32(&rpt[0..4]),
+                transaction: LittleEndian::read_u32(&rpt[4..8]),
+                flags: rpt[11],
+                keys,
+            });
+        }
+
+        Ok(IndexRootPage {
+            pag,
+            relation,
+            count,
+            indexes,
+        })
+    }
+}
+
+/// B-tree Page
+///
+/// The nodes of a B-tree page are prefix compressed, each key
+/// only stores the bytes that differ from the previous one. The
+/// jump nodes, right after the header, point to some nodes of the
+/// page with their full keys, avoiding a scan from the first node.
+#[derive(Debug, Clone)]
+pub struct BtreePage {
+    pub pag: Page,
+    /// Right sibling page
+    pub sibling: u32,
+    /// Left sibling page
+    pub left_sibling: u32,
+    /// Sum of all prefixes on page
+    pub prefix_total: i32,
+    /// Relation of the index
+    pub relation: u16,
+    /// Bytes used on this page
+    pub length: u16,
+    /// Index id, the slot on the index root page
+    pub id: u8,
+    /// Level of the page, 0 for the leaves
+    pub level: u8,
+    /// Interval between jump nodes
+    pub jump_interval: u16,
+    /// Size of the jump table
+    pub jump_size: u16,
+    /// Number of jump nodes
+    pub jump_count: u8,
+    pub jumps: Vec<JumpNode>,
+    raw: Vec<u8>,
+}
+
+/// Jump node of a B-tree page
+#[derive(Debug, Clone, PartialEq)]
+pub struct JumpNode {
+    /// Offset of the node on the page
+    pub offset: u16,
+    /// Full key of the node
+    pub key: Vec<u8>,
+}
+
+/// Node of a B-tree page
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexNode {
+    /// Record of the key, also present on the non-leaf pages
+    pub record_number: u64,
+    /// Lower level page, only for the non-leaf pages
+    pub page_number: u32,
+    /// Bytes shared with the key of the previous node
+    pub prefix: usize,
+    /// Full key of the node, prefix included
+    pub key: Vec<u8>,
+}
+
+impl BtreePage {
+    /// Parse the BtreePage from bytes
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<BtreePage, Error> {
+        let pag = Page::from_bytes(&bytes)?;
+        if pag.ptype != 0x07 {
+            return Err(Error::InvalidPage {
+                tpe: pag.ptype,
+                expected: 0x07,
+                desc: "b-tree".to_string(),
+            });
+        }
+        if bytes.len() < BTR_SIZE {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: BTR_SIZE,
+                msg: "b-tree page header".to_string(),
+            });
+        }
+
+        let length = LittleEndian::read_u16(&bytes[30..32]);
+        if length as usize > bytes.len() {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: length as usize,
+                msg: "b-tree page length".to_string(),
+            });
+        }
+
+        let jump_count = bytes[38];
+
+        let mut jumps = vec![];
+        let mut pos = BTR_SIZE;
+        let mut prev: Vec<u8> = vec![];
+        for _ in 0..jump_count {
+            let prefix = read_number(&bytes, &mut pos)? as usize;
+            let length = read_number(&bytes, &mut pos)? as usize;
+            let offset = LittleEndian::read_u16(take(&bytes, &mut pos, 2)?);
+
+            let mut key = prev[..prefix.min(prev.len())].to_vec();
+            key.extend(take(&bytes, &mut pos, length)?);
+            prev = key.clone();
+
+            jumps.push(JumpNode { offset, key });
+        }
+
+        Ok(BtreePage {
+            pag,
+            sibling: LittleEndian::read_u32(&bytes[16..20]),
+            left_sibling: LittleEndian::read_u32(&bytes[20..24]),
+            prefix_total: LittleEndian::read_i32(&bytes[24..28]),
+            relation: LittleEndian::read_u16(&bytes[28..30]),
+            length,
+            id: bytes[32],
+            level: bytes[33],
+            jump_interval: LittleEndian::read_u16(&bytes[34..36]),
+            jump_size: LittleEndian::read_u16(&bytes[36..38]),
+            jump_count,
+            jumps,
+            raw: bytes,
+        })
+    }
+
+    /// Check if this page is a leaf of the B-tree
+    pub fn is_leaf(&self) -> bool {
+        self.level == 0
+    }
+
+    /// Check if this is the last page of its level
+    pub fn is_end_level(&self) -> Result<bool, Error> {
+        let mut end_level = false;
+        self.read_nodes(|_, flags| end_level = flags == BTN_END_LEVEL)?;
+
+        Ok(end_level)
+    }
+
+    /// Nodes of the page, without the end markers
+    pub fn nodes(&self) -> Result<Vec<IndexNode>, Error> {
+        let mut nodes = vec![];
+        self.read_nodes(|node, flags| {
+            if flags != BTN_END_LEVEL {
+                nodes.push(node)
+            }
+        })?;
+
+        Ok(nodes)
+    }
+
+    /// Walk over the nodes, expanding the prefix compressed keys,
+    /// until the end marker
+    fn read_nodes(&self, mut f: impl FnMut(IndexNode, u8)) -> Result<(), Error> {
+        let bytes = &self.raw[..self.length as usize];
+        let mut pos = BTR_SIZE + self.jump_size as usize;
+        let mut prev: Vec<u8> = vec![];
+
+        while pos < bytes.len() {
+            // First byte holds the node flags on the upper 3 bits
+            // and the lower 5 bits of the record number
+            let first = take(bytes, &mut pos, 1)?[0];
+            let flags = first >> 5;
+
+            if flags == BTN_END_LEVEL {
+                f(
+                    IndexNode {
+                        record_number: 0,
+                        page_number: 0,
+                        prefix: 0,
+                        key: vec![],
+                    },
+                    flags,
+                );
+                return Ok(());
+            }
+
+            let record_number = (first & 0x1f) as u64 | read_number(bytes, &mut pos)? << 5;
+
+            let page_number = if self.is_leaf() {
+                0
+            } else {
+                read_number(bytes, &mut pos)? as u32
+            };
+
+            let prefix = match flags {
+                BTN_ZERO_PREFIX_ZERO_LENGTH => 0,
+                _ => read_number(bytes, &mut pos)? as usize,
+            };
+
+            let length = match flags {
+                BTN_ZERO_LENGTH | BTN_ZERO_PREFIX_ZERO_LENGTH => 0,
+                BTN_ONE_LENGTH => 1,
+                _ => read_number(bytes, &mut pos)? as usize,
+            };
+
+            if prefix > prev.len() {
+                return Err(Error::Overflow {
+                    limit: prev.len(),
+                    value: prefix,
+                    msg: "b-tree node prefix".to_string(),
+                });
+            }
+            let mut key = prev[..prefix].to_vec();
+            key.extend(take(bytes, &mut pos, length)?);
+            prev = key.clone();
+
+            // The end of bucket holds the first key of the next page
+            if flags == BTN_END_BUCKET {
+                return Ok(());
+            }
+
+            f(
+                IndexNode {
+                    record_number,
+                    page_number,
+                    prefix,
+                    key,
+                },
+                flags,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Read a number stored with 7 bits per byte, the upper bit
+/// marking if there are more bytes
+fn read_number(bytes: &[u8], pos: &mut usize) -> Result<u64, Error> {
+    let mut number = 0;
+
+    for shift in (0..64).step_by(7) {
+        let b = take(bytes, pos, 1)?[0];
+        number |= ((b & 0x7f) as u64) << shift;
+
+        if b & 0x80 == 0 {
+            return Ok(number);
+        }
+    }
+
+    Err(Error::from("Invalid b-tree number"))
+}
+
+fn take<'a>(bytes: &'a [u8], pos: &mut usize, size: usize) -> Result<&'a [u8], Error> {
+    let end = *pos + size;
+    if end > bytes.len() {
+        return Err(Error::Overflow {
+            limit: bytes.len(),
+            value: end,
+            msg: "b-tree node".to_string(),
+        });
+    }
+
+    let slice = &bytes[*pos..end];
+    *pos = end;
+    Ok(slice)
+}
+
+/// Index of a table
+#[derive(Debug, Clone)]
+pub struct Index {
+    /// RDB$INDEX_NAME
+    pub name: String,
+    /// RDB$INDEX_ID, the slot on the index root page plus one
+    pub id: u16,
+    pub unique: bool,
+    pub descending: bool,
+    pub inactive: bool,
+    /// Index referenced by the foreign key
+    pub foreign_key: Option<String>,
+    /// Field names of each segment of the key
+    pub segments: Vec<String>,
+    /// Root B-tree page, 0 when the index isn't built
+    pub root: u32,
+    pub keys: Vec<IndexKeyDescriptor>,
+    page_size: u16,
+    buffer: Source,
+}
+
+/// Entry of an index, a key and the record number it points to
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub key: Vec<u8>,
+    pub record_number: u64,
+}
+
+impl Index {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        name: String,
+        id: u16,
+        unique: bool,
+        descending: bool,
+        inactive: bool,
+        foreign_key: Option<String>,
+        segments: Vec<String>,
+        irt: Option<&IndexRoot>,
+        page_size: u16,
+        buffer: Source,
+    ) -> Index {
+        Index {
+            name,
+            id,
+            unique,
+            descending,
+            inactive,
+            foreign_key,
+            segments,
+            root: irt.map(|i| i.root).unwrap_or(0),
+            keys: irt.map(|i| i.keys.clone()).unwrap_or_default(),
+            page_size,
+            buffer,
+        }
+    }
+
+    /// Read a B-tree page of the index
+    pub fn page(&self, pageno: u32) -> Result<BtreePage, Error> {
+        let bytes = read_page(&self.buffer, self.page_size, pageno)?;
+        let page = BtreePage::from_bytes(bytes)?;
+
+        if page.id as u16 + 1 != self.id {
+            return Err(Error::Other(format!(
+                "B-tree page {} belongs to the index {}, expected {}",
+                pageno,
+                page.id as u16 + 1,
+                self.id
+            )));
+        }
+
+        Ok(page)
+    }
+
+    /// Iterate over all entries of the index, in the key order
+    pub fn entries(&self) -> Result<IndexEntries<'_>, Error> {
+        if self.root == 0 {
+            return Err(Error::Other(format!("Index {} isn't built", self.name)));
+        }
+
+        // Go down by the first node of each level
+        let mut page = self.page(self.root)?;
+        while !page.is_leaf() {
+            let first = page.nodes()?.into_iter().next().ok_or_else(|| {
+                Error::Other(format!("Empty non-leaf page on index {}", self.name))
+            })?;
+            page = self.page(first.page_number)?;
+        }
+
+        self.entries_from(page)
+    }
+
+    /// Entries with the keys between the bounds, inclusive, in the
+    /// index order
+    ///
+    /// The bounds are values of the leading segments of the key, so
+    /// an empty slice means no bound.
+    pub fn range(
+        &self,
+        from: &[Value],
+        to: &[Value],
+        columns: &[Column],
+    ) -> Result<Vec<IndexEntry>, Error> {
+        if self.root == 0 {
+            return Err(Error::Other(format!("Index {} isn't built", self.name)));
+        }
+        if self.segments.is_empty() {
+            return Err(Error::Other(format!(
+                "Expression index {} not supported",
+                self.name
+            )));
+        }
+
+        let from = self.encode_key(from, columns)?;
+        let to = self.encode_key(to, columns)?;
+
+        // Descending keys are stored in the reverse order of the values
+        let (lower, upper) = if self.descending {
+            (to, from)
+        } else {
+            (from, to)
+        };
+
+        // Go down by the last node before the lower bound, since
+        // equal keys can start on the previous page
+        let mut page = self.page(self.root)?;
+        while !page.is_leaf() {
+            let mut child = None;
+            for node in page.nodes()? {
+                if child.is_some() && self.compare_key(&node.key, &lower)? != Ordering::Less {
+                    break;
+                }
+                child = Some(node.page_number);
+            }
+
+            let child = child.ok_or_else(|| {
+                Error::Other(format!("Empty non-leaf page on index {}", self.name))
+            })?;
+            page = self.page(child)?;
+        }
+
+        let mut entries = vec![];
+        for entry in self.entries_from(page)? {
+            let entry = entry?;
+
+            if self.compare_key(&entry.key, &lower)? == Ordering::Less {
+                continue;
+            }
+            if self.compare_key(&entry.key, &upper)? == Ordering::Greater {
+                break;
+            }
+
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    fn entries_from(&self, page: BtreePage) -> Result<IndexEntries<'_>, Error> {
+        Ok(IndexEntries {
+            index: self,
+            nodes: page.nodes()?.into(),
+            sibling: page.sibling,
+        })
+    }
+
+    /// Encode the values of the leading segments of the key
+    pub fn encode_key(&self, values: &[Value], columns: &[Column]) -> Result<Vec<Vec<u8>>, Error> {
+        if values.len() > self.keys.len() {
+            return Err(Error::Other(format!(
+                "Index {} has {} segments, found {} values",
+                self.name,
+                self.keys.len(),
+                values.len()
+            )));
+        }
+
+        let mut segments = vec![];
+        for (desc, value) in self.keys.iter().zip(values) {
+            let col = self.segment_column(desc, columns)?;
+            segments.push(encode_segment(col, desc.itype, value)?);
+        }
+
+        Ok(segments)
+    }
+
+    /// Values of the key segments on a row, with the columns of
+    /// the row values
+    pub fn row_values(&self, row: &Row, columns: &[Column]) -> Result<Vec<Option<Value>>, Error> {
+        let mut values = vec![];
+        for desc in self.keys.iter() {
+            let col = self.segment_column(desc, columns)?;
+            let value = columns
+                .iter()
+                .zip(row.values.iter())
+                .find(|(c, _)| c.id == col.id)
+                .and_then(|(_, v)| v.clone());
+
+            values.push(value);
+        }
+
+        Ok(values)
+    }
+
+    /// Encoded segments of the key of a row
+    pub fn row_key(&self, row: &Row, columns: &[Column]) -> Result<Vec<Vec<u8>>, Error> {
+        let mut segments = vec![];
+        for (desc, value) in self.keys.iter().zip(self.row_values(row, columns)?) {
+            let col = self.segment_column(desc, columns)?;
+
+            segments.push(match value {
+                Some(value) => encode_segment(col, desc.itype, &value)?,
+                None => vec![],
+            });
+        }
+
+        Ok(segments)
+    }
+
+    /// Compare a stored key with the encoded segments, on the order
+    /// of the keys on the index
+    fn compare_key(&self, key: &[u8], search: &[Vec<u8>]) -> Result<Ordering, Error> {
+        let ord = compare_segments(&self.key_segments(key)?, search);
+
+        if self.descending {
+            return Ok(ord.reverse());
+        }
+
+        Ok(ord)
+    }
+
+    /// Split a stored key in the bytes of each segment, like the
+    /// ones of `encode_key`
+    pub fn key_segments(&self, key: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        // Descending keys are stored with all bits inverted
+        if self.descending {
+            let key: Vec<u8> = key.iter().map(|b| !b).collect();
+            return split_segments(&key, self.keys.len());
+        }
+
+        split_segments(key, self.keys.len())
+    }
+
+    /// Column of the table used by the key segment
+    fn segment_column<'a>(
+        &self,
+        desc: &IndexKeyDescriptor,
+        columns: &'a [Column],
+    ) -> Result<&'a Column, Error> {
+        columns
+            .iter()
+            .find(|c| c.id == desc.field as usize)
+            .ok_or_else(|| {
+                Error::Other(format!(
+                    "Field {} of the index {} not found",
+                    desc.field, self.name
+                ))
+            })
+    }
+
+    /// Decode the key of a node to the values of each segment
+    ///
+    /// The columns are the ones of the table, matched by the field
+    /// id of the key descriptors.
+    pub fn decode_key(&self, key: &[u8], columns: &[Column]) -> Result<Vec<Option<Value>>, Error> {
+        if self.keys.is_empty() {
+            return Err(Error::Other(format!("Index {} isn't built", self.name)));
+        }
+
+        let segments = self.key_segments(key)?;
+
+        let mut values = vec![];
+        for (desc, bytes) in self.keys.iter().zip(segments) {
+            let col = self.segment_column(desc, columns)?;
+            values.push(decode_segment(col, desc.itype, &bytes)?);
+        }
+
+        Ok(values)
+    }
+}
+
+/// Iterator over the leaf nodes of an index
+pub struct IndexEntries<'a> {
+    index: &'a Index,
+    nodes: VecDeque<IndexNode>,
+    sibling: u32,
+}
+
+impl Iterator for IndexEntries<'_> {
+    type Item = Result<IndexEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.nodes.is_empty() {
+            if self.sibling == 0 {
+                return None;
+            }
+
+            let page = match self.index.page(self.sibling) {
+                Ok(page) => page,
+                Err(e) => {
+                    self.sibling = 0;
+                    return Some(Err(e));
+                }
+            };
+            self.sibling = page.sibling;
+
+            match page.nodes() {
+                Ok(nodes) => self.nodes = nodes.into(),
+                Err(e) => {
+                    self.sibling = 0;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        self.nodes.pop_front().map(|node| {
+            Ok(IndexEntry {
+                key: node.key,
+                record_number: node.record_number,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    fn btree_page(level: u8, nodes: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0u8; 1024];
+        bytes[0] = 0x07;
+        // relation
+        bytes[28] = 131;
+        // length
+        LittleEndian::write_u16(&mut bytes[30..32], (BTR_SIZE + nodes.len()) as u16);
+        bytes[33] = level;
+        bytes[BTR_SIZE..BTR_SIZE + nodes.len()].copy_from_slice(nodes);
+        bytes
+    }
+
+    #[test]
+    pub fn leaf_nodes() -> Result<(), Error> {
+        let page = BtreePage::from_bytes(btree_page(
+            0,
+            &[
+                // one length, record 1, prefix 0, key "A"
+                0xa1, 0x00, 0x00, b'A',
+                // normal, record 300, prefix 1, length 2, key "BC"
+                0x0c, 0x09, 0x01, 0x02, b'B', b'C', // zero length, record 2, prefix 3
+                0x82, 0x00, 0x03, // end of level
+                0x20,
+            ],
+        ))?;
+
+        assert!(page.is_leaf());
+        assert!(page.is_end_level()?);
+
+        let nodes = page.nodes()?;
+        assert_eq!(3, nodes.len());
+        assert_eq!(b"A".to_vec(), nodes[0].key);
+        assert_eq!(300, nodes[1].record_number);
+        assert_eq!(b"ABC".to_vec(), nodes[1].key);
+        assert_eq!(2, nodes[2].record_number);
+        assert_eq!(b"ABC".to_vec(), nodes[2].key);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn non_leaf_nodes() -> Result<(), Error> {
+        let page = BtreePage::from_bytes(btree_page(
+            1,
+            &[
+                // zero prefix and length, record 0, page 107
+                0x60, 0x00, 0x6b, // normal, record 1477, page 180, prefix 0, key "RDB$"
+                0x05, 0x2e, 0xb4, 0x01, 0x00, 0x04, b'R', b'D', b'B', b'$',
+                // end of bucket, record 5, page 200, prefix 4, key "X"
+                0x45, 0x00, 0xc8, 0x01, 0x04, 0x01, b'X',
+            ],
+        ))?;
+
+        assert!(!page.is_leaf());
+        assert!(!page.is_end_level()?);
+
+        let nodes = page.nodes()?;
+        assert_eq!(2, nodes.len());
+        assert_eq!(107, nodes[0].page_number);
+        assert_eq!(1477, nodes[1].record_number);
+        assert_eq!(180, nodes[1].page_number);
+        assert_eq!(b"RDB$".to_vec(), nodes[1].key);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn truncated_pages() {
+        assert!(BtreePage::from_bytes(vec![]).is_err());
+        assert!(BtreePage::from_bytes(btree_page(0, &[])[..BTR_SIZE - 1].to_vec()).is_err());
+
+        let mut irt = vec![0u8; IRT_SIZE - 1];
+        irt[0] = 0x06;
+        assert!(IndexRootPage::from_bytes(vec![]).is_err());
+        assert!(IndexRootPage::from_bytes(irt).is_err());
+    }
+}
@@ -0,0 +1,566 @@
+//! Decoding of the B-tree index keys
+//!
+//! The keys are built to be compared byte by byte: numbers are
+//! stored as big-endian doubles with the sign flipped, trailing
+//! zeros and spaces are removed and compound keys are split in
+//! groups of 4 bytes, each one after a segment marker.
+
+use byteorder::{BigEndian, ByteOrder};
+use std::cmp::Ordering;
+
+use crate::charset::*;
+use crate::column::*;
+use crate::row::Value;
+use crate::Error;
+
+/// Numbers converted to double
+pub const IDX_NUMERIC: u16 = 0;
+/// Strings without collation
+pub const IDX_STRING: u16 = 1;
+/// Metadata names, like the strings
+pub const IDX_METADATA: u16 = 4;
+pub const IDX_SQL_DATE: u16 = 5;
+pub const IDX_SQL_TIME: u16 = 6;
+pub const IDX_TIMESTAMP: u16 = 7;
+/// 64 bits integers, split in a double and a short
+pub const IDX_NUMERIC2: u16 = 8;
+
+/// Check if the keys of the type can be decoded and encoded
+pub fn is_supported(itype: u16) -> bool {
+    matches!(
+        itype,
+        IDX_NUMERIC
+            | IDX_STRING
+            | IDX_METADATA
+            | IDX_SQL_DATE
+            | IDX_SQL_TIME
+            | IDX_TIMESTAMP
+            | IDX_NUMERIC2
+    )
+}
+
+/// Bytes of each group of the compound keys
+const STUFF_COUNT: usize = 4;
+
+/// Split the key in the bytes of each segment
+///
+/// Compound keys have a marker before each group of bytes with
+/// the count of the segments after the current one, plus one. The
+/// last group of a segment is padded with zeros, which are kept
+/// here.
+pub fn split_segments(key: &[u8], count: usize) -> Result<Vec<Vec<u8>>, Error> {
+    let mut segments = vec![vec![]; count];
+
+    if count <= 1 {
+        if let Some(seg) = segments.first_mut() {
+            seg.extend_from_slice(key);
+        }
+        return Ok(segments);
+    }
+
+    let mut pos = 0;
+    while pos < key.len() {
+        let marker = key[pos] as usize;
+        if marker == 0 || marker > count {
+            return Err(Error::Other(format!(
+                "Invalid segment marker {} at {} of the key",
+                marker, pos
+            )));
+        }
+
+        let end = (pos + 1 + STUFF_COUNT).min(key.len());
+        segments[count - marker].extend_from_slice(&key[pos + 1..end]);
+        pos = end;
+    }
+
+    Ok(segments)
+}
+
+/// Decode the bytes of a single segment with the key type and the
+/// column of the segment
+///
+/// Empty segments are the nulls and unsupported keys, like the ones
+/// with collations, are returned as bytes.
+pub fn decode_segment(col: &Column, itype: u16, bytes: &[u8]) -> Result<Option<Value>, Error> {
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+
+    let val = match itype {
+        IDX_NUMERIC => decode_numeric(col, decode_double(bytes)?)?,
+        IDX_NUMERIC2 => decode_int64(col, bytes)?,
+        IDX_SQL_DATE => Value::Date(decode_number(bytes, 4)? as i32),
+        IDX_SQL_TIME => Value::Time(decode_number(bytes, 4)? as u32),
+        IDX_TIMESTAMP => {
+            let ts = decode_number(bytes, 8)? as i64;
+            Value::Timestamp(
+                ts.div_euclid(864000000) as i32,
+                ts.rem_euclid(864000000) as u32,
+            )
+        }
+        IDX_STRING | IDX_METADATA => decode_string(col, bytes)?,
+        _ => Value::Bytes(bytes.to_vec()),
+    };
+
+    Ok(Some(val))
+}
+
+/// Encode a value to the bytes of a single segment, the reverse
+/// of `decode_segment`
+pub fn encode_segment(col: &Column, itype: u16, value: &Value) -> Result<Vec<u8>, Error> {
+    let bytes = match (itype, value) {
+        (IDX_NUMERIC, _) => encode_number(to_double(value)?.to_bits(), 8),
+        (IDX_NUMERIC2, _) => encode_int64(col, value)?,
+        (IDX_SQL_DATE, Value::Date(date)) => encode_number(*date as u32 as u64, 4),
+        (IDX_SQL_TIME, Value::Time(time)) => encode_number(*time as u64, 4),
+        (IDX_TIMESTAMP, Value::Timestamp(date, time)) => {
+            encode_number((*date as i64 * 864000000 + *time as i64) as u64, 8)
+        }
+        (IDX_TIMESTAMP, Value::Date(date)) => encode_number((*date as i64 * 864000000) as u64, 8),
+        (IDX_STRING | IDX_METADATA, _) => encode_string(col, value)?,
+        _ => {
+            return Err(Error::Other(format!(
+                "Value {:?} not supported on keys of type {}",
+                value, itype
+            )))
+        }
+    };
+
+    Ok(bytes)
+}
+
+/// Compare the segments of a key with the searched ones, only
+/// up to the amount of searched segments
+///
+/// The zeros padding the segments are ignored.
+pub fn compare_segments(segments: &[Vec<u8>], search: &[Vec<u8>]) -> Ordering {
+    for (seg, sch) in segments.iter().zip(search) {
+        let ord = trim_zeros(seg).cmp(trim_zeros(sch));
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+
+    Ordering::Equal
+}
+
+fn trim_zeros(bytes: &[u8]) -> &[u8] {
+    let end = bytes.iter().rposition(|b| *b != 0).map_or(0, |p| p + 1);
+    &bytes[..end]
+}
+
+/// Flip the sign of a number with `size` bytes to big-endian,
+/// removing the trailing zeros
+fn encode_number(bits: u64, size: usize) -> Vec<u8> {
+    let mask = u64::MAX >> (64 - size * 8);
+    let sign = 1 << (size * 8 - 1);
+    let bits = bits & mask;
+
+    // Negative numbers have all bits inverted
+    let num = if bits & sign != 0 {
+        !bits & mask
+    } else {
+        bits | sign
+    };
+
+    trim_zeros(&num.to_be_bytes()[8 - size..]).to_vec()
+}
+
+/// Numeric value of the number
+fn to_double(value: &Value) -> Result<f64, Error> {
+    let num = match value {
+        Value::SmallInt(v) => *v as f64,
+        Value::Int(v) => *v as f64,
+        Value::BigInt(v) => *v as f64,
+        Value::Float(v) => *v as f64,
+        Value::Double(v) => *v,
+        Value::Decimal(v, scale) if *scale < 0 => *v as f64 / 10f64.powi(-*scale as i32),
+        Value::Decimal(v, scale) => *v as f64 * 10f64.powi(*scale as i32),
+        _ => return Err(Error::Other(format!("Value {:?} isn't a number", value))),
+    };
+
+    // Negative zero must have the same key of the zero
+    Ok(if num == 0.0 { 0.0 } else { num })
+}
+
+/// Integer of the number with the scale of the column
+fn to_scaled(col: &Column, value: &Value) -> Result<i64, Error> {
+    let (num, scale) = match value {
+        Value::SmallInt(v) => (*v as i64, 0),
+        Value::Int(v) => (*v as i64, 0),
+        Value::BigInt(v) => (*v, 0),
+        Value::Decimal(v, scale) => (*v, *scale),
+        Value::Float(_) | Value::Double(_) => {
+            let num = to_double(value)? * 10f64.powi(-col.scale as i32);
+            return Ok(num.round() as i64);
+        }
+        _ => return Err(Error::Other(format!("Value {:?} isn't a number", value))),
+    };
+
+    let diff = scale as i32 - col.scale as i32;
+    if diff >= 0 {
+        10i64
+            .checked_pow(diff as u32)
+            .and_then(|pow| num.checked_mul(pow))
+            .ok_or_else(|| {
+                Error::Other(format!(
+                    "Value {} doesn't fit on the scale of {}",
+                    value, col.name
+                ))
+            })
+    } else {
+        // Powers beyond the i64 leave nothing of the number
+        Ok(10i64.checked_pow(-diff as u32).map_or(0, |pow| num / pow))
+    }
+}
+
+fn encode_int64(col: &Column, value: &Value) -> Result<Vec<u8>, Error> {
+    let num = to_scaled(col, value)?;
+
+    // Scale up the number while it fits, so the same value with
+    // different scales has the same key
+    let shift = int64_shift(num.unsigned_abs());
+    let num = 10i128
+        .checked_pow(shift)
+        .and_then(|pow| (num as i128).checked_mul(pow))
+        .ok_or_else(|| Error::Other(format!("Value {} overflows the key", value)))?;
+
+    let d_part = (num / 10000) as f64 / 10f64.powi(shift as i32 - col.scale as i32);
+    let s_part = (num % 10000) as i16 ^ i16::MIN;
+
+    let mut bytes = encode_number(d_part.to_bits(), 8);
+    bytes.resize(8, 0);
+    bytes.extend(s_part.to_be_bytes());
+
+    Ok(trim_zeros(&bytes).to_vec())
+}
+
+/// Limits of the absolute values for each power of ten used to
+/// scale up the 64 bits integers on the keys
+const INT64_SCALE_LIMITS: [u64; 19] = [
+    922337203685470000,
+    92233720368547000,
+    9223372036854700,
+    922337203685470,
+    92233720368548,
+    9223372036855,
+    922337203686,
+    92233720369,
+    9223372035,
+    922337204,
+    92233721,
+    9223373,
+    922338,
+    92234,
+    9224,
+    923,
+    93,
+    10,
+    1,
+];
+
+/// Power of ten applied to the 64 bits integer before split it
+fn int64_shift(num: u64) -> u32 {
+    INT64_SCALE_LIMITS
+        .iter()
+        .position(|limit| num >= *limit)
+        .unwrap_or(0) as u32
+}
+
+fn encode_string(col: &Column, value: &Value) -> Result<Vec<u8>, Error> {
+    let (bytes, pad) = match value {
+        Value::String(st) => (col.charset.encode(st)?, b' '),
+        Value::Bytes(bytes) => (bytes.clone(), 0),
+        _ => return Err(Error::Other(format!("Value {:?} isn't a string", value))),
+    };
+
+    let end = bytes.iter().rposition(|b| *b != pad).map_or(0, |p| p + 1);
+
+    Ok(bytes[..end].to_vec())
+}
+
+/// Revert the sign flip of a number with `size` bytes, restoring
+/// the trailing zeros
+fn decode_number(bytes: &[u8], size: usize) -> Result<u64, Error> {
+    let bytes = trim_padding(bytes, size)?;
+
+    let mut buf = [0; 8];
+    buf[8 - size..8 - size + bytes.len()].copy_from_slice(bytes);
+    let mut num = BigEndian::read_u64(&buf);
+
+    let sign = 1 << (size * 8 - 1);
+    if num & sign != 0 {
+        num ^= sign;
+    } else {
+        // Negative numbers have all bits inverted
+        num = !num & (u64::MAX >> (64 - size * 8));
+    }
+
+    Ok(num)
+}
+
+fn decode_double(bytes: &[u8]) -> Result<f64, Error> {
+    Ok(f64::from_bits(decode_number(bytes, 8)?))
+}
+
+/// Bytes of the number, without the zeros of the compound keys
+fn trim_padding(bytes: &[u8], size: usize) -> Result<&[u8], Error> {
+    if bytes.len() <= size {
+        return Ok(bytes);
+    }
+
+    if bytes[size..].iter().any(|b| *b != 0) {
+        return Err(Error::Other(format!(
+            "Numeric key with {} bytes, expected {}",
+            bytes.len(),
+            size
+        )));
+    }
+
+    Ok(&bytes[..size])
+}
+
+/// Convert the double of the key to the column type
+fn decode_numeric(col: &Column, num: f64) -> Result<Value, Error> {
+    let scaled = || (num * 10f64.powi(-col.scale as i32)).round() as i64;
+
+    let val = match col.tp {
+        ColumnType::Float => Value::Float(num as f32),
+        ColumnType::DoublePrecision => Value::Double(num),
+        _ if col.scale != 0 => Value::Decimal(scaled(), col.scale),
+        ColumnType::Smallint => Value::SmallInt(num as i16),
+        ColumnType::Integer => Value::Int(num as i32),
+        ColumnType::Bigint => Value::BigInt(num as i64),
+        _ => Value::Double(num),
+    };
+
+    Ok(val)
+}
+
+/// Decode the 64 bits integers
+///
+/// The value, scaled up by a power of ten, is split in a double
+/// part, with the value / 10000 unscaled, and a short part, with
+/// the value % 10000.
+fn decode_int64(col: &Column, bytes: &[u8]) -> Result<Value, Error> {
+    let mut buf = [0; 10];
+    let len = bytes.len().min(10);
+    if bytes[len..].iter().any(|b| *b != 0) {
+        return Err(Error::Other(format!(
+            "Int64 key with {} bytes, expected 10",
+            bytes.len()
+        )));
+    }
+    buf[..len].copy_from_slice(&bytes[..len]);
+
+    let d_part = decode_double(&buf[..8])?;
+    let s_part = BigEndian::read_i16(&buf[8..10]) ^ i16::MIN;
+
+    // The shift is found by the magnitude of the number, close
+    // enough with the double part only
+    let approx = d_part * 10000.0 * 10f64.powi(-col.scale as i32);
+    let shift = int64_shift(approx.abs().round() as u64);
+
+    let high = (d_part * 10f64.powi(shift as i32 - col.scale as i32)).round() as i128;
+    let num = ((high * 10000 + s_part as i128) / 10i128.pow(shift)) as i64;
+
+    if col.scale != 0 {
+        return Ok(Value::Decimal(num, col.scale));
+    }
+
+    Ok(Value::BigInt(num))
+}
+
+fn decode_string(col: &Column, bytes: &[u8]) -> Result<Value, Error> {
+    // Zeros of the compound keys padding
+    let end = bytes.iter().rposition(|b| *b != 0).map_or(0, |p| p + 1);
+    let bytes = &bytes[..end];
+
+    if col.charset == Charset::Octets {
+        return Ok(Value::Bytes(bytes.to_vec()));
+    }
+
+    let mut st = col.charset.decode(bytes)?;
+
+    // Trailing spaces aren't stored on the key
+    if col.tp == ColumnType::Char {
+        let len = st.chars().count();
+        if len < col.char_length {
+            st.extend(std::iter::repeat_n(' ', col.char_length - len));
+        }
+    }
+
+    Ok(Value::String(st))
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    fn column(tp: ColumnType, size: usize, scale: i16) -> Column {
+        Column {
+            name: "COL".to_string(),
+            id: 0,
+            position: 0,
+            size,
+            source: "COL".to_string(),
+            scale,
+            tp,
+            sub_type: 0,
+            charset: Charset::None,
+            char_length: size,
+            dimensions: vec![],
+            not_null: false,
+            computed: false,
+        }
+    }
+
+    #[test]
+    pub fn split_compound_key() -> Result<(), Error> {
+        let key = b"\x03Admi\x03n\x00\x00\x00\x02\xc0\x10\x00\x00\x01USA";
+
+        let segments = split_segments(key, 3)?;
+        assert_eq!(b"Admin\x00\x00\x00".to_vec(), segments[0]);
+        assert_eq!(b"\xc0\x10\x00\x00".to_vec(), segments[1]);
+        assert_eq!(b"USA".to_vec(), segments[2]);
+
+        assert!(split_segments(b"\x04Admi", 3).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn decode_numeric_keys() -> Result<(), Error> {
+        let col = column(ColumnType::Smallint, 2, 0);
+        assert_eq!(
+            Some(Value::SmallInt(2)),
+            decode_segment(&col, IDX_NUMERIC, b"\xc0")?
+        );
+        assert_eq!(
+            Some(Value::SmallInt(4)),
+            decode_segment(&col, IDX_NUMERIC, b"\xc0\x10\x00\x00")?
+        );
+        assert_eq!(
+            Some(Value::SmallInt(-2)),
+            decode_segment(&col, IDX_NUMERIC, b"\x3f\xff\xff\xff\xff\xff\xff\xff")?
+        );
+        assert_eq!(None, decode_segment(&col, IDX_NUMERIC, b"")?);
+
+        let col = column(ColumnType::Bigint, 8, -2);
+        assert_eq!(
+            Some(Value::Decimal(2640000, -2)),
+            decode_segment(&col, IDX_NUMERIC2, b"\xc0\x05\x1e\xb8\x51\xeb\x85\x1f\x80")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn decode_date_keys() -> Result<(), Error> {
+        let col = column(ColumnType::Timestamp, 8, 0);
+        assert_eq!(
+            Some(Value::Timestamp(48971, 0)),
+            decode_segment(&col, IDX_TIMESTAMP, b"\x80\x00&{H\xc9\x88")?
+        );
+
+        let col = column(ColumnType::Date, 4, 0);
+        assert_eq!(
+            Some(Value::Date(48971)),
+            decode_segment(&col, IDX_SQL_DATE, b"\x80\x00\xbf\x4b")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn decode_string_keys() -> Result<(), Error> {
+        let col = column(ColumnType::Char, 5, 0);
+        assert_eq!(
+            Some(Value::String("Eng  ".to_string())),
+            decode_segment(&col, IDX_STRING, b"Eng\x00")?
+        );
+
+        let col = column(ColumnType::Varchar, 5, 0);
+        assert_eq!(
+            Some(Value::String("Eng".to_string())),
+            decode_segment(&col, IDX_STRING, b"Eng")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn encode_keys() -> Result<(), Error> {
+        let col = column(ColumnType::Smallint, 2, 0);
+        assert_eq!(
+            b"\xc0".to_vec(),
+            encode_segment(&col, IDX_NUMERIC, &Value::SmallInt(2))?
+        );
+        assert_eq!(
+            b"\xc0\x10".to_vec(),
+            encode_segment(&col, IDX_NUMERIC, &Value::Int(4))?
+        );
+
+        let col = column(ColumnType::Bigint, 8, -2);
+        let bytes = encode_segment(&col, IDX_NUMERIC2, &Value::Decimal(2640000, -2))?;
+        assert_eq!(b"\xc0\x05\x1e\xb8\x51\xeb\x85\x1f\x80".to_vec(), bytes);
+        let bytes = encode_segment(&col, IDX_NUMERIC2, &Value::Decimal(4355000, -2))?;
+        assert_eq!(b"\xc0\x11\x6b\x85\x1e\xb8\x51\xec\x80".to_vec(), bytes);
+        for num in [
+            i64::MIN + 1,
+            -123456789012345678,
+            -123456789,
+            -1,
+            0,
+            1,
+            9999,
+            10000,
+            4355000,
+            123456789,
+            922337203685470000,
+            i64::MAX,
+        ] {
+            let bytes = encode_segment(&col, IDX_NUMERIC2, &Value::Decimal(num, -2))?;
+            assert_eq!(
+                Some(Value::Decimal(num, -2)),
+                decode_segment(&col, IDX_NUMERIC2, &bytes)?
+            );
+        }
+
+        // Values not fitting on the scale of the column
+        for value in [Value::BigInt(i64::MAX), Value::Decimal(1, 30)] {
+            assert!(encode_segment(&col, IDX_NUMERIC2, &value).is_err());
+        }
+        let bytes = encode_segment(&col, IDX_NUMERIC2, &Value::Decimal(1, -30))?;
+        assert_eq!(
+            Some(Value::Decimal(0, -2)),
+            decode_segment(&col, IDX_NUMERIC2, &bytes)?
+        );
+
+        let col = column(ColumnType::Timestamp, 8, 0);
+        assert_eq!(
+            b"\x80\x00&{H\xc9\x88".to_vec(),
+            encode_segment(&col, IDX_TIMESTAMP, &Value::Timestamp(48971, 0))?
+        );
+
+        let col = column(ColumnType::Char, 5, 0);
+        assert_eq!(
+            b"Eng".to_vec(),
+            encode_segment(&col, IDX_STRING, &Value::String("Eng  ".to_string()))?
+        );
+        assert!(encode_segment(&col, IDX_NUMERIC, &Value::String("Eng".to_string())).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn compare_keys() {
+        let a = vec![b"Eng\x00".to_vec(), b"\xc0".to_vec()];
+        let b = vec![b"Eng".to_vec(), b"\xc0\x10".to_vec()];
+
+        assert_eq!(Ordering::Equal, compare_segments(&a, &b[..1]));
+        assert_eq!(Ordering::Less, compare_segments(&a, &b));
+        assert_eq!(Ordering::Greater, compare_segments(&b, &[vec![]]));
+    }
+}
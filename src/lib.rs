@@ -1,19 +1,63 @@
 //! Firebird raw reader
 
+mod array;
+mod backup;
+mod blob;
+mod blr;
+mod charset;
+mod check;
 mod column;
 mod data;
 mod database;
 mod error;
+mod file;
+mod filter;
+mod generator;
+mod index;
+mod key;
+mod nbackup;
 mod page;
+mod pip;
+mod pointer;
 mod record;
 mod row;
+mod scn;
+mod shadow;
+mod source;
+#[cfg(feature = "sql")]
+mod sql;
 mod table;
+mod tip;
 
+pub use array::ArrayDescriptor;
+pub use backup::{Backup, BackupIndex, BackupProcedure, BackupTrigger};
+pub use blob::{BlobHeader, BlobId, BlobPage, BlobReader};
+pub use blr::print_blr;
+pub use charset::Charset;
+pub use check::{IndexCheck, IndexIssue};
 pub use column::{Column, ColumnType};
+pub use data::{DataPage, DataPageRecord};
 pub use database::Database;
 pub use error::Error;
-pub use row::{Row, Value};
-pub use table::Table;
+pub use file::DatabaseFile;
+pub use filter::{Filter, FilterColumn, Operator};
+pub use generator::{Generator, GeneratorPage};
+pub use index::{
+    BtreePage, Index, IndexEntries, IndexEntry, IndexKeyDescriptor, IndexNode, IndexRoot,
+    IndexRootPage, JumpNode,
+};
+pub use nbackup::{BackupState, DeltaFile, IncrementalBackup};
+pub use page::{AnyPage, HeaderClumplet, HeaderPage, Page, PageType};
+pub use pip::{PageAllocation, PageInventoryPage, SpaceUsage};
+pub use pointer::PointerPage;
+pub use row::{DbKey, Row, Value};
+pub use scn::ScnPage;
+pub use shadow::{PageDifference, Shadow, ShadowFile};
+pub use source::{PageBytes, PageSource};
+#[cfg(feature = "sql")]
+pub use sql::{query, QueryResult};
+pub use table::{RowRef, Table, TablePreparated};
+pub use tip::{TransactionInventoryPage, TransactionState};
 
 #[cfg(test)]
 pub mod tests;
@@ -3,12 +3,12 @@
 use argopt::{cmd_group, subcmd};
 use tabled::{builder::Builder, Style, Table as TabledTable, Tabled};
 
-use rfirebird::{ColumnType, Database, Error};
+use rfirebird::{Backup, ColumnType, Database, DbKey, Error, Filter, PageType, Row, Table};
 
-#[cmd_group(commands = [tables, columns, rows])]
+#[cmd_group(commands = [tables, columns, rows, query, check, generators, space, pages, shadows])]
 fn main() -> Result<(), Error> {}
 
-/// Show all avaliable tables of the database
+/// Show all avaliable tables of the database or gbak backup
 #[subcmd]
 fn tables(
     file: String,
@@ -16,9 +16,7 @@ fn tables(
     #[opt(long, default_value = "y")]
     system_tables: String,
 ) -> Result<(), Error> {
-    let mut db = Database::open_file(&file)?;
-
-    let tables = db.tables()?;
+    let tables = load_tables(&file, None)?;
 
     let data = tables
         .iter()
@@ -39,9 +37,7 @@ fn tables(
 /// Show columns of a database table
 #[subcmd]
 fn columns(file: String, table: String) -> Result<(), Error> {
-    let mut db = Database::open_file(&file)?;
-
-    let tables = db.tables()?;
+    let tables = load_tables(&file, None)?;
 
     let otable = tables
         .into_iter()
@@ -55,6 +51,11 @@ fn columns(file: String, table: String) -> Result<(), Error> {
             position: c.position,
             size: c.size,
             tp: c.tp.clone(),
+            dimensions: c
+                .dimensions
+                .iter()
+                .map(|(lower, upper)| format!("[{}:{}]", lower, upper))
+                .collect(),
             scale: c.scale,
             is_not_null: c.not_null,
             is_computed: c.computed,
@@ -72,33 +73,71 @@ fn columns(file: String, table: String) -> Result<(), Error> {
 
 /// Show all rows values of a database table
 #[subcmd]
-fn rows(file: String, table: String) -> Result<(), Error> {
-    let mut db = Database::open_file(&file)?;
-
-    let tables = db.tables()?;
+fn rows(
+    file: String,
+    table: String,
+    /// Charset of the columns declared without one, like WIN1252
+    #[opt(long)]
+    charset: Option<String>,
+    /// Show only the row of this RDB$DB_KEY, like 8300000001000000
+    #[opt(long)]
+    db_key: Option<String>,
+    /// Show only these columns, separated by commas
+    #[opt(long)]
+    columns: Option<String>,
+    /// Leave out the first rows
+    #[opt(long)]
+    skip: Option<usize>,
+    /// Show at most this amount of rows
+    #[opt(long)]
+    limit: Option<usize>,
+    /// Show only the rows matching the condition, like "salary > 5000 AND dept_no = '600'"
+    #[opt(long = "where")]
+    condition: Option<String>,
+) -> Result<(), Error> {
+    let tables = load_tables(&file, charset)?;
 
     let otable = tables
         .into_iter()
         .find(|t| t.name.to_lowercase() == table.to_lowercase().trim());
 
     if let Some(table) = otable {
+        let db_key = db_key.map(|k| k.parse::<DbKey>()).transpose()?;
+
         let mut ptable = table.prepare()?;
+        if let Some(columns) = columns {
+            let names: Vec<&str> = columns.split(',').collect();
+            ptable = ptable.project(&names)?;
+        }
+        if let Some(condition) = condition {
+            ptable = ptable.filter_by(condition.parse::<Filter>()?)?;
+        }
+        if let Some(skip) = skip {
+            ptable = ptable.skip(skip);
+        }
+        if let Some(limit) = limit {
+            ptable = ptable.limit(limit);
+        }
+
         let mut builder = Builder::default();
 
         let columns = ptable.columns.iter().map(|c| c.name.clone());
         builder.set_columns(columns);
 
-        while let Some(row) = ptable.read()? {
-            let mut prow = vec![];
-
-            for cval in row.values {
-                prow.push(match cval {
-                    Some(val) => val.to_string(),
-                    None => "".to_string(),
-                });
+        if let Some(key) = db_key {
+            if key.relation != table.relation {
+                return Err(Error::Other(format!(
+                    "DB key {} belongs to the relation {}, not to the table {}",
+                    key, key.relation, table.name
+                )));
+            }
+            if let Some(row) = ptable.get(key.record_number)? {
+                builder.add_record(printable_values(row));
+            }
+        } else {
+            for row in ptable {
+                builder.add_record(printable_values(row?));
             }
-
-            builder.add_record(prow);
         }
 
         let printable = builder.build().with(Style::psql());
@@ -111,6 +150,297 @@ fn rows(file: String, table: String) -> Result<(), Error> {
     return Err(Error::from("Table not found"));
 }
 
+/// Run a read-only SELECT on the database or gbak backup
+#[subcmd]
+fn query(
+    file: String,
+    sql: String,
+    /// Charset of the columns declared without one, like WIN1252
+    #[opt(long)]
+    charset: Option<String>,
+    /// Show how the tables are read
+    #[opt(long)]
+    plan: bool,
+) -> Result<(), Error> {
+    let tables = load_tables(&file, charset)?;
+    let result = rfirebird::query(&tables, &sql)?;
+
+    if plan {
+        println!("{}", result.plan);
+    }
+
+    let mut builder = Builder::default();
+    builder.set_columns(result.columns);
+    for values in result.rows {
+        builder.add_record(printable_values(Row {
+            raw: vec![],
+            values,
+            db_key: None,
+        }));
+    }
+
+    let printable = builder.build().with(Style::psql());
+
+    println!("{}", printable);
+
+    Ok(())
+}
+
+/// Check the indexes against the rows of the tables
+#[subcmd]
+fn check(
+    file: String,
+    /// Check only this table
+    table: Option<String>,
+) -> Result<(), Error> {
+    let db = Database::open_file(&file)?;
+
+    let tables = db.tables()?;
+
+    let tables: Vec<_> = tables
+        .into_iter()
+        .filter(|t| match &table {
+            Some(name) => t.name.to_lowercase() == name.to_lowercase().trim(),
+            None => true,
+        })
+        .collect();
+
+    if tables.is_empty() {
+        return Err(Error::from("Table not found"));
+    }
+
+    let mut data = vec![];
+    let mut issues = 0;
+
+    for table in tables {
+        for check in table.check_indexes()? {
+            let status = match &check.skipped {
+                Some(reason) => format!("skipped: {}", reason),
+                None if check.issues.is_empty() => "ok".to_string(),
+                None => format!("{} issues", check.issues.len()),
+            };
+
+            data.push(CheckPrintable {
+                table: table.name.clone(),
+                index: check.index.clone(),
+                entries: check.entries,
+                rows: check.rows,
+                garbage: check.garbage,
+                status,
+            });
+
+            for issue in check.issues.iter() {
+                eprintln!("{}.{}: {}", table.name, check.index, issue);
+            }
+            issues += check.issues.len();
+        }
+    }
+
+    let printable = TabledTable::new(data).with(Style::psql());
+
+    println!("{}", printable);
+
+    if issues > 0 {
+        return Err(Error::Other(format!("Found {} index issues", issues)));
+    }
+
+    Ok(())
+}
+
+/// Show the generators with their current values
+#[subcmd]
+fn generators(
+    file: String,
+    /// Show system generators
+    #[opt(long, default_value = "y")]
+    system_generators: String,
+) -> Result<(), Error> {
+    let db = Database::open_file(&file)?;
+
+    let generators = db.generators()?;
+
+    let data = generators
+        .iter()
+        .filter(|g| !g.is_system_generator || system_generators == "y")
+        .map(|g| GeneratorPrintable {
+            name: g.name.clone(),
+            id: g.id,
+            value: g.value,
+            initial_value: g.initial_value,
+            increment: g.increment,
+        });
+
+    let printable = TabledTable::new(data).with(Style::psql());
+
+    println!("{}", printable);
+
+    Ok(())
+}
+
+/// Show the free space of the database
+#[subcmd]
+fn space(file: String) -> Result<(), Error> {
+    let db = Database::open_file(&file)?;
+
+    let usage = db.space_usage()?;
+
+    let mut builder = Builder::default();
+    builder.set_columns(["", "pages", "%"]);
+
+    let percent = |pages: usize| format!("{:.1}", pages as f64 * 100.0 / usage.pages as f64);
+    builder.add_record([
+        "allocated".to_string(),
+        (usage.pages - usage.free_pages).to_string(),
+        percent(usage.pages - usage.free_pages),
+    ]);
+    builder.add_record([
+        "free".to_string(),
+        usage.free_pages.to_string(),
+        percent(usage.free_pages),
+    ]);
+    builder.add_record([
+        "data".to_string(),
+        usage.data_pages.to_string(),
+        percent(usage.data_pages),
+    ]);
+    let levels = ["0-19%", "20-39%", "40-59%", "60-79%", "80-100%"];
+    for (level, pages) in levels.iter().zip(usage.fill_distribution.iter()) {
+        builder.add_record([
+            format!("data filled {}", level),
+            pages.to_string(),
+            percent(*pages),
+        ]);
+    }
+
+    println!("{}", builder.build().with(Style::psql()));
+    println!(
+        "Reclaimable by backup/restore: {} bytes",
+        usage.reclaimable()
+    );
+
+    Ok(())
+}
+
+/// Show the type, generation and SCN of the pages
+#[subcmd]
+fn pages(file: String) -> Result<(), Error> {
+    let db = Database::open_file(&file)?;
+
+    let mut data = vec![];
+    for pageno in 0..db.page_count()? as u32 {
+        let page = db.page(pageno)?;
+        let pag = page.pag();
+
+        data.push(PagePrintable {
+            pageno,
+            tp: format!("{:?}", page.page_type()),
+            flags: pag.flags,
+            generation: pag.generation,
+            scn: pag.scn,
+        });
+    }
+
+    let printable = TabledTable::new(data).with(Style::psql());
+
+    println!("{}", printable);
+
+    Ok(())
+}
+
+/// Show the shadows of the database, or the pages differing from one
+#[subcmd]
+fn shadows(
+    file: String,
+    /// Compare the database with this shadow file
+    #[opt(long)]
+    compare: Option<String>,
+) -> Result<(), Error> {
+    let db = Database::open_file(&file)?;
+
+    if let Some(root) = db.shadowed_file() {
+        println!("Shadow of {}", root);
+    }
+
+    if let Some(compare) = compare {
+        let shadow = Database::open_shadow(&compare)?;
+
+        let tp = |tp: Option<PageType>| tp.map(|t| format!("{:?}", t)).unwrap_or_default();
+        let data: Vec<_> = db
+            .compare_shadow(&shadow)?
+            .into_iter()
+            .map(|d| DifferencePrintable {
+                pageno: d.pageno,
+                database: tp(d.database),
+                shadow: tp(d.shadow),
+            })
+            .collect();
+
+        if data.is_empty() {
+            println!("No differing pages");
+            return Ok(());
+        }
+
+        let printable = TabledTable::new(data).with(Style::psql());
+
+        println!("{}", printable);
+
+        return Ok(());
+    }
+
+    let mut data = vec![];
+    for shadow in db.shadows()? {
+        for file in shadow.files.iter() {
+            data.push(ShadowPrintable {
+                number: shadow.number,
+                file: file.name.clone(),
+                sequence: file.sequence,
+                start: file.start,
+                length: file.length,
+                inactive: shadow.inactive,
+                manual: shadow.manual,
+                conditional: shadow.conditional,
+            });
+        }
+    }
+
+    let printable = TabledTable::new(data).with(Style::psql());
+
+    println!("{}", printable);
+
+    Ok(())
+}
+
+/// Tables of the database, or of the gbak backup
+fn load_tables(file: &str, charset: Option<String>) -> Result<Vec<Table>, Error> {
+    let charset = charset.map(|c| c.parse()).transpose()?;
+
+    if Backup::is_backup(file)? {
+        let mut backup = Backup::open_file(file)?;
+        if let Some(charset) = charset {
+            backup.set_none_charset(charset);
+        }
+
+        return backup.tables();
+    }
+
+    let mut db = Database::open_file(file)?;
+    if let Some(charset) = charset {
+        db.set_none_charset(charset);
+    }
+
+    db.tables()
+}
+
+fn printable_values(row: Row) -> Vec<String> {
+    row.values
+        .into_iter()
+        .map(|cval| match cval {
+            Some(val) => val.to_string(),
+            None => "".to_string(),
+        })
+        .collect()
+}
+
 #[derive(Tabled)]
 struct TablePrintable {
     pub name: String,
@@ -125,7 +455,56 @@ pub struct ColumnPrintable {
     pub size: usize,
     #[tabled(rename = "type")]
     pub tp: ColumnType,
+    pub dimensions: String,
     pub scale: i16,
     pub is_not_null: bool,
     pub is_computed: bool,
 }
+
+#[derive(Tabled)]
+struct CheckPrintable {
+    pub table: String,
+    pub index: String,
+    pub entries: usize,
+    pub rows: usize,
+    pub garbage: usize,
+    pub status: String,
+}
+
+#[derive(Tabled)]
+struct GeneratorPrintable {
+    pub name: String,
+    pub id: u16,
+    pub value: i64,
+    pub initial_value: i64,
+    pub increment: i32,
+}
+
+#[derive(Tabled)]
+struct PagePrintable {
+    pub pageno: u32,
+    #[tabled(rename = "type")]
+    pub tp: String,
+    pub flags: u8,
+    pub generation: u32,
+    pub scn: u32,
+}
+
+#[derive(Tabled)]
+struct ShadowPrintable {
+    pub number: i16,
+    pub file: String,
+    pub sequence: i16,
+    pub start: i32,
+    pub length: i32,
+    pub inactive: bool,
+    pub manual: bool,
+    pub conditional: bool,
+}
+
+#[derive(Tabled)]
+struct DifferencePrintable {
+    pub pageno: u32,
+    pub database: String,
+    pub shadow: String,
+}
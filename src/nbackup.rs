@@ -0,0 +1,250 @@
+//! Firebird nbackup files representation
+
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::ptr;
+
+use crate::Error;
+
+/// First allocation page of the delta file
+const DIFF_FIRST_ALLOC_PAGE: u32 = 1;
+
+/// State of the database regarding nbackup, from the header flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupState {
+    /// Changes are written to the database files
+    Normal,
+    /// Database files locked, changes are written to the delta file
+    Stalled,
+    /// Changes of the delta file being merged into the database
+    Merge,
+}
+
+/// Delta File
+///
+/// While the database is locked by nbackup, the changed pages are
+/// written to the delta file. The allocation pages of the delta file
+/// hold the amount of entries followed by the database page number
+/// of each one, stored on the pages right after the allocation page.
+/// A full allocation page is followed by the next one.
+#[derive(Debug)]
+pub struct DeltaFile {
+    file: File,
+    page_size: u16,
+    /// Delta page of each changed database page
+    pages: HashMap<u32, u32>,
+}
+
+impl DeltaFile {
+    /// Open the delta file and read its allocation table
+    pub fn open<P: AsRef<Path>>(fpath: P, page_size: u16) -> Result<DeltaFile, Error> {
+        let fpath = fpath.as_ref();
+        let mut file =
+            File::open(fpath).map_err(|e| Error::Other(format!("{}: {}", fpath.display(), e)))?;
+
+        let len = file.metadata()?.len();
+        let capacity = page_size as usize / 4 - 1;
+
+        let mut pages = HashMap::new();
+        let mut alloc = DIFF_FIRST_ALLOC_PAGE;
+        let mut buffer = vec![0u8; page_size.into()];
+
+        while (alloc as u64 + 1) * page_size as u64 <= len {
+            file.seek(SeekFrom::Start(alloc as u64 * page_size as u64))?;
+            file.read_exact(&mut buffer)?;
+
+            let count = LittleEndian::read_u32(&buffer[0..4]) as usize;
+            if count > capacity {
+                return Err(Error::Overflow {
+                    limit: capacity,
+                    value: count,
+                    msg: "delta allocation page count".to_string(),
+                });
+            }
+
+            for i in 1..=count {
+                let pageno = LittleEndian::read_u32(&buffer[i * 4..i * 4 + 4]);
+                // Newer entries of the same page replace the older ones
+                pages.insert(pageno, alloc + i as u32);
+            }
+
+            if count < capacity {
+                break;
+            }
+            alloc += count as u32 + 1;
+        }
+
+        Ok(DeltaFile {
+            file,
+            page_size,
+            pages,
+        })
+    }
+
+    /// Database pages changed on the delta file
+    pub fn changed_pages(&self) -> Vec<u32> {
+        let mut pages: Vec<u32> = self.pages.keys().copied().collect();
+        pages.sort_unstable();
+
+        pages
+    }
+
+    /// Position of the page on the delta file, if changed
+    pub fn delta_page(&self, pageno: u32) -> Option<u32> {
+        self.pages.get(&pageno).copied()
+    }
+
+    pub fn page_size(&self) -> u16 {
+        self.page_size
+    }
+
+    /// Read part of a changed page, starting at `offset`
+    pub(crate) fn read_at(
+        &mut self,
+        pageno: u32,
+        offset: usize,
+        buf: &mut [u8],
+    ) -> Result<Option<usize>, std::io::Error> {
+        let delta = match self.pages.get(&pageno) {
+            Some(delta) => *delta,
+            None => return Ok(None),
+        };
+
+        let max = buf.len().min(self.page_size as usize - offset);
+        self.file.seek(SeekFrom::Start(
+            delta as u64 * self.page_size as u64 + offset as u64,
+        ))?;
+        self.file.read_exact(&mut buf[..max])?;
+
+        Ok(Some(max))
+    }
+}
+
+/// Signature of the incremental backup files
+const BACKUP_SIGNATURE: &[u8; 8] = b"NBAK\0\0\0\0";
+
+/// Size of the incremental backup header
+const INC_HEADER_SIZE: usize = 56;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct IncrementalHeaderRep {
+    pub signature: [u8; 8],
+    pub version: u16,
+    pub level: u16,
+    pub backup_guid: [u8; 16],
+    pub prev_guid: [u8; 16],
+    pub page_size: u32,
+    pub backup_scn: u32,
+    pub prev_scn: u32,
+}
+
+/// Incremental Backup File
+///
+/// Backup of level 1 or above made by nbackup, with the pages changed
+/// since the backup of the previous level. After the header, each page
+/// is stored preceded by its page number.
+#[derive(Debug)]
+pub struct IncrementalBackup {
+    pub version: u16,
+    pub level: u16,
+    /// Id of this backup
+    pub backup_guid: [u8; 16],
+    /// Id of the backup of the previous level
+    pub prev_guid: [u8; 16],
+    pub page_size: u32,
+    /// SCN of this backup
+    pub backup_scn: u32,
+    /// SCN of the backup of the previous level
+    pub prev_scn: u32,
+    file: File,
+    /// Position on the file of each page
+    pages: HashMap<u32, u64>,
+}
+
+impl IncrementalBackup {
+    /// Open the backup file and locate its pages
+    pub fn open<P: AsRef<Path>>(fpath: P) -> Result<IncrementalBackup, Error> {
+        let fpath = fpath.as_ref();
+        let mut file =
+            File::open(fpath).map_err(|e| Error::Other(format!("{}: {}", fpath.display(), e)))?;
+
+        let mut bytes = [0u8; INC_HEADER_SIZE];
+        file.read_exact(&mut bytes)?;
+        // The array has no alignment, unlike the struct
+        let rinc: IncrementalHeaderRep = unsafe { ptr::read_unaligned(bytes.as_ptr() as *const _) };
+
+        if &rinc.signature != BACKUP_SIGNATURE {
+            return Err(Error::Other(format!(
+                "{}: not an incremental backup file of nbackup",
+                fpath.display()
+            )));
+        }
+
+        let len = file.metadata()?.len();
+        let record = 4 + rinc.page_size as u64;
+
+        let mut pages = HashMap::new();
+        let mut pos = INC_HEADER_SIZE as u64;
+        let mut pageno = [0u8; 4];
+
+        while pos + record <= len {
+            file.seek(SeekFrom::Start(pos))?;
+            file.read_exact(&mut pageno)?;
+            pages.insert(u32::from_le_bytes(pageno), pos + 4);
+
+            pos += record;
+        }
+
+        Ok(IncrementalBackup {
+            version: rinc.version,
+            level: rinc.level,
+            backup_guid: rinc.backup_guid,
+            prev_guid: rinc.prev_guid,
+            page_size: rinc.page_size,
+            backup_scn: rinc.backup_scn,
+            prev_scn: rinc.prev_scn,
+            file,
+            pages,
+        })
+    }
+
+    /// Check if the file is an incremental backup, instead of a
+    /// database or a backup of level 0
+    pub fn is_incremental<P: AsRef<Path>>(fpath: P) -> Result<bool, Error> {
+        let mut signature = [0u8; 8];
+        let mut file = File::open(fpath)?;
+
+        Ok(file.read(&mut signature)? == 8 && &signature == BACKUP_SIGNATURE)
+    }
+
+    /// Database pages stored on this backup
+    pub fn changed_pages(&self) -> Vec<u32> {
+        let mut pages: Vec<u32> = self.pages.keys().copied().collect();
+        pages.sort_unstable();
+
+        pages
+    }
+
+    /// Read part of a stored page, starting at `offset`
+    pub(crate) fn read_at(
+        &mut self,
+        pageno: u32,
+        offset: usize,
+        buf: &mut [u8],
+    ) -> Result<Option<usize>, std::io::Error> {
+        let start = match self.pages.get(&pageno) {
+            Some(start) => *start,
+            None => return Ok(None),
+        };
+
+        let max = buf.len().min(self.page_size as usize - offset);
+        self.file.seek(SeekFrom::Start(start + offset as u64))?;
+        self.file.read_exact(&mut buf[..max])?;
+
+        Ok(Some(max))
+    }
+}
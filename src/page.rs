@@ -1,9 +1,24 @@
 //! Firebird page's representation
 
+use byteorder::{ByteOrder, LittleEndian};
+use std::convert::TryFrom;
 use std::ptr;
 
+use crate::blob::BlobPage;
+use crate::data::DataPage;
+use crate::generator::GeneratorPage;
+use crate::index::{BtreePage, IndexRootPage};
+use crate::nbackup::BackupState;
+use crate::pip::PageInventoryPage;
+use crate::pointer::PointerPage;
+use crate::scn::ScnPage;
+use crate::source::Source;
+use crate::tip::TransactionInventoryPage;
 use crate::Error;
 
+/// Size of the standard page header
+pub const PAG_SIZE: usize = 16;
+
 /// Standard Database Page Header
 ///
 /// Every page in a database has a 16-byte standard page header.
@@ -11,11 +26,83 @@ use crate::Error;
 #[derive(Debug, Copy, Clone)]
 pub struct Page {
     pub ptype: u8,
-    flags: u8,
+    /// Flags specific of each page type
+    pub flags: u8,
     reserved: u16,
-    generation: u32,
-    scn: u32,
-    pageno: u32,
+    /// Incremented each time the page is written
+    pub generation: u32,
+    /// System change number of the last write, used by nbackup
+    pub scn: u32,
+    /// Page number, as written by the engine
+    pub pageno: u32,
+}
+
+/// Type of the page, from the first byte of its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PageType {
+    /// Free pages never written
+    Undefined = 0x00,
+    Header = 0x01,
+    PageInventory = 0x02,
+    TransactionInventory = 0x03,
+    Pointer = 0x04,
+    Data = 0x05,
+    IndexRoot = 0x06,
+    Btree = 0x07,
+    Blob = 0x08,
+    Generator = 0x09,
+    ScnInventory = 0x0a,
+}
+
+impl TryFrom<u8> for PageType {
+    type Error = Error;
+
+    fn try_from(ptype: u8) -> Result<Self, Self::Error> {
+        let tpe = match ptype {
+            0x00 => PageType::Undefined,
+            0x01 => PageType::Header,
+            0x02 => PageType::PageInventory,
+            0x03 => PageType::TransactionInventory,
+            0x04 => PageType::Pointer,
+            0x05 => PageType::Data,
+            0x06 => PageType::IndexRoot,
+            0x07 => PageType::Btree,
+            0x08 => PageType::Blob,
+            0x09 => PageType::Generator,
+            0x0a => PageType::ScnInventory,
+            _ => return Err(Error::Other(format!("Unknown page type {}", ptype))),
+        };
+
+        Ok(tpe)
+    }
+}
+
+impl Page {
+    /// Type of the page
+    pub fn page_type(&self) -> Result<PageType, Error> {
+        PageType::try_from(self.ptype)
+    }
+
+    /// Parse the standard header of any page
+    pub fn from_bytes(bytes: &[u8]) -> Result<Page, Error> {
+        if bytes.len() < PAG_SIZE {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: PAG_SIZE,
+                msg: "page header".to_string(),
+            });
+        }
+
+        // Any slice can be given, without the alignment of the struct
+        Ok(Page {
+            ptype: bytes[0],
+            flags: bytes[1],
+            reserved: LittleEndian::read_u16(&bytes[2..4]),
+            generation: LittleEndian::read_u32(&bytes[4..8]),
+            scn: LittleEndian::read_u32(&bytes[8..12]),
+            pageno: LittleEndian::read_u32(&bytes[12..16]),
+        })
+    }
 }
 
 /// Database Page Header
@@ -44,7 +131,7 @@ pub struct HeaderPage {
     /// Next transaction id
     next_transaction: u32,
     /// sequence number of file
-    sequence: u16,
+    pub sequence: u16,
     /// Flag settings, see below
     flags: u16,
     /// Date/time of creation
@@ -83,7 +170,29 @@ pub struct HeaderPage {
     data: [u8; 1],
 }
 
+/// Header flag of the files of an active shadow
+const HDR_ACTIVE_SHADOW: u16 = 0x0001;
+
+/// nbackup state bits of the header flags
+const HDR_BACKUP_MASK: u16 = 0x0c00;
+const HDR_NBAK_STALLED: u16 = 0x0400;
+const HDR_NBAK_MERGE: u16 = 0x0800;
+
 impl HeaderPage {
+    /// State of the database regarding nbackup
+    pub fn backup_state(&self) -> BackupState {
+        match self.flags & HDR_BACKUP_MASK {
+            HDR_NBAK_STALLED => BackupState::Stalled,
+            HDR_NBAK_MERGE => BackupState::Merge,
+            _ => BackupState::Normal,
+        }
+    }
+
+    /// Check if the file is a shadow of the database
+    pub fn is_shadow(&self) -> bool {
+        self.flags & HDR_ACTIVE_SHADOW != 0
+    }
+
     pub fn from_bytes(bytes: [u8; 1024]) -> Result<HeaderPage, Error> {
         let hdr: HeaderPage = unsafe { ptr::read(bytes.as_ptr() as *const _) };
 
@@ -98,3 +207,174 @@ impl HeaderPage {
         Ok(hdr)
     }
 }
+
+/// Size of the header page before the clumplets
+const HDR_SIZE: usize = 128;
+
+/// Header clumplet types
+const HDR_END: u8 = 0;
+const HDR_ROOT_FILE_NAME: u8 = 1;
+const HDR_FILE: u8 = 2;
+const HDR_LAST_PAGE: u8 = 3;
+const HDR_SWEEP_INTERVAL: u8 = 4;
+const HDR_DIFFERENCE_FILE: u8 = 6;
+const HDR_BACKUP_GUID: u8 = 7;
+
+/// Variable data of the header page
+///
+/// Stored after the fixed fields of the header page, as a type,
+/// a length and the data, until the end clumplet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeaderClumplet {
+    /// Original name of the primary file
+    RootFileName(String),
+    /// Next file of the database
+    File(String),
+    /// Last page stored on this file
+    LastPage(u32),
+    SweepInterval(u32),
+    /// Delta file used while locked by nbackup
+    DifferenceFile(String),
+    /// Id generated each time the database is locked by nbackup
+    BackupGuid([u8; 16]),
+    Other(u8, Vec<u8>),
+}
+
+impl HeaderClumplet {
+    /// Parse the clumplets of the raw header page
+    pub fn parse(bytes: &[u8]) -> Result<Vec<HeaderClumplet>, Error> {
+        let mut clumplets = vec![];
+        let mut pos = HDR_SIZE;
+
+        while pos < bytes.len() && bytes[pos] != HDR_END {
+            if pos + 2 > bytes.len() {
+                break;
+            }
+
+            let tpe = bytes[pos];
+            let len = bytes[pos + 1] as usize;
+            let end = pos + 2 + len;
+            if end > bytes.len() {
+                return Err(Error::Overflow {
+                    limit: bytes.len(),
+                    value: end,
+                    msg: "header clumplet".to_string(),
+                });
+            }
+            let data = &bytes[pos + 2..end];
+
+            let text = || String::from_utf8_lossy(data).to_string();
+            let number = || {
+                let mut buf = [0u8; 4];
+                let len = data.len().min(4);
+                buf[..len].copy_from_slice(&data[..len]);
+                u32::from_le_bytes(buf)
+            };
+
+            clumplets.push(match tpe {
+                HDR_ROOT_FILE_NAME => HeaderClumplet::RootFileName(text()),
+                HDR_FILE => HeaderClumplet::File(text()),
+                HDR_LAST_PAGE => HeaderClumplet::LastPage(number()),
+                HDR_SWEEP_INTERVAL => HeaderClumplet::SweepInterval(number()),
+                HDR_DIFFERENCE_FILE => HeaderClumplet::DifferenceFile(text()),
+                HDR_BACKUP_GUID if data.len() == 16 => {
+                    let mut guid = [0u8; 16];
+                    guid.copy_from_slice(data);
+                    HeaderClumplet::BackupGuid(guid)
+                }
+                _ => HeaderClumplet::Other(tpe, data.to_vec()),
+            });
+
+            pos = end;
+        }
+
+        Ok(clumplets)
+    }
+}
+
+/// Any page of the database, parsed by its type
+#[derive(Debug, Clone)]
+pub enum AnyPage {
+    Undefined(Page),
+    Header(Box<HeaderPage>),
+    PageInventory(PageInventoryPage),
+    TransactionInventory(TransactionInventoryPage),
+    Pointer(PointerPage),
+    Data(DataPage),
+    IndexRoot(IndexRootPage),
+    Btree(BtreePage),
+    Blob(BlobPage),
+    Generator(GeneratorPage),
+    ScnInventory(ScnPage),
+}
+
+impl AnyPage {
+    /// Parse the page from bytes, according to its type
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<AnyPage, Error> {
+        let pag = Page::from_bytes(&bytes)?;
+
+        let page = match pag.page_type()? {
+            PageType::Undefined => AnyPage::Undefined(pag),
+            PageType::Header => {
+                let mut tag = [0u8; 1024];
+                let len = bytes.len().min(tag.len());
+                tag[..len].copy_from_slice(&bytes[..len]);
+
+                AnyPage::Header(Box::new(HeaderPage::from_bytes(tag)?))
+            }
+            PageType::PageInventory => {
+                AnyPage::PageInventory(PageInventoryPage::from_bytes(bytes)?)
+            }
+            PageType::TransactionInventory => {
+                AnyPage::TransactionInventory(TransactionInventoryPage::from_bytes(bytes)?)
+            }
+            PageType::Pointer => AnyPage::Pointer(PointerPage::from_bytes(bytes)?),
+            PageType::Data => AnyPage::Data(DataPage::from_bytes(bytes)?),
+            PageType::IndexRoot => AnyPage::IndexRoot(IndexRootPage::from_bytes(bytes)?),
+            PageType::Btree => AnyPage::Btree(BtreePage::from_bytes(bytes)?),
+            PageType::Blob => AnyPage::Blob(BlobPage::from_bytes(bytes)?),
+            PageType::Generator => AnyPage::Generator(GeneratorPage::from_bytes(bytes)?),
+            PageType::ScnInventory => AnyPage::ScnInventory(ScnPage::from_bytes(bytes)?),
+        };
+
+        Ok(page)
+    }
+
+    /// Standard header of the page
+    pub fn pag(&self) -> Page {
+        match self {
+            AnyPage::Undefined(pag) => *pag,
+            AnyPage::Header(p) => p.pag,
+            AnyPage::PageInventory(p) => p.pag,
+            AnyPage::TransactionInventory(p) => p.pag,
+            AnyPage::Pointer(p) => p.pag,
+            AnyPage::Data(p) => p.pag,
+            AnyPage::IndexRoot(p) => p.pag,
+            AnyPage::Btree(p) => p.pag,
+            AnyPage::Blob(p) => p.pag,
+            AnyPage::Generator(p) => p.pag,
+            AnyPage::ScnInventory(p) => p.pag,
+        }
+    }
+
+    pub fn page_type(&self) -> PageType {
+        match self {
+            AnyPage::Undefined(_) => PageType::Undefined,
+            AnyPage::Header(_) => PageType::Header,
+            AnyPage::PageInventory(_) => PageType::PageInventory,
+            AnyPage::TransactionInventory(_) => PageType::TransactionInventory,
+            AnyPage::Pointer(_) => PageType::Pointer,
+            AnyPage::Data(_) => PageType::Data,
+            AnyPage::IndexRoot(_) => PageType::IndexRoot,
+            AnyPage::Btree(_) => PageType::Btree,
+            AnyPage::Blob(_) => PageType::Blob,
+            AnyPage::Generator(_) => PageType::Generator,
+            AnyPage::ScnInventory(_) => PageType::ScnInventory,
+        }
+    }
+}
+
+/// Read the raw content of a specific page of buffer
+pub fn read_page(buffer: &Source, page_size: u16, pageno: u32) -> Result<Vec<u8>, Error> {
+    Ok(buffer.page(page_size, pageno)?.into_vec())
+}
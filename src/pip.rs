@@ -0,0 +1,115 @@
+//! Firebird page inventory page representation
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::page::*;
+use crate::Error;
+
+/// Size of the page inventory page header, before the bitmap
+const PIP_SIZE: usize = 28;
+
+/// Amount of pages of each extent, reserved at once
+pub const PAGES_PER_EXTENT: usize = 8;
+
+/// Page Inventory Page
+///
+/// Every page of the database has a bit on a PIP, set when the page
+/// is free and cleared once it's allocated. The first PIP is always
+/// the page 1, the next ones are placed on the last page covered by
+/// the previous PIP.
+#[derive(Debug, Clone)]
+pub struct PageInventoryPage {
+    pub pag: Page,
+    /// Lowest page that may be free
+    pub min: u32,
+    /// Lowest free extent
+    pub extent: u32,
+    /// Amount of pages allocated from this PIP
+    pub used: u32,
+    /// One bit per page, set for the free ones
+    pub bits: Vec<u8>,
+}
+
+impl PageInventoryPage {
+    /// Parse the PageInventoryPage from bytes
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<PageInventoryPage, Error> {
+        let pag = Page::from_bytes(&bytes)?;
+        if pag.ptype != 0x02 {
+            return Err(Error::InvalidPage {
+                tpe: pag.ptype,
+                expected: 0x02,
+                desc: "page inventory".to_string(),
+            });
+        }
+        if bytes.len() < PIP_SIZE {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: PIP_SIZE,
+                msg: "page inventory page header".to_string(),
+            });
+        }
+
+        Ok(PageInventoryPage {
+            pag,
+            min: LittleEndian::read_u32(&bytes[16..20]),
+            extent: LittleEndian::read_u32(&bytes[20..24]),
+            used: LittleEndian::read_u32(&bytes[24..28]),
+            bits: bytes[PIP_SIZE..].to_vec(),
+        })
+    }
+
+    /// Amount of pages tracked by each PIP
+    pub fn pages_per_pip(page_size: u16) -> usize {
+        (page_size as usize - PIP_SIZE) * 8
+    }
+
+    /// Page number of the PIP of a given sequence
+    pub fn pip_page(page_size: u16, sequence: u32) -> u32 {
+        match sequence {
+            0 => 1,
+            _ => sequence * Self::pages_per_pip(page_size) as u32 - 1,
+        }
+    }
+
+    /// Check if the page at this position of the PIP is free
+    pub fn is_free(&self, slot: usize) -> bool {
+        self.bits[slot / 8] & (1 << (slot % 8)) != 0
+    }
+
+    /// Check if every page of the extent at this position is allocated
+    pub fn is_extent_full(&self, slot: usize) -> bool {
+        // Extents are aligned, taking exactly a byte of the bitmap
+        self.bits[slot / PAGES_PER_EXTENT] == 0
+    }
+}
+
+/// Allocation state of a page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageAllocation {
+    pub pageno: u32,
+    pub is_free: bool,
+    /// Every page of its extent is allocated
+    pub is_extent_full: bool,
+}
+
+/// Summary of the space used by the database
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpaceUsage {
+    pub page_size: u16,
+    /// Amount of pages of the file
+    pub pages: usize,
+    pub free_pages: usize,
+    pub data_pages: usize,
+    /// Unused bytes of the data pages
+    pub data_free_space: usize,
+    /// Data pages by fill level: 0-19%, 20-39%, 40-59%, 60-79% and 80-100%
+    pub fill_distribution: [usize; 5],
+}
+
+impl SpaceUsage {
+    /// Bytes that could be reclaimed by a backup and restore, from
+    /// the free pages and the unused space of the data pages
+    pub fn reclaimable(&self) -> usize {
+        self.free_pages * self.page_size as usize + self.data_free_space
+    }
+}
@@ -0,0 +1,131 @@
+//! Firebird pointer page representation
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::page::*;
+use crate::Error;
+
+/// Size of the pointer page header, before the page numbers
+const PPG_SIZE: usize = 32;
+
+/// Pointer page flags
+const PPG_EOF: u8 = 0x01;
+
+/// Data page flags
+const PPG_DP_FULL: u8 = 0x01;
+const PPG_DP_LARGE: u8 = 0x02;
+const PPG_DP_SWEPT: u8 = 0x04;
+const PPG_DP_SECONDARY: u8 = 0x08;
+const PPG_DP_EMPTY: u8 = 0x10;
+
+/// Pointer Page
+///
+/// Lists the data pages of a table, each pointer page holds the
+/// page numbers of a range of data page sequences followed by a
+/// byte of flags for each one of them.
+#[derive(Debug, Clone)]
+pub struct PointerPage {
+    pub pag: Page,
+    /// Sequence number of this pointer page for the table
+    pub sequence: u32,
+    /// Next pointer page of the table, 0 on the last one
+    pub next: u32,
+    /// Amount of slots used
+    pub count: u16,
+    pub relation: u16,
+    /// Lowest slot with space available
+    pub min_space: u16,
+    /// Highest slot with space available
+    pub max_space: u16,
+    /// Data pages, 0 for the released slots
+    pub pages: Vec<u32>,
+    /// Flags of each data page
+    pub flags: Vec<u8>,
+}
+
+impl PointerPage {
+    /// Parse the PointerPage from bytes
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<PointerPage, Error> {
+        let pag = Page::from_bytes(&bytes)?;
+        if pag.ptype != 0x04 {
+            return Err(Error::InvalidPage {
+                tpe: pag.ptype,
+                expected: 0x04,
+                desc: "pointer".to_string(),
+            });
+        }
+        if bytes.len() < PPG_SIZE {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: PPG_SIZE,
+                msg: "pointer page header".to_string(),
+            });
+        }
+
+        let per_page = Self::data_pages_per_pointer(bytes.len().min(u16::MAX as usize) as u16);
+        let count = LittleEndian::read_u16(&bytes[24..26]) as usize;
+        if count > per_page {
+            return Err(Error::Overflow {
+                limit: per_page,
+                value: count,
+                msg: "pointer page count".to_string(),
+            });
+        }
+
+        let pages = bytes[PPG_SIZE..PPG_SIZE + count * 4]
+            .chunks_exact(4)
+            .map(LittleEndian::read_u32)
+            .collect();
+
+        let flags_start = PPG_SIZE + per_page * 4;
+        let flags = bytes[flags_start..flags_start + count].to_vec();
+
+        Ok(PointerPage {
+            pag,
+            sequence: LittleEndian::read_u32(&bytes[16..20]),
+            next: LittleEndian::read_u32(&bytes[20..24]),
+            count: count as u16,
+            relation: LittleEndian::read_u16(&bytes[26..28]),
+            min_space: LittleEndian::read_u16(&bytes[28..30]),
+            max_space: LittleEndian::read_u16(&bytes[30..32]),
+            pages,
+            flags,
+        })
+    }
+
+    /// Amount of data pages listed by each pointer page
+    pub fn data_pages_per_pointer(page_size: u16) -> usize {
+        // Every data page takes its page number and a byte of flags
+        (page_size as usize - PPG_SIZE) * 8 / (32 + 8)
+    }
+
+    /// Check if it's the last pointer page of the table
+    pub fn is_eof(&self) -> bool {
+        self.pag.flags & PPG_EOF != 0
+    }
+
+    /// Check if the data page has no space for new records
+    pub fn is_full(&self, slot: usize) -> bool {
+        self.flags[slot] & PPG_DP_FULL != 0
+    }
+
+    /// Check if the data page holds a big record
+    pub fn is_large(&self, slot: usize) -> bool {
+        self.flags[slot] & PPG_DP_LARGE != 0
+    }
+
+    /// Check if the data page has no garbage to collect
+    pub fn is_swept(&self, slot: usize) -> bool {
+        self.flags[slot] & PPG_DP_SWEPT != 0
+    }
+
+    /// Check if the data page only holds fragments and back versions
+    pub fn is_secondary(&self, slot: usize) -> bool {
+        self.flags[slot] & PPG_DP_SECONDARY != 0
+    }
+
+    /// Check if the data page has no records
+    pub fn is_empty(&self, slot: usize) -> bool {
+        self.flags[slot] & PPG_DP_EMPTY != 0
+    }
+}
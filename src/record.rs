@@ -1,12 +1,26 @@
 //! Firebird records operations
 
-use std::ptr;
+use byteorder::{ByteOrder, LittleEndian};
 
 use crate::Error;
 
+/// Record is logically deleted
+pub const RHD_DELETED: u16 = 1;
+/// Record is an old version
+pub const RHD_CHAIN: u16 = 2;
+/// Record is a fragment
+pub const RHD_FRAGMENT: u16 = 4;
+/// Isn't a record but a blob
+pub const RHD_BLOB: u16 = 16;
+/// Blob is a stream mode blob
+pub const RHD_STREAM_BLOB: u16 = 32;
+
+/// Size of the record header, before the compressed data
+pub const RHD_SIZE: usize = 13;
+
 /// Header for unfragmented firebird records
 #[derive(Debug, Clone)]
-pub struct RecordHeader {
+pub struct RecordHeader<'a> {
     /// The id of the transaction that created this record
     pub transaction: i32,
     /// This is the record’s back pointer page
@@ -16,89 +30,90 @@ pub struct RecordHeader {
     pub flags: u16,
     /// The record format version
     pub format: u8,
-    /// This is the start of the compressed data.
-    pub data: Vec<u8>,
-}
-
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct RecordHeaderRepr {
-    pub transaction: i32,
-    pub b_page: i32,
-    pub b_line: u16,
-    pub flags: u16,
-    pub format: u8,
-    pub data: [u8; 1024],
+    /// This is the start of the compressed data, borrowed from
+    /// the data page
+    pub data: &'a [u8],
 }
 
-impl RecordHeader {
-    pub fn from_bytes(bytes: Vec<u8>) -> Result<RecordHeader, Error> {
-        if bytes.len() > 1024 {
-            return Err(Error::Overflow {
-                limit: 1024,
-                value: bytes.len(),
-                msg: "supported data on header".to_string(),
-            });
+impl<'a> RecordHeader<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<RecordHeader<'a>, Error> {
+        if bytes.len() < RHD_SIZE {
+            return Err(Error::Other(format!(
+                "Record with {} bytes is smaller than its header",
+                bytes.len()
+            )));
         }
 
-        let rrecord: RecordHeaderRepr = unsafe { ptr::read(bytes.as_ptr() as *const _) };
-
-        let mut data = rrecord.data.to_vec();
-        data.truncate(bytes.len() as usize);
-
         let record = RecordHeader {
-            transaction: rrecord.transaction,
-            b_page: rrecord.b_page,
-            b_line: rrecord.b_line,
-            flags: rrecord.flags,
-            format: rrecord.format,
-            data,
+            transaction: LittleEndian::read_i32(&bytes[0..4]),
+            b_page: LittleEndian::read_i32(&bytes[4..8]),
+            b_line: LittleEndian::read_u16(&bytes[8..10]),
+            flags: LittleEndian::read_u16(&bytes[10..12]),
+            format: bytes[12],
+            data: &bytes[RHD_SIZE..],
         };
 
         Ok(record)
     }
 
+    /// Check if this is the current version of a table row, not an
+    /// old version, a fragment, a blob or a deleted record
+    pub fn is_primary(&self) -> bool {
+        self.flags & (RHD_DELETED | RHD_CHAIN | RHD_FRAGMENT | RHD_BLOB) == 0
+    }
+
     /// Uncompress the data field
     pub fn read(&self) -> Result<Vec<u8>, Error> {
-        Ok(rle_decode(&self.data))
+        Ok(rle_decode(self.data))
+    }
+
+    /// Uncompress the data field on a buffer, reusing its memory
+    pub fn read_into(&self, result: &mut Vec<u8>) -> Result<(), Error> {
+        result.clear();
+        rle_decode_into(self.data, result);
+
+        Ok(())
     }
 }
 
 /// Decode the firebird record data
-fn rle_decode(data: &Vec<u8>) -> Vec<u8> {
+fn rle_decode(data: &[u8]) -> Vec<u8> {
+    let mut result = vec![];
+    rle_decode_into(data, &mut result);
+
+    result
+}
+
+/// Decode the firebird record data at the end of the buffer
+fn rle_decode_into(data: &[u8], result: &mut Vec<u8>) {
     // The compression is a type known as Run Length Encoding (RLE)
     // More infos: https://firebirdsql.org/file/documentation/html/en/firebirddocs/firebirdinternals/firebird-internals.html#fbint-p5-examine-data
 
-    let mut result = Vec::<u8>::new();
-    let mut iter = data.iter();
-    let iter = iter.by_ref();
+    let mut pos = 0;
 
-    while let Some(n) = iter.next() {
-        let n = *n;
-        let ni = n as i8;
+    while pos < data.len() {
+        let ni = data[pos] as i8;
+        pos += 1;
 
         match ni {
             // 0 is the end of data, normally a padding byte
             0 => break,
             // The next 'n' bytes are stored 'verbatim'.
             ni if ni > 0 => {
-                for next_byte in iter.take(n.into()) {
-                    result.push(*next_byte);
-                }
+                let end = (pos + ni as usize).min(data.len());
+                result.extend_from_slice(&data[pos..end]);
+                pos = end;
             }
             // The next byte is repeated 'n' times, but stored only once.
             _ => {
-                if let Some(next_byte) = iter.next() {
-                    let to = (ni as i16).abs();
-                    for _ in 0..to {
-                        result.push(*next_byte);
-                    }
+                if let Some(next_byte) = data.get(pos) {
+                    let len = result.len() + ni.unsigned_abs() as usize;
+                    result.resize(len, *next_byte);
+                    pos += 1;
                 }
             }
         }
     }
-
-    result
 }
 
 #[cfg(test)]
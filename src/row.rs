@@ -1,8 +1,14 @@
 //! Row definition and API
 
 use byteorder::{ByteOrder, LittleEndian};
+use std::borrow::Cow;
 use std::fmt;
+use std::str::FromStr;
 
+use crate::array::*;
+use crate::blob::*;
+use crate::blr::print_blr;
+use crate::charset::*;
 use crate::column::*;
 use crate::Error;
 
@@ -10,127 +16,571 @@ use crate::Error;
 pub struct Row {
     pub raw: Vec<Vec<u8>>,
     pub values: Vec<Option<Value>>,
+    /// RDB$DB_KEY of the record, when read from a table
+    pub db_key: Option<DbKey>,
+}
+
+/// Record identifier, the same of RDB$DB_KEY
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DbKey {
+    pub relation: u16,
+    /// Record number inside of the relation, like the ones
+    /// on the index entries
+    pub record_number: u64,
+}
+
+impl DbKey {
+    /// Parse the 8 bytes of the RDB$DB_KEY
+    pub fn from_bytes(bytes: &[u8]) -> Result<DbKey, Error> {
+        if bytes.len() != 8 {
+            return Err(Error::Other(format!(
+                "DB key with {} bytes, expected 8",
+                bytes.len()
+            )));
+        }
+
+        // Same layout of the blob ids, but the record
+        // number is stored plus one
+        let id = BlobId::from_bytes(bytes)?;
+        if id.number == 0 {
+            return Err(Error::from("DB key without record number"));
+        }
+
+        Ok(DbKey {
+            relation: id.relation,
+            record_number: id.number - 1,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let number = self.record_number + 1;
+
+        let mut bytes = [0; 8];
+        LittleEndian::write_u16(&mut bytes[0..2], self.relation);
+        bytes[3] = (number >> 32) as u8;
+        LittleEndian::write_u32(&mut bytes[4..8], number as u32);
+
+        bytes
+    }
+}
+
+impl fmt::Display for DbKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Same format used by isql
+        for b in self.to_bytes() {
+            write!(f, "{:02X}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DbKey {
+    type Err = Error;
+
+    /// DB key from its hexadecimal form, like `8300000001000000`
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        let st = st.trim();
+        if st.len() != 16 || !st.is_ascii() {
+            return Err(Error::Other(format!("Invalid DB key {}", st)));
+        }
+
+        let bytes = (0..16)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&st[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|e| Error::Other(format!("Invalid DB key {}: {}", st, e)))?;
+
+        DbKey::from_bytes(&bytes)
+    }
 }
 
 impl Row {
     /// Load and prepare the row
-    pub fn load(columns: &Vec<Column>, rec_data: Vec<u8>) -> Result<Self, Error> {
-        let mut raw = vec![];
-        let mut values = vec![];
-
-        let mut readed = 4;
-        for (icol, col) in columns.iter().enumerate() {
-            if col.computed {
-                values.push(None);
-                continue;
-            }
+    pub fn load(columns: &[Column], rec_data: &[u8]) -> Result<Self, Error> {
+        Self::load_with_layout(columns, &layout(columns), rec_data)
+    }
 
-            let mut start = readed;
-            let mut end = readed + col.size + (col.scale.abs() as usize);
+    /// Load the row with the places of the columns already known,
+    /// shared by all the rows of a table
+    pub(crate) fn load_with_layout(
+        columns: &[Column],
+        layout: &[Option<(usize, usize)>],
+        rec_data: &[u8],
+    ) -> Result<Self, Error> {
+        let mut raw = Vec::with_capacity(columns.len());
+        let mut values = Vec::with_capacity(columns.len());
 
-            // 2 bytes for varying size info + regular field size
-            if col.tp == ColumnType::Varchar {
-                end = end + 2;
-            }
+        for (col, place) in columns.iter().zip(layout) {
+            let bcol = match column_bytes(*place, rec_data) {
+                Some(bcol) => bcol,
+                // computed columns aren't stored on the record
+                None => {
+                    values.push(None);
+                    continue;
+                }
+            };
 
-            // we always need even char fields size
-            if (col.tp == ColumnType::Varchar || col.tp == ColumnType::Char) && col.size % 2 != 0 {
-                end = end + 1;
-            }
+            raw.push(bcol.to_vec());
+            values.push(record_value(col, bcol, rec_data)?);
+        }
 
-            if col.tp == ColumnType::Timestamp {
-                // If the timestamp start with 0000, we shift 2 position
-                if rec_data[start..start + 4] == [0, 0, 0, 0] {
-                    start = start + 2;
-                    end = end + 2;
-                }
+        Ok(Self {
+            values,
+            raw,
+            db_key: None,
+        })
+    }
+}
 
-                // If the previous and the next columns isn't an
-                // other timestamp, we get more 2 positions
-                let next = match columns.get(icol + 1) {
-                    Some(ncol) => &ncol.tp,
-                    None => &ColumnType::Timestamp,
-                };
-                let prev = match columns.get(icol - 1) {
-                    Some(ncol) => &ncol.tp,
-                    None => &ColumnType::Integer,
-                };
-                if next != &ColumnType::Timestamp && prev != &ColumnType::Timestamp {
-                    end = end + 2;
-                }
-            }
+/// Bytes of the column on the record, none for the not stored ones
+pub(crate) fn column_bytes(place: Option<(usize, usize)>, rec_data: &[u8]) -> Option<&[u8]> {
+    let (start, end) = place?;
+    let start = start.min(rec_data.len());
+    let end = end.min(rec_data.len());
+
+    Some(&rec_data[start..end])
+}
+
+/// Value of the column on the record, with the blobs and arrays
+/// still as its ids
+pub(crate) fn record_value(
+    col: &Column,
+    bcol: &[u8],
+    rec_data: &[u8],
+) -> Result<Option<Value>, Error> {
+    if !has_value(col, bcol, rec_data) {
+        return Ok(None);
+    }
+
+    if col.is_array() {
+        // The array content is loaded like the blobs
+        return parse_blob(bcol)
+            .map_err(|e| Error::Other(format!("Parsing {} as array: {}", col.name, e)));
+    }
+
+    parse_value(col, bcol)
+}
+
+/// Check if the column has a value on the record, not null
+pub(crate) fn has_value(col: &Column, bcol: &[u8], rec_data: &[u8]) -> bool {
+    // Records of older formats can be shorter
+    !is_null(col, rec_data) && bcol.len() >= col.storage_size()
+}
+
+/// Text of a char or varchar column, borrowing the bytes when they
+/// are already valid UTF-8
+pub(crate) fn parse_text<'b>(col: &Column, bytes: &'b [u8]) -> Result<Cow<'b, str>, Error> {
+    let text = match col.tp {
+        ColumnType::Char => char_text(col, bytes),
+        ColumnType::Varchar => varchar_bytes(bytes).and_then(|bytes| {
+            col.charset
+                .decode_borrowed(bytes)
+                .map_err(|e| e.to_string())
+        }),
+        _ => return Err(Error::Other(format!("Column {} isn't a text", col.name))),
+    };
+
+    text.map_err(|e| Error::Other(format!("Parsing {} as text: {}", col.name, e)))
+}
+
+/// Parse the bytes of a single value of the column type, the same
+/// values decoded from the index keys of the column
+pub(crate) fn parse_value(col: &Column, bytes: &[u8]) -> Result<Option<Value>, Error> {
+    let val = match col.tp {
+        ColumnType::Varchar => parse_varchar(col, bytes)
+            .map_err(|e| Error::Other(format!("Parsing {} as varchar: {}", col.name, e)))?,
+        ColumnType::Char => parse_char(col, bytes)
+            .map_err(|e| Error::Other(format!("Parsing {} as char: {}", col.name, e)))?,
+        ColumnType::Integer => parse_integer(col, bytes)
+            .map_err(|e| Error::Other(format!("Parsing {} as integer: {}", col.name, e)))?,
+        ColumnType::Smallint => parse_smallinteger(col, bytes)
+            .map_err(|e| Error::Other(format!("Parsing {} as small integer: {}", col.name, e)))?,
+        ColumnType::Bigint => parse_biginteger(col, bytes)
+            .map_err(|e| Error::Other(format!("Parsing {} as big integer: {}", col.name, e)))?,
+        ColumnType::Float => Some(Value::Float(LittleEndian::read_f32(bytes))),
+        ColumnType::DoublePrecision => Some(Value::Double(LittleEndian::read_f64(bytes))),
+        ColumnType::Date => Some(Value::Date(LittleEndian::read_i32(bytes))),
+        ColumnType::Time => Some(Value::Time(LittleEndian::read_u32(bytes))),
+        ColumnType::Timestamp => Some(Value::Timestamp(
+            LittleEndian::read_i32(&bytes[0..4]),
+            LittleEndian::read_u32(&bytes[4..8]),
+        )),
+        ColumnType::Blob => parse_blob(bytes)
+            .map_err(|e| Error::Other(format!("Parsing {} as blob: {}", col.name, e)))?,
+    };
 
-            if end > rec_data.len() {
-                end = rec_data.len();
+    Ok(val)
+}
+
+/// Parse the content of an array blob, nesting the elements
+/// by the column dimensions
+pub fn parse_array(col: &Column, content: &[u8]) -> Result<Value, Error> {
+    if col.dimensions.is_empty() {
+        return Err(Error::Other(format!("Column {} isn't an array", col.name)));
+    }
+
+    let desc = ArrayDescriptor::from_bytes(content)?;
+
+    let count: i32 = col
+        .dimensions
+        .iter()
+        .map(|(lower, upper)| (upper - lower + 1).max(0))
+        .product();
+    if count != desc.count {
+        return Err(Error::Other(format!(
+            "Array {} with {} elements, expected {}",
+            col.name, desc.count, count
+        )));
+    }
+
+    let mut elements = vec![];
+    for bytes in desc.elements(content)? {
+        if bytes.len() < col.element_size() {
+            return Err(Error::Other(format!(
+                "Array {} with elements of {} bytes",
+                col.name,
+                bytes.len()
+            )));
+        }
+        elements.push(parse_value(col, bytes)?);
+    }
+
+    Ok(nest_array(&mut elements.into_iter(), &col.dimensions))
+}
+
+pub(crate) fn nest_array(
+    elements: &mut impl Iterator<Item = Option<Value>>,
+    dims: &[(i32, i32)],
+) -> Value {
+    let (lower, upper) = dims[0];
+
+    let values = (lower..=upper)
+        .map(|_| {
+            if dims.len() == 1 {
+                elements.next().flatten()
+            } else {
+                Some(nest_array(elements, &dims[1..]))
             }
+        })
+        .collect();
 
-            let bcol = &rec_data[start..end];
-            raw.push(bcol.to_vec());
+    Value::Array(values)
+}
 
-            let val = match col.tp {
-                ColumnType::Varchar => parse_varchar(bcol.to_vec())
-                    .map_err(|e| Error::Other(format!("Parsing {} as varchar: {}", col.name, e)))?,
-                ColumnType::Char => parse_char(col.size, bcol.to_vec())
-                    .map_err(|e| Error::Other(format!("Parsing {} as char: {}", col.name, e)))?,
-                ColumnType::Integer if col.scale == 0 => parse_integer(bcol.to_vec())
-                    .map_err(|e| Error::Other(format!("Parsing {} as integer: {}", col.name, e)))?,
-                ColumnType::Smallint => parse_smallinteger(bcol.to_vec()).map_err(|e| {
-                    Error::Other(format!("Parsing {} as small integer: {}", col.name, e))
-                })?,
-                _ => None,
-            };
-            values.push(val);
+/// Value of a blob column from the blob content
+pub(crate) fn parse_blob_content(col: &Column, content: Vec<u8>) -> Result<Value, Error> {
+    let val = if col.is_text_blob() {
+        let st = col
+            .charset
+            .decode(&content)
+            .map_err(|e| Error::Other(format!("Parsing {} as text blob: {}", col.name, e)))?;
+        Value::String(st)
+    } else if col.is_blr_blob() {
+        Value::Blr(content)
+    } else {
+        Value::Bytes(content)
+    };
 
-            readed = end;
+    Ok(val)
+}
+
+/// Start and end of each column on the record, `None` for the
+/// not stored ones
+///
+/// The record starts with the null flags, followed by the columns
+/// in the field id order, each one aligned by its size. The end
+/// of a column goes until the start of the next one, padding
+/// included.
+pub(crate) fn layout(columns: &[Column]) -> Vec<Option<(usize, usize)>> {
+    let count = columns.iter().map(|c| c.id + 1).max().unwrap_or(0);
+
+    // Null flags are stored in blocks of 32 bits
+    let mut offset = ((count + 32) & !31) >> 3;
+
+    let mut order: Vec<usize> = (0..columns.len())
+        .filter(|i| !columns[*i].computed)
+        .collect();
+    order.sort_by_key(|i| columns[*i].id);
+
+    let mut starts = vec![None; columns.len()];
+    for i in order.iter() {
+        let col = &columns[*i];
+        let align = col.alignment();
+
+        offset = offset.div_ceil(align) * align;
+        starts[*i] = Some(offset);
+        offset += col.storage_size();
+    }
+
+    let mut places = vec![None; columns.len()];
+    for (pos, i) in order.iter().enumerate() {
+        if let Some(start) = starts[*i] {
+            let end = match order.get(pos + 1) {
+                Some(next) => starts[*next].unwrap_or(start),
+                None => start + columns[*i].storage_size(),
+            };
+            places[*i] = Some((start, end));
         }
+    }
 
-        Ok(Self { values, raw })
+    places
+}
+
+/// Check the null flag of the column on the record
+fn is_null(col: &Column, rec_data: &[u8]) -> bool {
+    match rec_data.get(col.id / 8) {
+        Some(flags) => flags & (1 << (col.id % 8)) != 0,
+        None => true,
     }
 }
 
 /// Cell value of a row
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     String(String),
     Int(i32),
     SmallInt(i16),
+    Blob(BlobId),
+    Bytes(Vec<u8>),
+    Blr(Vec<u8>),
+    Array(Vec<Option<Value>>),
+    BigInt(i64),
+    Float(f32),
+    Double(f64),
+    /// Scaled integer of the NUMERIC and DECIMAL columns
+    Decimal(i64, i16),
+    /// Days since 1858-11-17
+    Date(i32),
+    /// Time of the day in 1/10000 seconds
+    Time(u32),
+    Timestamp(i32, u32),
+}
+
+macro_rules! value_from {
+    ($($tp:ty => $variant:ident),*) => {
+        $(
+            impl From<$tp> for Value {
+                fn from(v: $tp) -> Self {
+                    Value::$variant(v.into())
+                }
+            }
+        )*
+    };
 }
 
+value_from!(i16 => SmallInt, i32 => Int, i64 => BigInt, f32 => Float, f64 => Double, String => String, &str => String);
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Value::String(v) => write!(f, "{}", v),
             Value::Int(v) => write!(f, "{}", v),
             Value::SmallInt(v) => write!(f, "{}", v),
+            Value::Blob(v) => write!(f, "{}", v),
+            Value::Bytes(v) => write_hex(f, v),
+            Value::Blr(v) => match print_blr(v) {
+                Ok(st) => write!(f, "{}", st),
+                Err(_) => write_hex(f, v),
+            },
+            Value::Array(v) => {
+                write!(f, "[")?;
+                for (i, val) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    if let Some(val) = val {
+                        write!(f, "{}", val)?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Value::BigInt(v) => write!(f, "{}", v),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Double(v) => write!(f, "{}", v),
+            Value::Decimal(v, scale) => write_decimal(f, *v, *scale),
+            Value::Date(v) => write_date(f, *v),
+            Value::Time(v) => write_time(f, *v),
+            Value::Timestamp(date, time) => {
+                write_date(f, *date)?;
+                write!(f, " ")?;
+                write_time(f, *time)
+            }
         }
     }
 }
 
-fn parse_smallinteger(bytes: Vec<u8>) -> Result<Option<Value>, String> {
-    let it = LittleEndian::read_i16(&bytes);
+fn write_decimal(f: &mut fmt::Formatter, value: i64, scale: i16) -> fmt::Result {
+    if scale >= 0 {
+        let scaled = 10i128
+            .checked_pow(scale as u32)
+            .and_then(|pow| (value as i128).checked_mul(pow));
+        return match scaled {
+            Some(scaled) => write!(f, "{}", scaled),
+            None if value == 0 => write!(f, "0"),
+            None => write!(f, "{}{}", value, "0".repeat(scale as usize)),
+        };
+    }
+
+    let digits = -(scale as i32) as usize;
+    let abs = (value as i128).unsigned_abs();
+    let sign = if value < 0 { "-" } else { "" };
+
+    match 10u128.checked_pow(digits as u32) {
+        Some(div) => write!(f, "{}{}.{:0digits$}", sign, abs / div, abs % div),
+        // Bigger than any i64, only the leading zeros are missing
+        None => write!(f, "{}0.{:0digits$}", sign, abs),
+    }
+}
+
+fn write_date(f: &mut fmt::Formatter, days: i32) -> fmt::Result {
+    let (year, month, day) = civil_date(days);
+
+    write!(f, "{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn write_time(f: &mut fmt::Formatter, time: u32) -> fmt::Result {
+    let secs = time / 10000;
+
+    write!(
+        f,
+        "{:02}:{:02}:{:02}.{:04}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        time % 10000
+    )
+}
+
+/// Year, month and day of the days since 1858-11-17
+pub fn civil_date(days: i32) -> (i64, u32, u32) {
+    // Shift to days since 0000-03-01, so the leap day is
+    // the last one of the year
+    let days = days as i64 + 678881;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Days since 1858-11-17 of the date, none when it doesn't exist
+pub(crate) fn civil_days(year: i64, month: u32, day: u32) -> Option<i32> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Inverse of the civil_date
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = i32::try_from(era * 146097 + doe - 678881).ok()?;
+
+    // Days like 02-30 would be moved to the next month
+    let (_, m, d) = civil_date(days);
+    if (m, d) != (month, day) {
+        return None;
+    }
+
+    Some(days)
+}
+
+fn write_hex(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    for b in bytes {
+        write!(f, "{:02x}", b)?;
+    }
+    Ok(())
+}
+
+fn parse_smallinteger(col: &Column, bytes: &[u8]) -> Result<Option<Value>, String> {
+    let it = LittleEndian::read_i16(bytes);
+
+    if col.scale != 0 {
+        return Ok(Some(Value::Decimal(it as i64, col.scale)));
+    }
 
     Ok(Some(Value::SmallInt(it)))
 }
 
-fn parse_integer(bytes: Vec<u8>) -> Result<Option<Value>, String> {
-    let it = LittleEndian::read_i32(&bytes);
+fn parse_integer(col: &Column, bytes: &[u8]) -> Result<Option<Value>, String> {
+    let it = LittleEndian::read_i32(bytes);
+
+    if col.scale != 0 {
+        return Ok(Some(Value::Decimal(it as i64, col.scale)));
+    }
 
     Ok(Some(Value::Int(it)))
 }
 
-fn parse_char(size: usize, bytes: Vec<u8>) -> Result<Option<Value>, String> {
-    let bytes = bytes[0..size].to_vec();
-    let st = String::from_utf8(bytes)
-        .map_err(|e| format!("Found column with an invalid UTF-8 string: {}", e))?;
+fn parse_biginteger(col: &Column, bytes: &[u8]) -> Result<Option<Value>, String> {
+    let it = LittleEndian::read_i64(bytes);
+
+    if col.scale != 0 {
+        return Ok(Some(Value::Decimal(it, col.scale)));
+    }
+
+    Ok(Some(Value::BigInt(it)))
+}
+
+fn parse_char(col: &Column, bytes: &[u8]) -> Result<Option<Value>, String> {
+    if col.charset == Charset::Octets {
+        return Ok(Some(Value::Bytes(bytes[0..col.size].to_vec())));
+    }
+
+    Ok(Some(Value::String(char_text(col, bytes)?.into_owned())))
+}
 
-    Ok(Some(Value::String(st)))
+fn char_text<'b>(col: &Column, bytes: &'b [u8]) -> Result<Cow<'b, str>, String> {
+    let bytes = &bytes[0..col.size];
+
+    let st = col
+        .charset
+        .decode_borrowed(bytes)
+        .map_err(|e| e.to_string())?;
+
+    // The size is in bytes, so multi-byte charsets are
+    // padded with more spaces than the characters length
+    let end = match st.char_indices().nth(col.char_length) {
+        Some((end, _)) if col.char_length > 0 => end,
+        _ => st.len(),
+    };
+
+    Ok(match st {
+        Cow::Borrowed(st) => Cow::Borrowed(&st[..end]),
+        Cow::Owned(mut st) => {
+            st.truncate(end);
+            Cow::Owned(st)
+        }
+    })
 }
 
-fn parse_varchar(bytes: Vec<u8>) -> Result<Option<Value>, String> {
+fn parse_varchar(col: &Column, bytes: &[u8]) -> Result<Option<Value>, String> {
+    let bytes = varchar_bytes(bytes)?;
+
+    if col.charset == Charset::Octets {
+        return Ok(Some(Value::Bytes(bytes.to_vec())));
+    }
+
+    let st = col
+        .charset
+        .decode_borrowed(bytes)
+        .map_err(|e| e.to_string())?;
+
+    Ok(Some(Value::String(st.into_owned())))
+}
+
+fn varchar_bytes(bytes: &[u8]) -> Result<&[u8], String> {
     // varchar format:
     // {size}\0{byte}{byte}{byte}\0\0...
 
-    let end = (bytes[0] + 2) as usize;
+    let end = LittleEndian::read_u16(bytes) as usize + 2;
     if end > bytes.len() {
         return Err(format!(
             "Varchar size {} > {} buffer size",
@@ -138,10 +588,12 @@ fn parse_varchar(bytes: Vec<u8>) -> Result<Option<Value>, String> {
             bytes.len()
         ));
     }
-    let bytes = bytes[2..end].to_vec();
 
-    let st = String::from_utf8(bytes)
-        .map_err(|e| format!("Found column with an invalid UTF-8 string: {}", e))?;
+    Ok(&bytes[2..end])
+}
+
+fn parse_blob(bytes: &[u8]) -> Result<Option<Value>, String> {
+    let id = BlobId::from_bytes(bytes).map_err(|e| e.to_string())?;
 
-    Ok(Some(Value::String(st)))
+    Ok(Some(Value::Blob(id)))
 }
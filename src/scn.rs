@@ -0,0 +1,76 @@
+//! Firebird SCN inventory page representation
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::page::*;
+use crate::Error;
+
+/// Size of the SCN page header, before the SCNs
+const SCN_SIZE: usize = 20;
+
+/// SCN Inventory Page
+///
+/// Keeps a copy of the system change number of each page, so the
+/// pages changed since a given SCN, like by nbackup, can be found
+/// without reading the whole database. The first SCN page is always
+/// the page 2, the next ones start each range of pages they cover.
+#[derive(Debug, Clone)]
+pub struct ScnPage {
+    pub pag: Page,
+    /// Sequence number of the page
+    pub sequence: u32,
+    /// SCN of each page covered by this page
+    pub scns: Vec<u32>,
+}
+
+impl ScnPage {
+    /// Parse the ScnPage from bytes
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<ScnPage, Error> {
+        let pag = Page::from_bytes(&bytes)?;
+        if pag.ptype != 0x0a {
+            return Err(Error::InvalidPage {
+                tpe: pag.ptype,
+                expected: 0x0a,
+                desc: "SCN inventory".to_string(),
+            });
+        }
+        // At least one SCN after the header
+        if bytes.len() < SCN_SIZE + 4 {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: SCN_SIZE + 4,
+                msg: "SCN inventory page header".to_string(),
+            });
+        }
+
+        let per_page = Self::pages_per_scn(bytes.len().min(u16::MAX as usize) as u16);
+        let end = SCN_SIZE + per_page * 4;
+
+        let scns = bytes[SCN_SIZE..end]
+            .chunks_exact(4)
+            .map(LittleEndian::read_u32)
+            .collect();
+
+        Ok(ScnPage {
+            pag,
+            sequence: LittleEndian::read_u32(&bytes[16..20]),
+            scns,
+        })
+    }
+
+    /// Amount of pages tracked by each SCN page, the biggest
+    /// power of two that fits on the page
+    pub fn pages_per_scn(page_size: u16) -> usize {
+        let max = (page_size as usize - SCN_SIZE) / 4;
+
+        1 << (usize::BITS - 1 - max.leading_zeros())
+    }
+
+    /// Page number of the SCN page of a given sequence
+    pub fn scn_page(page_size: u16, sequence: u32) -> u32 {
+        match sequence {
+            0 => 2,
+            _ => sequence * Self::pages_per_scn(page_size) as u32,
+        }
+    }
+}
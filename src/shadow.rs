@@ -0,0 +1,132 @@
+//! Firebird database shadows representation
+
+use crate::page::PageType;
+use crate::row::Value;
+use crate::table::Table;
+use crate::Error;
+
+/// RDB$FILE_FLAGS bits of the shadow files
+const FILE_INACTIVE: i32 = 2;
+const FILE_MANUAL: i32 = 4;
+const FILE_CONDITIONAL: i32 = 16;
+
+/// Shadow of the database
+///
+/// A shadow is a page by page copy of the database, kept updated
+/// by the engine and declared on RDB$FILES. Like the database, it
+/// can be split on several files. The header of each shadow file
+/// is flagged as shadow and names the database it copies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shadow {
+    /// RDB$SHADOW_NUMBER of the shadow
+    pub number: i16,
+    pub files: Vec<ShadowFile>,
+    pub inactive: bool,
+    /// The database keeps working when the shadow is unavailable
+    pub manual: bool,
+    /// Created only when another shadow replaces the database
+    pub conditional: bool,
+}
+
+/// File of a shadow
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowFile {
+    pub name: String,
+    pub sequence: i16,
+    /// First page stored on the file
+    pub start: i32,
+    /// Amount of pages of the file, 0 for the last one
+    pub length: i32,
+}
+
+impl Shadow {
+    /// Load the shadows declared on the RDB$FILES table
+    pub fn load(tables: Vec<Table>) -> Result<Vec<Shadow>, Error> {
+        let table = match tables.into_iter().find(|t| t.name == "RDB$FILES") {
+            Some(table) => table,
+            None => return Ok(vec![]),
+        };
+
+        let mut ptable = table.prepare()?;
+        let column = |name: &str| {
+            ptable
+                .columns
+                .iter()
+                .position(|c| c.name == name)
+                .ok_or_else(|| Error::Other(format!("Column {} not found on RDB$FILES", name)))
+        };
+        let name = column("RDB$FILE_NAME")?;
+        let sequence = column("RDB$FILE_SEQUENCE")?;
+        let start = column("RDB$FILE_START")?;
+        let length = column("RDB$FILE_LENGTH")?;
+        let flags = column("RDB$FILE_FLAGS")?;
+        let number = column("RDB$SHADOW_NUMBER")?;
+
+        let mut shadows: Vec<Shadow> = vec![];
+
+        while let Some(row) = ptable.read()? {
+            // The secondary files of the database are on the same
+            // table, without shadow number
+            let shadow = integer(&row.values[number]) as i16;
+            if shadow == 0 {
+                continue;
+            }
+
+            let file = ShadowFile {
+                name: match &row.values[name] {
+                    Some(Value::String(name)) => name.trim_end().to_string(),
+                    _ => String::new(),
+                },
+                sequence: integer(&row.values[sequence]) as i16,
+                start: integer(&row.values[start]),
+                length: integer(&row.values[length]),
+            };
+            let flags = integer(&row.values[flags]);
+
+            let pos = match shadows.iter().position(|s| s.number == shadow) {
+                Some(pos) => pos,
+                None => {
+                    shadows.push(Shadow {
+                        number: shadow,
+                        files: vec![],
+                        inactive: false,
+                        manual: false,
+                        conditional: false,
+                    });
+                    shadows.len() - 1
+                }
+            };
+
+            let shadow = &mut shadows[pos];
+            shadow.inactive |= flags & FILE_INACTIVE != 0;
+            shadow.manual |= flags & FILE_MANUAL != 0;
+            shadow.conditional |= flags & FILE_CONDITIONAL != 0;
+            shadow.files.push(file);
+        }
+
+        for shadow in shadows.iter_mut() {
+            shadow.files.sort_by_key(|f| f.sequence);
+        }
+        shadows.sort_by_key(|s| s.number);
+
+        Ok(shadows)
+    }
+}
+
+/// Page differing between a database and its shadow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageDifference {
+    pub pageno: u32,
+    /// Type of the page on the database, none when missing
+    pub database: Option<PageType>,
+    /// Type of the page on the shadow, none when missing
+    pub shadow: Option<PageType>,
+}
+
+fn integer(val: &Option<Value>) -> i32 {
+    match val {
+        Some(Value::SmallInt(v)) => *v as i32,
+        Some(Value::Int(v)) => *v,
+        _ => 0,
+    }
+}
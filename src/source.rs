@@ -0,0 +1,355 @@
+//! Sources of the database pages
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::ops::{Deref, Range};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::file::DatabaseFile;
+use crate::nbackup::DeltaFile;
+use crate::Error;
+
+/// Source shared by the database, its tables and its indexes
+pub(crate) type Source = Arc<SharedSource>;
+
+/// Pages kept on the cache by default
+const DEFAULT_CACHE_PAGES: usize = 1024;
+
+/// Bytes of a database, read page by page
+///
+/// The database files, `File`, `Cursor` and `BufReader` of other
+/// sources are already sources. Any other `Read + Seek + Send`
+/// becomes one with an empty implementation, or is read with
+/// `Database::open_reader`.
+pub trait PageSource: Read + Seek + Send {
+    /// Size of the whole database, in bytes
+    fn size(&mut self) -> Result<u64, Error> {
+        let pos = self.stream_position()?;
+        let len = self.seek(SeekFrom::End(0))?;
+        self.seek(SeekFrom::Start(pos))?;
+
+        Ok(len)
+    }
+
+    /// Paths of the files of the database, empty when not read
+    /// from files
+    fn files(&self) -> Vec<&Path> {
+        vec![]
+    }
+
+    /// nbackup delta file overlaid on the database
+    fn delta(&self) -> Option<&DeltaFile> {
+        None
+    }
+
+    /// Content of a page, copied from the source by default
+    fn page_bytes(&mut self, page_size: u16, pageno: u32) -> Result<PageBytes, Error> {
+        self.seek(SeekFrom::Start(pageno as u64 * page_size as u64))?;
+
+        let mut poip = vec![0u8; page_size.into()];
+        self.read_exact(&mut poip)?;
+
+        Ok(PageBytes::from(poip))
+    }
+}
+
+/// Source of pages shared by the threads, with a cache of the
+/// pages read last
+pub(crate) struct SharedSource {
+    source: Mutex<Box<dyn PageSource>>,
+    cache: Mutex<PageCache>,
+}
+
+/// Pages read, the oldest ones evicted first
+struct PageCache {
+    pages: HashMap<u32, PageBytes>,
+    order: VecDeque<u32>,
+    capacity: usize,
+}
+
+impl SharedSource {
+    pub fn new<S: PageSource + 'static>(source: S) -> Source {
+        Arc::new(SharedSource {
+            source: Mutex::new(Box::new(source)),
+            cache: Mutex::new(PageCache {
+                pages: HashMap::new(),
+                order: VecDeque::new(),
+                capacity: DEFAULT_CACHE_PAGES,
+            }),
+        })
+    }
+
+    /// Exclusive access to the source, for reading outside of the
+    /// cache
+    pub fn lock(&self) -> MutexGuard<'_, Box<dyn PageSource>> {
+        // The sources are only read, a panic while holding the lock
+        // can't leave them inconsistent
+        self.source.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn cache(&self) -> MutexGuard<'_, PageCache> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Content of a page, from the cache when read before
+    pub fn page(&self, page_size: u16, pageno: u32) -> Result<PageBytes, Error> {
+        if let Some(bytes) = self.cache().pages.get(&pageno) {
+            if bytes.len() == page_size as usize {
+                return Ok(bytes.clone());
+            }
+        }
+
+        let bytes = self.lock().page_bytes(page_size, pageno)?;
+
+        let mut cache = self.cache();
+        if cache.capacity > 0 && cache.pages.insert(pageno, bytes.clone()).is_none() {
+            cache.order.push_back(pageno);
+            cache.shrink();
+        }
+
+        Ok(bytes)
+    }
+
+    /// Change the amount of pages kept on the cache, 0 disables it
+    pub fn set_cache_size(&self, pages: usize) {
+        let mut cache = self.cache();
+        cache.capacity = pages;
+        cache.shrink();
+    }
+}
+
+impl fmt::Debug for SharedSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedSource")
+            .field("cached_pages", &self.cache().pages.len())
+            .finish()
+    }
+}
+
+impl PageCache {
+    fn shrink(&mut self) {
+        while self.order.len() > self.capacity {
+            if let Some(pageno) = self.order.pop_front() {
+                self.pages.remove(&pageno);
+            }
+        }
+    }
+}
+
+/// Bytes of a page, owned or borrowed from the database kept
+/// on memory
+#[derive(Clone)]
+pub struct PageBytes(Bytes);
+
+#[derive(Clone)]
+enum Bytes {
+    Owned(Arc<Vec<u8>>),
+    Shared(Arc<dyn AsRef<[u8]> + Send + Sync>, Range<usize>),
+}
+
+impl PageBytes {
+    /// Check if the bytes are borrowed, without a copy of its own
+    pub fn is_shared(&self) -> bool {
+        matches!(self.0, Bytes::Shared(..))
+    }
+
+    /// Owned bytes of the page, copied when borrowed
+    pub fn into_vec(self) -> Vec<u8> {
+        match self.0 {
+            Bytes::Owned(bytes) => Arc::try_unwrap(bytes).unwrap_or_else(|b| (*b).clone()),
+            Bytes::Shared(..) => self.to_vec(),
+        }
+    }
+}
+
+impl Deref for PageBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.0 {
+            Bytes::Owned(bytes) => bytes,
+            Bytes::Shared(bytes, range) => &(**bytes).as_ref()[range.clone()],
+        }
+    }
+}
+
+impl From<Vec<u8>> for PageBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        PageBytes(Bytes::Owned(Arc::new(bytes)))
+    }
+}
+
+impl fmt::Debug for PageBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PageBytes")
+            .field("len", &self.len())
+            .field("shared", &self.is_shared())
+            .finish()
+    }
+}
+
+impl PartialEq for PageBytes {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl PageSource for DatabaseFile {
+    fn size(&mut self) -> Result<u64, Error> {
+        DatabaseFile::len(self)
+    }
+
+    fn files(&self) -> Vec<&Path> {
+        DatabaseFile::files(self)
+    }
+
+    fn delta(&self) -> Option<&DeltaFile> {
+        DatabaseFile::delta(self)
+    }
+}
+
+impl PageSource for File {
+    fn size(&mut self) -> Result<u64, Error> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+impl<S: PageSource> PageSource for BufReader<S> {
+    fn size(&mut self) -> Result<u64, Error> {
+        self.get_mut().size()
+    }
+
+    fn files(&self) -> Vec<&Path> {
+        self.get_ref().files()
+    }
+
+    fn delta(&self) -> Option<&DeltaFile> {
+        self.get_ref().delta()
+    }
+}
+
+/// Database on memory, or memory mapped
+impl<T: AsRef<[u8]> + Send> PageSource for Cursor<T> {
+    fn size(&mut self) -> Result<u64, Error> {
+        Ok(self.get_ref().as_ref().len() as u64)
+    }
+}
+
+/// Database kept on memory, like a memory mapped file, whose data
+/// pages are read without copies
+pub(crate) struct MemorySource {
+    bytes: Arc<dyn AsRef<[u8]> + Send + Sync>,
+    pos: u64,
+}
+
+impl MemorySource {
+    pub fn new<T: AsRef<[u8]> + Send + Sync + 'static>(bytes: T) -> Self {
+        MemorySource {
+            bytes: Arc::new(bytes),
+            pos: 0,
+        }
+    }
+
+    fn content(&self) -> &[u8] {
+        (*self.bytes).as_ref()
+    }
+}
+
+impl Read for MemorySource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes = self.content();
+        let start = (self.pos as usize).min(bytes.len());
+        let size = buf.len().min(bytes.len() - start);
+
+        buf[..size].copy_from_slice(&bytes[start..start + size]);
+        self.pos += size as u64;
+
+        Ok(size)
+    }
+}
+
+impl Seek for MemorySource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+            SeekFrom::End(n) => (self.content().len() as u64).checked_add_signed(n),
+        };
+
+        self.pos = new.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative position")
+        })?;
+
+        Ok(self.pos)
+    }
+}
+
+impl PageSource for MemorySource {
+    fn size(&mut self) -> Result<u64, Error> {
+        Ok(self.content().len() as u64)
+    }
+
+    fn page_bytes(&mut self, page_size: u16, pageno: u32) -> Result<PageBytes, Error> {
+        let start = pageno as usize * page_size as usize;
+        let end = start + page_size as usize;
+        if end > self.content().len() {
+            return Err(Error::Other(format!(
+                "Page {} beyond the end of the database",
+                pageno
+            )));
+        }
+
+        Ok(PageBytes(Bytes::Shared(self.bytes.clone(), start..end)))
+    }
+}
+
+/// Source of a reader not implementing the trait by itself
+pub(crate) struct ReaderSource<R>(pub R);
+
+impl<R: Read> Read for ReaderSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for ReaderSource<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+impl<R: Read + Seek + Send> PageSource for ReaderSource<R> {}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    #[test]
+    pub fn caching_pages() -> Result<(), Error> {
+        let bytes: Vec<u8> = (0..4u8).flat_map(|p| vec![p; 1024]).collect();
+        let source = SharedSource::new(Cursor::new(bytes));
+        source.set_cache_size(2);
+
+        for pageno in [0, 1, 2, 1] {
+            assert_eq!(
+                vec![pageno as u8; 1024],
+                source.page(1024, pageno)?.to_vec()
+            );
+        }
+        {
+            let cache = source.cache();
+            assert_eq!(VecDeque::from([1, 2]), cache.order);
+            assert!(!cache.pages.contains_key(&0));
+        }
+
+        source.set_cache_size(0);
+        assert_eq!(vec![3; 1024], source.page(1024, 3)?.to_vec());
+        assert!(source.cache().pages.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1410 @@
+//! Read-only SQL queries over the tables
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use sqlparser::ast::{self, BinaryOperator, DataType, UnaryOperator};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+
+use crate::column::*;
+use crate::filter::*;
+use crate::key::is_supported;
+use crate::row::*;
+use crate::Error;
+use crate::Table;
+
+/// Rows returned by a query
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    /// Names of the columns, or their aliases
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Option<Value>>>,
+    /// How the tables were read, like the PLAN of Firebird
+    pub plan: String,
+}
+
+/// Run a SELECT on the tables
+///
+/// Supports the projections, WHERE, inner joins, GROUP BY with
+/// COUNT, SUM, AVG, MIN and MAX, HAVING, DISTINCT, ORDER BY, LIMIT
+/// and OFFSET. The conditions on a single table are checked while
+/// reading it, and the equalities on the first column of an index
+/// are looked up on the index.
+pub fn query(tables: &[Table], sql: &str) -> Result<QueryResult, Error> {
+    let statements = Parser::parse_sql(&GenericDialect {}, sql)
+        .map_err(|e| Error::Other(format!("Invalid query: {}", e)))?;
+
+    let query = match statements.as_slice() {
+        [ast::Statement::Query(query)] => query,
+        _ => return Err(Error::from("Only a single SELECT is supported")),
+    };
+
+    QueryPlan::new(tables, query)?.run()
+}
+
+/// Expression with its columns bound to the tables of the query
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    /// Source and slot among the columns read from it
+    Column(usize, usize),
+    Literal(Option<Value>),
+    Negate(Box<Expr>),
+    Compare(Box<Expr>, Operator, Box<Expr>),
+    In(Box<Expr>, Vec<Expr>),
+    Like(Box<Expr>, Box<Expr>),
+    IsNull(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// Result of an aggregate of the query
+    Aggregate(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Aggregate {
+    function: Function,
+    /// None for the COUNT(*)
+    arg: Option<Expr>,
+    distinct: bool,
+}
+
+/// Values of the joined row and of the aggregates of its group
+struct Context<'r> {
+    row: &'r [Option<Value>],
+    offsets: &'r [usize],
+    aggregates: &'r [Option<Value>],
+}
+
+/// Table read by the query
+struct Source<'t> {
+    table: &'t Table,
+    /// Alias or name of the table
+    name: String,
+    columns: Vec<Column>,
+    /// Positions of the columns read, the slots of the row
+    used: Vec<usize>,
+    filter: Option<Filter>,
+    /// Index and value for looking up the rows
+    lookup: Option<(String, Value)>,
+    /// Index, column and the value of the other tables for looking
+    /// up the rows of each joined row
+    join_lookup: Option<(String, usize, Expr)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SortValue {
+    Output(usize),
+    Hidden(usize),
+}
+
+struct SortKey {
+    value: SortValue,
+    descending: bool,
+    nulls_first: bool,
+}
+
+struct QueryPlan<'t> {
+    sources: Vec<Source<'t>>,
+    aggregates: Vec<Aggregate>,
+    /// Conditions checked after reading each source
+    conditions: Vec<Vec<Expr>>,
+    grouping: Option<Vec<Expr>>,
+    having: Option<Expr>,
+    columns: Vec<String>,
+    outputs: Vec<Expr>,
+    /// Values only used for sorting the rows
+    hidden: Vec<Expr>,
+    order: Vec<SortKey>,
+    distinct: bool,
+    offset: usize,
+    limit: Option<usize>,
+    /// Offset and limit applied while reading the only table
+    scan_range: Option<(usize, Option<usize>)>,
+}
+
+impl<'t> QueryPlan<'t> {
+    fn new(tables: &'t [Table], query: &ast::Query) -> Result<Self, Error> {
+        if query.with.is_some()
+            || query
+                .fetch
+                .as_ref()
+                .is_some_and(|f| f.percent || f.with_ties)
+        {
+            return Err(Error::Other(format!("Unsupported query {}", query)));
+        }
+        let select = match query.body.as_ref() {
+            ast::SetExpr::Select(select) => select,
+            _ => return Err(Error::from("Only a single SELECT is supported")),
+        };
+        if select.into.is_some()
+            || select.top.is_some()
+            || matches!(select.distinct, Some(ast::Distinct::On(_)))
+        {
+            return Err(Error::Other(format!("Unsupported query {}", query)));
+        }
+
+        let mut plan = QueryPlan {
+            sources: vec![],
+            aggregates: vec![],
+            conditions: vec![],
+            grouping: None,
+            having: None,
+            columns: vec![],
+            outputs: vec![],
+            hidden: vec![],
+            order: vec![],
+            distinct: matches!(select.distinct, Some(ast::Distinct::Distinct)),
+            offset: 0,
+            limit: None,
+            scan_range: None,
+        };
+
+        // Inner joins and WHERE have the same conditions
+        let mut conditions = vec![];
+        for from in &select.from {
+            plan.add_source(tables, &from.relation)?;
+            for join in &from.joins {
+                plan.add_source(tables, &join.relation)?;
+                match &join.join_operator {
+                    ast::JoinOperator::Inner(ast::JoinConstraint::On(on)) => {
+                        conditions.push(on.clone())
+                    }
+                    ast::JoinOperator::CrossJoin => {}
+                    _ => return Err(Error::from("Only inner joins with ON are supported")),
+                }
+            }
+        }
+        if plan.sources.is_empty() {
+            return Err(Error::from("Query without tables"));
+        }
+        conditions.extend(select.selection.clone());
+
+        for item in &select.projection {
+            plan.add_output(item)?;
+        }
+
+        plan.having = match &select.having {
+            Some(having) => Some(plan.compile(having, true)?),
+            None => None,
+        };
+
+        let mut conjuncts = vec![];
+        for condition in &conditions {
+            split_and(plan.compile(condition, false)?, &mut conjuncts);
+        }
+
+        match &select.group_by {
+            ast::GroupByExpr::Expressions(exprs, modifiers) if modifiers.is_empty() => {
+                if !exprs.is_empty() {
+                    let keys = exprs
+                        .iter()
+                        .map(|e| plan.compile(e, false))
+                        .collect::<Result<_, _>>()?;
+                    plan.grouping = Some(keys);
+                }
+            }
+            _ => return Err(Error::from("Only GROUP BY of expressions is supported")),
+        }
+
+        if let Some(order_by) = &query.order_by {
+            for item in &order_by.exprs {
+                plan.add_order(item)?;
+            }
+        }
+
+        if plan.grouping.is_none() && (!plan.aggregates.is_empty() || plan.having.is_some()) {
+            plan.grouping = Some(vec![]);
+        }
+        if let Some(keys) = &plan.grouping {
+            let exprs = plan.outputs.iter().chain(&plan.hidden).chain(&plan.having);
+            for expr in exprs {
+                if !is_grouped(expr, keys) {
+                    return Err(Error::from(
+                        "Columns of the select list must be on the GROUP BY or on an aggregate",
+                    ));
+                }
+            }
+        }
+
+        plan.offset = match &query.offset {
+            Some(offset) => plan.count(&offset.value)?,
+            None => 0,
+        };
+        plan.limit = match (
+            &query.limit,
+            query.fetch.as_ref().and_then(|f| f.quantity.as_ref()),
+        ) {
+            (Some(limit), _) | (None, Some(limit)) => Some(plan.count(limit)?),
+            (None, None) => None,
+        };
+
+        plan.place_conditions(conjuncts);
+
+        Ok(plan)
+    }
+
+    fn add_source(&mut self, tables: &'t [Table], factor: &ast::TableFactor) -> Result<(), Error> {
+        let (name, alias) = match factor {
+            ast::TableFactor::Table { name, alias, .. } => (name, alias),
+            _ => return Err(Error::Other(format!("Unsupported table {}", factor))),
+        };
+
+        let table_name = match name.0.as_slice() {
+            [name] => &name.value,
+            _ => return Err(Error::Other(format!("Unsupported table {}", name))),
+        };
+        let table = tables
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(table_name))
+            .ok_or_else(|| Error::Other(format!("Table {} not found", table_name)))?;
+
+        let name = match alias {
+            Some(alias) => alias.name.value.to_uppercase(),
+            None => table.name.clone(),
+        };
+        if self.sources.iter().any(|s| s.name == name) {
+            return Err(Error::Other(format!(
+                "Table {} used twice without alias",
+                name
+            )));
+        }
+
+        self.sources.push(Source {
+            table,
+            name,
+            columns: table.prepare()?.columns,
+            used: vec![],
+            filter: None,
+            lookup: None,
+            join_lookup: None,
+        });
+
+        Ok(())
+    }
+
+    /// Bind the column to its table, reading it
+    fn column(&mut self, qualifier: Option<&str>, name: &str) -> Result<Expr, Error> {
+        let mut found = None;
+        for (idx, source) in self.sources.iter().enumerate() {
+            if qualifier.is_some_and(|q| !q.eq_ignore_ascii_case(&source.name)) {
+                continue;
+            }
+
+            if let Some(pos) = source
+                .columns
+                .iter()
+                .position(|c| c.name.eq_ignore_ascii_case(name))
+            {
+                if found.is_some() {
+                    return Err(Error::Other(format!("Column {} is ambiguous", name)));
+                }
+                found = Some((idx, pos));
+            }
+        }
+
+        let (idx, pos) = found.ok_or_else(|| match qualifier {
+            Some(qualifier) => Error::Other(format!("Column {}.{} not found", qualifier, name)),
+            None => Error::Other(format!("Column {} not found", name)),
+        })?;
+
+        Ok(Expr::Column(idx, self.read_column(idx, pos)))
+    }
+
+    /// Slot of the column among the ones read from the source
+    fn read_column(&mut self, source: usize, pos: usize) -> usize {
+        let used = &mut self.sources[source].used;
+        match used.iter().position(|p| *p == pos) {
+            Some(slot) => slot,
+            None => {
+                used.push(pos);
+                used.len() - 1
+            }
+        }
+    }
+
+    fn compile(&mut self, expr: &ast::Expr, aggregates: bool) -> Result<Expr, Error> {
+        let compiled = match expr {
+            ast::Expr::Identifier(ident) => self.column(None, &ident.value)?,
+            ast::Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+                [qualifier, name] => self.column(Some(&qualifier.value), &name.value)?,
+                _ => return Err(Error::Other(format!("Unsupported column {}", expr))),
+            },
+            ast::Expr::Value(value) => Expr::Literal(literal(value)?),
+            ast::Expr::TypedString { data_type, value } => {
+                let like = match data_type {
+                    DataType::Date => Value::Date(0),
+                    DataType::Time(..) => Value::Time(0),
+                    DataType::Timestamp(..) => Value::Timestamp(0, 0),
+                    _ => return Err(Error::Other(format!("Unsupported literal {}", expr))),
+                };
+                Expr::Literal(Some(parse_as(value, &like)?))
+            }
+            ast::Expr::Nested(expr) => self.compile(expr, aggregates)?,
+            ast::Expr::UnaryOp { op, expr } => {
+                let expr = Box::new(self.compile(expr, aggregates)?);
+                match op {
+                    UnaryOperator::Minus => Expr::Negate(expr),
+                    UnaryOperator::Plus => *expr,
+                    UnaryOperator::Not => Expr::Not(expr),
+                    _ => return Err(Error::Other(format!("Unsupported operator {}", op))),
+                }
+            }
+            ast::Expr::BinaryOp { left, op, right } => {
+                let left = Box::new(self.compile(left, aggregates)?);
+                let right = Box::new(self.compile(right, aggregates)?);
+                let op = match op {
+                    BinaryOperator::And => return Ok(Expr::And(left, right)),
+                    BinaryOperator::Or => return Ok(Expr::Or(left, right)),
+                    BinaryOperator::Eq => Operator::Equal,
+                    BinaryOperator::NotEq => Operator::NotEqual,
+                    BinaryOperator::Lt => Operator::Less,
+                    BinaryOperator::LtEq => Operator::LessOrEqual,
+                    BinaryOperator::Gt => Operator::Greater,
+                    BinaryOperator::GtEq => Operator::GreaterOrEqual,
+                    _ => return Err(Error::Other(format!("Unsupported operator {}", op))),
+                };
+                Expr::Compare(left, op, right)
+            }
+            ast::Expr::IsNull(expr) => Expr::IsNull(Box::new(self.compile(expr, aggregates)?)),
+            ast::Expr::IsNotNull(expr) => Expr::Not(Box::new(Expr::IsNull(Box::new(
+                self.compile(expr, aggregates)?,
+            )))),
+            ast::Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let expr = Box::new(self.compile(expr, aggregates)?);
+                let list = list
+                    .iter()
+                    .map(|e| self.compile(e, aggregates))
+                    .collect::<Result<_, _>>()?;
+                negate(Expr::In(expr, list), *negated)
+            }
+            ast::Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                let expr = self.compile(expr, aggregates)?;
+                let low = Expr::Compare(
+                    Box::new(expr.clone()),
+                    Operator::GreaterOrEqual,
+                    Box::new(self.compile(low, aggregates)?),
+                );
+                let high = Expr::Compare(
+                    Box::new(expr),
+                    Operator::LessOrEqual,
+                    Box::new(self.compile(high, aggregates)?),
+                );
+                negate(Expr::And(Box::new(low), Box::new(high)), *negated)
+            }
+            ast::Expr::Like {
+                negated,
+                any: false,
+                expr,
+                pattern,
+                escape_char: None,
+            } => {
+                let expr = Box::new(self.compile(expr, aggregates)?);
+                let pattern = Box::new(self.compile(pattern, aggregates)?);
+                negate(Expr::Like(expr, pattern), *negated)
+            }
+            ast::Expr::Function(function) if aggregates => self.aggregate(function)?,
+            ast::Expr::Function(function) => {
+                return Err(Error::Other(format!(
+                    "Function {} not allowed here",
+                    function.name
+                )))
+            }
+            _ => return Err(Error::Other(format!("Unsupported expression {}", expr))),
+        };
+
+        Ok(compiled)
+    }
+
+    fn aggregate(&mut self, function: &ast::Function) -> Result<Expr, Error> {
+        let name = function.name.to_string().to_uppercase();
+        let func = match name.as_str() {
+            "COUNT" => Function::Count,
+            "SUM" => Function::Sum,
+            "AVG" => Function::Avg,
+            "MIN" => Function::Min,
+            "MAX" => Function::Max,
+            _ => return Err(Error::Other(format!("Unsupported function {}", name))),
+        };
+
+        let args = match &function.args {
+            ast::FunctionArguments::List(args)
+                if args.clauses.is_empty()
+                    && function.over.is_none()
+                    && function.filter.is_none()
+                    && function.within_group.is_empty() =>
+            {
+                args
+            }
+            _ => return Err(Error::Other(format!("Unsupported function {}", function))),
+        };
+
+        let arg = match args.args.as_slice() {
+            [ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Wildcard)]
+                if func == Function::Count =>
+            {
+                None
+            }
+            [ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(expr))] => {
+                Some(self.compile(expr, false)?)
+            }
+            _ => return Err(Error::Other(format!("Unsupported function {}", function))),
+        };
+
+        self.aggregates.push(Aggregate {
+            function: func,
+            arg,
+            distinct: matches!(
+                args.duplicate_treatment,
+                Some(ast::DuplicateTreatment::Distinct)
+            ),
+        });
+
+        Ok(Expr::Aggregate(self.aggregates.len() - 1))
+    }
+
+    fn add_output(&mut self, item: &ast::SelectItem) -> Result<(), Error> {
+        let (expr, name) = match item {
+            ast::SelectItem::UnnamedExpr(expr) => (expr, None),
+            ast::SelectItem::ExprWithAlias { expr, alias } => (expr, Some(ident_name(alias))),
+            ast::SelectItem::Wildcard(_) => {
+                for source in 0..self.sources.len() {
+                    self.add_all_columns(source);
+                }
+                return Ok(());
+            }
+            ast::SelectItem::QualifiedWildcard(name, _) => {
+                let source = self
+                    .sources
+                    .iter()
+                    .position(|s| s.name.eq_ignore_ascii_case(&name.to_string()))
+                    .ok_or_else(|| Error::Other(format!("Table {} not found", name)))?;
+                self.add_all_columns(source);
+                return Ok(());
+            }
+        };
+
+        let compiled = self.compile(expr, true)?;
+        let name = name.unwrap_or_else(|| match (&compiled, expr) {
+            (Expr::Column(source, slot), _) => {
+                let source = &self.sources[*source];
+                source.columns[source.used[*slot]].name.clone()
+            }
+            (_, ast::Expr::Function(function)) => function.name.to_string().to_uppercase(),
+            _ => expr.to_string(),
+        });
+
+        self.columns.push(name);
+        self.outputs.push(compiled);
+
+        Ok(())
+    }
+
+    fn add_all_columns(&mut self, source: usize) {
+        for pos in 0..self.sources[source].columns.len() {
+            let slot = self.read_column(source, pos);
+            self.columns
+                .push(self.sources[source].columns[pos].name.clone());
+            self.outputs.push(Expr::Column(source, slot));
+        }
+    }
+
+    /// Sort by a column of the output, by its position or name, or by
+    /// any expression
+    fn add_order(&mut self, item: &ast::OrderByExpr) -> Result<(), Error> {
+        let value = match &item.expr {
+            ast::Expr::Value(ast::Value::Number(num, _)) => {
+                let pos: usize = num
+                    .parse()
+                    .map_err(|_| Error::Other(format!("Invalid ORDER BY position {}", num)))?;
+                if pos == 0 || pos > self.outputs.len() {
+                    return Err(Error::Other(format!(
+                        "ORDER BY position {} out of the {} columns",
+                        pos,
+                        self.outputs.len()
+                    )));
+                }
+                SortValue::Output(pos - 1)
+            }
+            ast::Expr::Identifier(ident)
+                if self.columns.iter().any(|c| *c == ident_name(ident)) =>
+            {
+                let name = ident_name(ident);
+                SortValue::Output(self.columns.iter().position(|c| *c == name).unwrap_or(0))
+            }
+            expr => {
+                let compiled = self.compile(expr, true)?;
+                match self.outputs.iter().position(|o| *o == compiled) {
+                    Some(pos) => SortValue::Output(pos),
+                    None => {
+                        self.hidden.push(compiled);
+                        SortValue::Hidden(self.hidden.len() - 1)
+                    }
+                }
+            }
+        };
+
+        let descending = item.asc == Some(false);
+        self.order.push(SortKey {
+            value,
+            descending,
+            // Firebird places the nulls first on the ascending order
+            nulls_first: item.nulls_first.unwrap_or(!descending),
+        });
+
+        Ok(())
+    }
+
+    /// Non-negative integer of the LIMIT and OFFSET
+    fn count(&mut self, expr: &ast::Expr) -> Result<usize, Error> {
+        let count = match self.compile(expr, false)? {
+            Expr::Literal(Some(Value::BigInt(count))) => usize::try_from(count).ok(),
+            _ => None,
+        };
+
+        count.ok_or_else(|| Error::Other(format!("Invalid row count {}", expr)))
+    }
+
+    /// Check the conditions on a single table while reading it, using
+    /// its indexes for the equalities, and the others after reading
+    /// the last of its tables
+    fn place_conditions(&mut self, conjuncts: Vec<Expr>) {
+        self.conditions = vec![vec![]; self.sources.len()];
+
+        for conjunct in conjuncts {
+            let mut sources = HashSet::new();
+            collect_sources(&conjunct, &mut sources);
+            let last = sources.iter().max().copied().unwrap_or(0);
+
+            if sources.len() == 1 {
+                let source = &mut self.sources[last];
+                if let Some(filter) = to_filter(&conjunct, source) {
+                    if source.lookup.is_none() {
+                        source.lookup = index_lookup(&conjunct, source);
+                    }
+                    source.filter = Some(match source.filter.take() {
+                        Some(current) => current.and(filter),
+                        None => filter,
+                    });
+                    continue;
+                }
+            }
+
+            if last > 0 && self.sources[last].join_lookup.is_none() {
+                self.sources[last].join_lookup = join_lookup(&conjunct, last, &self.sources[last]);
+            }
+            self.conditions[last].push(conjunct);
+        }
+
+        let simple = self.sources.len() == 1
+            && self.conditions[0].is_empty()
+            && self.sources[0].lookup.is_none()
+            && self.grouping.is_none()
+            && self.order.is_empty()
+            && !self.distinct;
+        if simple {
+            self.scan_range = Some((self.offset, self.limit));
+            self.offset = 0;
+            self.limit = None;
+        }
+    }
+
+    fn run(&self) -> Result<QueryResult, Error> {
+        let mut offsets = vec![];
+        let mut width = 0;
+        for source in &self.sources {
+            offsets.push(width);
+            width += source.used.len();
+        }
+
+        let mut rows = self.read(&self.sources[0], self.scan_range)?;
+        rows = self.check(rows, &self.conditions[0], &offsets)?;
+
+        for (idx, source) in self.sources.iter().enumerate().skip(1) {
+            let others = match &source.join_lookup {
+                Some(_) if source.lookup.is_none() => None,
+                _ => Some(self.read(source, None)?),
+            };
+
+            let mut joined = vec![];
+            for left in rows {
+                let right = match (&others, &source.join_lookup) {
+                    (Some(others), _) => others.clone(),
+                    (None, Some((index, slot, expr))) => {
+                        let ctx = Context {
+                            row: &left,
+                            offsets: &offsets,
+                            aggregates: &[],
+                        };
+                        let col = &source.columns[source.used[*slot]];
+                        match expr.value(&ctx)?.and_then(|value| coerce(&value, col)) {
+                            Some(value) => self.lookup(source, index, value)?,
+                            None => vec![],
+                        }
+                    }
+                    (None, None) => vec![],
+                };
+
+                for right in right {
+                    let mut row = left.clone();
+                    row.extend(right);
+                    joined.push(row);
+                }
+            }
+
+            rows = self.check(joined, &self.conditions[idx], &offsets)?;
+        }
+
+        let mut results = match &self.grouping {
+            Some(keys) => self.group(rows, keys, &offsets)?,
+            None => {
+                let mut results = vec![];
+                for row in &rows {
+                    let ctx = Context {
+                        row,
+                        offsets: &offsets,
+                        aggregates: &[],
+                    };
+                    results.push(self.output(&ctx)?);
+                }
+                results
+            }
+        };
+
+        if self.distinct {
+            let mut seen = HashSet::new();
+            results.retain(|(values, _)| seen.insert(format!("{:?}", values)));
+        }
+
+        if !self.order.is_empty() {
+            results.sort_by(|a, b| self.compare(a, b));
+        }
+
+        let rows = results
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .map(|(values, _)| values)
+            .collect();
+
+        Ok(QueryResult {
+            columns: self.columns.clone(),
+            rows,
+            plan: self.plan(),
+        })
+    }
+
+    /// Rows of the source, with only the columns used
+    fn read(
+        &self,
+        source: &Source,
+        range: Option<(usize, Option<usize>)>,
+    ) -> Result<Vec<Vec<Option<Value>>>, Error> {
+        if let Some((index, value)) = &source.lookup {
+            return self.lookup(source, index, value.clone());
+        }
+
+        let names: Vec<&str> = source
+            .used
+            .iter()
+            .map(|pos| source.columns[*pos].name.as_str())
+            .collect();
+
+        let mut ptable = source.table.prepare()?.project(&names)?;
+        if let Some(filter) = &source.filter {
+            ptable = ptable.filter_by(filter.clone())?;
+        }
+        if let Some((skip, limit)) = range {
+            ptable = ptable.skip(skip);
+            if let Some(limit) = limit {
+                ptable = ptable.limit(limit);
+            }
+        }
+
+        ptable.map(|row| row.map(|r| r.values)).collect()
+    }
+
+    /// Rows of the source found on the index, checked against the
+    /// filter of the source
+    fn lookup(
+        &self,
+        source: &Source,
+        index: &str,
+        value: Value,
+    ) -> Result<Vec<Vec<Option<Value>>>, Error> {
+        let mut rows = vec![];
+
+        for row in source.table.lookup(index, &[value])? {
+            let value = |name: &str| -> Result<Option<Value>, Error> {
+                let pos = source
+                    .columns
+                    .iter()
+                    .position(|c| c.name == name)
+                    .ok_or_else(|| Error::Other(format!("Column {} not found", name)))?;
+                Ok(row.values[pos].clone())
+            };
+            if let Some(filter) = &source.filter {
+                if filter.eval(&mut |name| value(name))? != Some(true) {
+                    continue;
+                }
+            }
+
+            rows.push(
+                source
+                    .used
+                    .iter()
+                    .map(|pos| row.values[*pos].clone())
+                    .collect(),
+            );
+        }
+
+        Ok(rows)
+    }
+
+    /// Rows matching all the conditions
+    fn check(
+        &self,
+        rows: Vec<Vec<Option<Value>>>,
+        conditions: &[Expr],
+        offsets: &[usize],
+    ) -> Result<Vec<Vec<Option<Value>>>, Error> {
+        if conditions.is_empty() {
+            return Ok(rows);
+        }
+
+        let mut checked = vec![];
+        'rows: for row in rows {
+            let ctx = Context {
+                row: &row,
+                offsets,
+                aggregates: &[],
+            };
+            for condition in conditions {
+                if condition.check(&ctx)? != Some(true) {
+                    continue 'rows;
+                }
+            }
+            checked.push(row);
+        }
+
+        Ok(checked)
+    }
+
+    /// Output and sort values of each group of rows
+    fn group(
+        &self,
+        rows: Vec<Vec<Option<Value>>>,
+        keys: &[Expr],
+        offsets: &[usize],
+    ) -> Result<Vec<OutputRow>, Error> {
+        let mut groups: Vec<(Vec<Option<Value>>, Vec<Accumulator>)> = vec![];
+        let mut positions = HashMap::new();
+
+        for row in rows {
+            let ctx = Context {
+                row: &row,
+                offsets,
+                aggregates: &[],
+            };
+            let key = keys
+                .iter()
+                .map(|k| k.value(&ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let pos = *positions
+                .entry(format!("{:?}", key))
+                .or_insert_with(|| groups.len());
+            if pos == groups.len() {
+                let accumulators = self.aggregates.iter().map(Accumulator::new).collect();
+                groups.push((row.clone(), accumulators));
+            }
+
+            for acc in groups[pos].1.iter_mut() {
+                acc.add(&ctx)?;
+            }
+        }
+
+        // The aggregates without GROUP BY have a row even without rows
+        if groups.is_empty() && keys.is_empty() {
+            let width = offsets.last().copied().unwrap_or(0)
+                + self.sources.last().map_or(0, |s| s.used.len());
+            let accumulators = self.aggregates.iter().map(Accumulator::new).collect();
+            groups.push((vec![None; width], accumulators));
+        }
+
+        let mut results = vec![];
+        for (row, accumulators) in groups {
+            let aggregates: Vec<_> = accumulators.into_iter().map(|a| a.result()).collect();
+            let ctx = Context {
+                row: &row,
+                offsets,
+                aggregates: &aggregates,
+            };
+
+            if let Some(having) = &self.having {
+                if having.check(&ctx)? != Some(true) {
+                    continue;
+                }
+            }
+            results.push(self.output(&ctx)?);
+        }
+
+        Ok(results)
+    }
+
+    fn output(&self, ctx: &Context) -> Result<OutputRow, Error> {
+        let values = self
+            .outputs
+            .iter()
+            .map(|o| o.value(ctx))
+            .collect::<Result<_, _>>()?;
+        let hidden = self
+            .hidden
+            .iter()
+            .map(|h| h.value(ctx))
+            .collect::<Result<_, _>>()?;
+
+        Ok((values, hidden))
+    }
+
+    fn compare(&self, a: &OutputRow, b: &OutputRow) -> Ordering {
+        for key in &self.order {
+            let (x, y) = match key.value {
+                SortValue::Output(pos) => (&a.0[pos], &b.0[pos]),
+                SortValue::Hidden(pos) => (&a.1[pos], &b.1[pos]),
+            };
+
+            let ord = match (x, y) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) if key.nulls_first => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) if key.nulls_first => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(x), Some(y)) => {
+                    let ord = compare_values(x, y).unwrap_or(Ordering::Equal);
+                    if key.descending {
+                        ord.reverse()
+                    } else {
+                        ord
+                    }
+                }
+            };
+
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+
+        Ordering::Equal
+    }
+
+    /// Plan with the syntax of Firebird
+    fn plan(&self) -> String {
+        let sources: Vec<String> = self
+            .sources
+            .iter()
+            .map(|source| match (&source.lookup, &source.join_lookup) {
+                (Some((index, _)), _) | (None, Some((index, ..))) => {
+                    format!("{} INDEX ({})", source.name, index)
+                }
+                (None, None) => format!("{} NATURAL", source.name),
+            })
+            .collect();
+
+        if sources.len() == 1 {
+            format!("PLAN ({})", sources[0])
+        } else {
+            format!("PLAN JOIN ({})", sources.join(", "))
+        }
+    }
+}
+
+/// Values of the output columns and the hidden sort values
+type OutputRow = (Vec<Option<Value>>, Vec<Option<Value>>);
+
+impl Expr {
+    fn value(&self, ctx: &Context) -> Result<Option<Value>, Error> {
+        let value = match self {
+            Expr::Column(source, slot) => {
+                ctx.row.get(ctx.offsets[*source] + slot).cloned().flatten()
+            }
+            Expr::Literal(value) => value.clone(),
+            Expr::Aggregate(idx) => ctx.aggregates[*idx].clone(),
+            Expr::Negate(expr) => match expr.value(ctx)? {
+                Some(value) => Some(negate_value(value)?),
+                None => None,
+            },
+            _ => {
+                return Err(Error::Other(format!(
+                    "Conditions can't be used as values: {:?}",
+                    self
+                )))
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Result of the condition, none when unknown
+    fn check(&self, ctx: &Context) -> Result<Option<bool>, Error> {
+        let result = match self {
+            Expr::Compare(left, op, right) => match (left.value(ctx)?, right.value(ctx)?) {
+                (Some(left), Some(right)) => Some(op.matches(compare_values(&left, &right)?)),
+                _ => None,
+            },
+            Expr::In(expr, list) => match expr.value(ctx)? {
+                Some(value) => {
+                    let mut result = Some(false);
+                    for item in list {
+                        match item.value(ctx)? {
+                            Some(item) if compare_values(&value, &item)? == Ordering::Equal => {
+                                return Ok(Some(true));
+                            }
+                            Some(_) => {}
+                            None => result = None,
+                        }
+                    }
+                    result
+                }
+                None => None,
+            },
+            Expr::Like(expr, pattern) => match (expr.value(ctx)?, pattern.value(ctx)?) {
+                (Some(value), Some(pattern)) => {
+                    Some(like(text(&value).trim_end_matches(' '), &text(&pattern)))
+                }
+                _ => None,
+            },
+            Expr::IsNull(expr) => Some(expr.value(ctx)?.is_none()),
+            Expr::And(left, right) => match left.check(ctx)? {
+                Some(false) => Some(false),
+                left => match right.check(ctx)? {
+                    Some(false) => Some(false),
+                    Some(true) => left,
+                    None => None,
+                },
+            },
+            Expr::Or(left, right) => match left.check(ctx)? {
+                Some(true) => Some(true),
+                left => match right.check(ctx)? {
+                    Some(true) => Some(true),
+                    Some(false) => left,
+                    None => None,
+                },
+            },
+            Expr::Not(expr) => expr.check(ctx)?.map(|r| !r),
+            _ => {
+                return Err(Error::Other(format!(
+                    "Values can't be used as conditions: {:?}",
+                    self
+                )))
+            }
+        };
+
+        Ok(result)
+    }
+
+    /// Visit the expression and the ones inside of it
+    fn walk<'e>(&'e self, f: &mut dyn FnMut(&'e Expr)) {
+        f(self);
+        match self {
+            Expr::Negate(expr) | Expr::IsNull(expr) | Expr::Not(expr) => expr.walk(f),
+            Expr::Compare(left, _, right)
+            | Expr::Like(left, right)
+            | Expr::And(left, right)
+            | Expr::Or(left, right) => {
+                left.walk(f);
+                right.walk(f);
+            }
+            Expr::In(expr, list) => {
+                expr.walk(f);
+                list.iter().for_each(|e| e.walk(f));
+            }
+            Expr::Column(..) | Expr::Literal(_) | Expr::Aggregate(_) => {}
+        }
+    }
+}
+
+/// Running value of an aggregate over the rows of a group
+struct Accumulator<'p> {
+    aggregate: &'p Aggregate,
+    count: i64,
+    sum: Option<Number>,
+    value: Option<Value>,
+    seen: HashSet<String>,
+}
+
+impl<'p> Accumulator<'p> {
+    fn new(aggregate: &'p Aggregate) -> Self {
+        Accumulator {
+            aggregate,
+            count: 0,
+            sum: None,
+            value: None,
+            seen: HashSet::new(),
+        }
+    }
+
+    fn add(&mut self, ctx: &Context) -> Result<(), Error> {
+        let value = match &self.aggregate.arg {
+            Some(arg) => match arg.value(ctx)? {
+                Some(value) => value,
+                // Nulls are left out of the aggregates
+                None => return Ok(()),
+            },
+            None => {
+                self.count += 1;
+                return Ok(());
+            }
+        };
+
+        if self.aggregate.distinct && !self.seen.insert(format!("{:?}", value)) {
+            return Ok(());
+        }
+        self.count += 1;
+
+        match self.aggregate.function {
+            Function::Count => {}
+            Function::Sum | Function::Avg => {
+                let num = Number::from_value(&value)
+                    .ok_or_else(|| Error::Other(format!("Value {:?} isn't a number", value)))?;
+                self.sum = Some(match &self.sum {
+                    Some(sum) => sum.add(&num),
+                    None => num,
+                });
+            }
+            Function::Min | Function::Max => {
+                let replace = match &self.value {
+                    Some(current) => {
+                        let ord = compare_values(&value, current)?;
+                        match self.aggregate.function {
+                            Function::Min => ord == Ordering::Less,
+                            _ => ord == Ordering::Greater,
+                        }
+                    }
+                    None => true,
+                };
+                if replace {
+                    self.value = Some(value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn result(self) -> Option<Value> {
+        match self.aggregate.function {
+            Function::Count => Some(Value::BigInt(self.count)),
+            Function::Sum => self.sum.map(|sum| sum.to_value()),
+            Function::Avg => self.sum.map(|sum| sum.div(self.count).to_value()),
+            Function::Min | Function::Max => self.value,
+        }
+    }
+}
+
+/// Arithmetic of the SUM and AVG
+impl Number {
+    /// Sum of the numbers, exact when both are
+    fn add(&self, other: &Number) -> Number {
+        match (self, other) {
+            (Number::Exact(a, da), Number::Exact(b, db)) => {
+                let digits = (*da).max(*db);
+                let a = a.saturating_mul(10i128.pow(digits - da));
+                let b = b.saturating_mul(10i128.pow(digits - db));
+                Number::Exact(a.saturating_add(b), digits)
+            }
+            _ => Number::Approx(self.to_f64() + other.to_f64()),
+        }
+    }
+
+    /// Division by an integer, truncated when exact
+    fn div(&self, by: i64) -> Number {
+        match self {
+            Number::Exact(v, digits) => Number::Exact(v / by as i128, *digits),
+            Number::Approx(v) => Number::Approx(v / by as f64),
+        }
+    }
+
+    /// Value of the number, a BIGINT or a NUMERIC when exact
+    fn to_value(&self) -> Value {
+        match self {
+            Number::Exact(v, 0) => match i64::try_from(*v) {
+                Ok(v) => Value::BigInt(v),
+                Err(_) => Value::Double(self.to_f64()),
+            },
+            Number::Exact(v, digits) => match i64::try_from(*v) {
+                Ok(v) => Value::Decimal(v, -(*digits as i16)),
+                Err(_) => Value::Double(self.to_f64()),
+            },
+            Number::Approx(v) => Value::Double(*v),
+        }
+    }
+}
+
+fn literal(value: &ast::Value) -> Result<Option<Value>, Error> {
+    match value {
+        ast::Value::Number(num, _) => parse_number(num)
+            .map(Some)
+            .ok_or_else(|| Error::Other(format!("Invalid number {}", num))),
+        ast::Value::SingleQuotedString(text) => Ok(Some(Value::String(text.clone()))),
+        ast::Value::Null => Ok(None),
+        _ => Err(Error::Other(format!("Unsupported value {}", value))),
+    }
+}
+
+/// Name of the alias, upper case when not quoted
+fn ident_name(ident: &ast::Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_uppercase(),
+    }
+}
+
+fn negate(expr: Expr, negated: bool) -> Expr {
+    if negated {
+        Expr::Not(Box::new(expr))
+    } else {
+        expr
+    }
+}
+
+fn negate_value(value: Value) -> Result<Value, Error> {
+    let negated = match &value {
+        Value::SmallInt(v) => v.checked_neg().map(Value::SmallInt),
+        Value::Int(v) => v.checked_neg().map(Value::Int),
+        Value::BigInt(v) => v.checked_neg().map(Value::BigInt),
+        Value::Decimal(v, scale) => v.checked_neg().map(|v| Value::Decimal(v, *scale)),
+        Value::Float(v) => Some(Value::Float(-v)),
+        Value::Double(v) => Some(Value::Double(-v)),
+        value => return Err(Error::Other(format!("Value {:?} isn't a number", value))),
+    };
+
+    negated.ok_or_else(|| Error::Other(format!("Negating {} overflows", value)))
+}
+
+/// Text of the value for the LIKE
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(st) => st.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn split_and(expr: Expr, conjuncts: &mut Vec<Expr>) {
+    match expr {
+        Expr::And(left, right) => {
+            split_and(*left, conjuncts);
+            split_and(*right, conjuncts);
+        }
+        expr => conjuncts.push(expr),
+    }
+}
+
+fn collect_sources(expr: &Expr, sources: &mut HashSet<usize>) {
+    expr.walk(&mut |e| {
+        if let Expr::Column(source, _) = e {
+            sources.insert(*source);
+        }
+    });
+}
+
+/// Check if the expression only uses the keys of the groups and the
+/// aggregates
+fn is_grouped(expr: &Expr, keys: &[Expr]) -> bool {
+    if keys.contains(expr) {
+        return true;
+    }
+
+    match expr {
+        Expr::Column(..) => false,
+        Expr::Literal(_) | Expr::Aggregate(_) => true,
+        Expr::Negate(expr) | Expr::IsNull(expr) | Expr::Not(expr) => is_grouped(expr, keys),
+        Expr::Compare(left, _, right)
+        | Expr::Like(left, right)
+        | Expr::And(left, right)
+        | Expr::Or(left, right) => is_grouped(left, keys) && is_grouped(right, keys),
+        Expr::In(expr, list) => is_grouped(expr, keys) && list.iter().all(|e| is_grouped(e, keys)),
+    }
+}
+
+fn column_name<'s>(source: &'s Source, slot: usize) -> &'s str {
+    &source.columns[source.used[slot]].name
+}
+
+/// Filter of the rows of a single table, when the condition has only
+/// comparisons of its columns with values
+fn to_filter(expr: &Expr, source: &Source) -> Option<Filter> {
+    let filter = match expr {
+        Expr::Compare(left, op, right) => match (left.as_ref(), right.as_ref()) {
+            (Expr::Column(_, slot), Expr::Literal(Some(value))) => {
+                Filter::Compare(column_name(source, *slot).to_string(), *op, value.clone())
+            }
+            (Expr::Literal(Some(value)), Expr::Column(_, slot)) => Filter::Compare(
+                column_name(source, *slot).to_string(),
+                flip(*op),
+                value.clone(),
+            ),
+            _ => return None,
+        },
+        Expr::In(expr, list) => match expr.as_ref() {
+            Expr::Column(_, slot) => {
+                let values = list
+                    .iter()
+                    .map(|e| match e {
+                        Expr::Literal(Some(value)) => Some(value.clone()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Filter::In(column_name(source, *slot).to_string(), values)
+            }
+            _ => return None,
+        },
+        Expr::Like(expr, pattern) => match (expr.as_ref(), pattern.as_ref()) {
+            (Expr::Column(_, slot), Expr::Literal(Some(Value::String(pattern)))) => {
+                Filter::Like(column_name(source, *slot).to_string(), pattern.clone())
+            }
+            _ => return None,
+        },
+        Expr::IsNull(expr) => match expr.as_ref() {
+            Expr::Column(_, slot) => Filter::IsNull(column_name(source, *slot).to_string()),
+            _ => return None,
+        },
+        Expr::And(left, right) => to_filter(left, source)?.and(to_filter(right, source)?),
+        Expr::Or(left, right) => to_filter(left, source)?.or(to_filter(right, source)?),
+        Expr::Not(expr) => !to_filter(expr, source)?,
+        _ => return None,
+    };
+
+    Some(filter)
+}
+
+/// Same comparison with the sides swapped
+fn flip(op: Operator) -> Operator {
+    match op {
+        Operator::Less => Operator::Greater,
+        Operator::LessOrEqual => Operator::GreaterOrEqual,
+        Operator::Greater => Operator::Less,
+        Operator::GreaterOrEqual => Operator::LessOrEqual,
+        op => op,
+    }
+}
+
+/// Active index of the source with the column as its first segment
+fn find_index(source: &Source, slot: usize) -> Option<String> {
+    let column = column_name(source, slot);
+    let indexes = source.table.indexes().ok()?;
+
+    // The keys of the rows found are encoded for checking them, so
+    // every segment must be of a supported type
+    indexes
+        .into_iter()
+        .find(|index| {
+            !index.inactive
+                && index.root != 0
+                && index.segments.first().is_some_and(|s| s == column)
+                && !index.keys.is_empty()
+                && index.keys.iter().all(|k| is_supported(k.itype))
+        })
+        .map(|index| index.name)
+}
+
+/// Index and value for the equality of a column with a value
+fn index_lookup(expr: &Expr, source: &Source) -> Option<(String, Value)> {
+    let (slot, value) = match expr {
+        Expr::Compare(left, Operator::Equal, right) => match (left.as_ref(), right.as_ref()) {
+            (Expr::Column(_, slot), Expr::Literal(Some(value)))
+            | (Expr::Literal(Some(value)), Expr::Column(_, slot)) => (*slot, value),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let value = coerce(value, &source.columns[source.used[slot]])?;
+    Some((find_index(source, slot)?, value))
+}
+
+/// Index of the source and the expression of the other tables for
+/// the equality of columns
+fn join_lookup(expr: &Expr, idx: usize, source: &Source) -> Option<(String, usize, Expr)> {
+    match expr {
+        Expr::Compare(left, Operator::Equal, right) => match (left.as_ref(), right.as_ref()) {
+            (Expr::Column(a, slot), other @ Expr::Column(b, _))
+            | (other @ Expr::Column(b, _), Expr::Column(a, slot))
+                if *a == idx && *b < idx =>
+            {
+                Some((find_index(source, *slot)?, *slot, other.clone()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Value converted to the type of the column, for encoding the keys
+fn coerce(value: &Value, col: &Column) -> Option<Value> {
+    let like = match col.tp {
+        ColumnType::Date => Value::Date(0),
+        ColumnType::Time => Value::Time(0),
+        ColumnType::Timestamp => Value::Timestamp(0, 0),
+        ColumnType::Smallint
+        | ColumnType::Integer
+        | ColumnType::Bigint
+        | ColumnType::Float
+        | ColumnType::DoublePrecision => Value::BigInt(0),
+        ColumnType::Char | ColumnType::Varchar => {
+            return match value {
+                Value::String(_) => Some(value.clone()),
+                _ => None,
+            }
+        }
+        ColumnType::Blob => return None,
+    };
+
+    match value {
+        Value::String(text) => parse_as(text, &like).ok(),
+        value if compare_values(value, &like).is_ok() => Some(value.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    #[test]
+    pub fn negating_values() -> Result<(), Error> {
+        assert_eq!(Value::Int(-5), negate_value(Value::Int(5))?);
+        assert_eq!(
+            Value::Decimal(12, -1),
+            negate_value(Value::Decimal(-12, -1))?
+        );
+        assert_eq!(Value::Double(-0.5), negate_value(Value::Double(0.5))?);
+
+        assert!(negate_value(Value::SmallInt(i16::MIN)).is_err());
+        assert!(negate_value(Value::Int(i32::MIN)).is_err());
+        assert!(negate_value(Value::BigInt(i64::MIN)).is_err());
+        assert!(negate_value(Value::Decimal(i64::MIN, -2)).is_err());
+        assert!(negate_value(Value::from("a")).is_err());
+
+        Ok(())
+    }
+}
@@ -1,16 +1,29 @@
 //! Firebird table representation
 
 use byteorder::{ByteOrder, LittleEndian};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
-use std::fs::File;
-use std::io::BufReader;
-use std::rc::Rc;
+use std::io::Read;
+use std::ops::Range;
 use std::slice::Iter;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, OnceLock};
 
+use crate::backup::{BackupRecord, BackupRelation};
+use crate::blob::*;
+use crate::charset::*;
+use crate::check::*;
 use crate::column::*;
 use crate::data::*;
+use crate::filter::Filter;
+use crate::index::*;
+use crate::key::compare_segments;
 use crate::page::*;
+use crate::record::RecordHeader;
 use crate::row::*;
+use crate::source::Source;
 use crate::Error;
 
 /// Basic reference of a table
@@ -19,13 +32,47 @@ pub struct Table {
     pub name: String,
     pub is_system_table: bool,
     pub relation: u16,
-    pages: Rc<Vec<DataPage>>,
+    source: TableSource,
+    none_charset: Charset,
+    /// Data pages, columns and indexes, loaded on the first use
+    table_pages: OnceLock<TablePages>,
+    columns: OnceLock<Vec<Column>>,
+    indexes: OnceLock<Vec<Index>>,
+}
+
+/// Where the rows of the table come from
+#[derive(Debug)]
+enum TableSource {
+    /// Data pages of a database
+    Pages(PageStorage),
+    /// Relation of a gbak backup, with its rows already read
+    Backup(Arc<BackupRelation>),
+}
+
+#[derive(Debug)]
+struct PageStorage {
+    pages: Arc<Vec<DataPage>>,
+    header: HeaderPage,
+    buffer: Source,
+}
+
+/// Data pages of a table among the ones of the database
+#[derive(Debug, Default)]
+struct TablePages {
+    /// Positions on the pages of the database, on the file order
+    positions: Vec<usize>,
+    /// Position of the page of each sequence
+    sequences: HashMap<u32, usize>,
 }
 
 impl Table {
     /// Load all tables of database
-    pub fn load(header: HeaderPage, buffer: &mut BufReader<File>) -> Result<Vec<Table>, Error> {
-        let pages = Rc::new(DataPage::load(header, buffer)?);
+    pub(crate) fn load(
+        header: HeaderPage,
+        buffer: Source,
+        none_charset: Charset,
+    ) -> Result<Vec<Table>, Error> {
+        let pages = Arc::new(DataPage::load(header, &mut **buffer.lock())?);
         let mut tables = vec![];
 
         for data in pages.iter() {
@@ -39,15 +86,24 @@ impl Table {
                     }
                     // RDB$RELATION_NAME field
                     let bname = &rec_data[42..72];
-                    let name = String::from_utf8_lossy(&bname).trim().to_string();
-                    let is_system_table = name.to_lowercase().contains("$");
-                    let relation = rec_data[32] as u16;
+                    let name = String::from_utf8_lossy(bname).trim().to_string();
+                    let is_system_table = name.to_lowercase().contains('$');
+                    // RDB$RELATION_ID field
+                    let relation = LittleEndian::read_u16(&rec_data[32..34]);
 
                     tables.push(Table {
                         name,
                         is_system_table,
                         relation,
-                        pages: pages.clone(),
+                        source: TableSource::Pages(PageStorage {
+                            pages: pages.clone(),
+                            header,
+                            buffer: buffer.clone(),
+                        }),
+                        none_charset,
+                        table_pages: OnceLock::new(),
+                        columns: OnceLock::new(),
+                        indexes: OnceLock::new(),
                     })
                 }
             }
@@ -56,26 +112,98 @@ impl Table {
         Ok(tables)
     }
 
-    /// Prepare the table for access its rows
-    pub fn prepare<'a>(&'a self) -> Result<TablePreparated<'a>, Error> {
-        TablePreparated::load(&self)
+    /// Table of a relation read from a gbak backup
+    pub(crate) fn from_backup(
+        relation: u16,
+        backup: Arc<BackupRelation>,
+        none_charset: Charset,
+    ) -> Table {
+        Table {
+            name: backup.name.clone(),
+            is_system_table: backup.is_system_table,
+            relation,
+            source: TableSource::Backup(backup),
+            none_charset,
+            table_pages: OnceLock::new(),
+            columns: OnceLock::new(),
+            indexes: OnceLock::new(),
+        }
     }
-}
 
-/// Preparated table for rows acesss
-pub struct TablePreparated<'a> {
-    pages: Iter<'a, DataPage>,
-    current_page: Option<&'a DataPage>,
-    current_record_idx: usize,
-    table: &'a Table,
-    pub columns: Vec<Column>,
-}
+    /// Check if the table was read from a gbak backup, which has
+    /// no pages, blob ids or indexes
+    pub fn is_from_backup(&self) -> bool {
+        matches!(self.source, TableSource::Backup(_))
+    }
+
+    /// Pages of the table, not available on backups
+    fn pages(&self) -> Result<&PageStorage, Error> {
+        match &self.source {
+            TableSource::Pages(source) => Ok(source),
+            TableSource::Backup(_) => Err(Error::Other(format!(
+                "Table {} was read from a backup, without pages",
+                self.name
+            ))),
+        }
+    }
+
+    /// Data pages of the table, found on the first use
+    fn table_pages(&self) -> Result<&TablePages, Error> {
+        let source = self.pages()?;
+
+        Ok(self.table_pages.get_or_init(|| {
+            let mut own = TablePages::default();
+            for (pos, data) in source.pages.iter().enumerate() {
+                if data.relation == self.relation {
+                    own.positions.push(pos);
+                    own.sequences.entry(data.sequence).or_insert(pos);
+                }
+            }
+            own
+        }))
+    }
+
+    /// Data page of the table by its sequence
+    fn data_page(&self, sequence: u32) -> Result<Option<&DataPage>, Error> {
+        let source = self.pages()?;
+        let pos = self.table_pages()?.sequences.get(&sequence);
+
+        Ok(pos.map(|pos| &source.pages[*pos]))
+    }
+
+    /// Columns of the table, loaded on the first use
+    pub(crate) fn columns(&self) -> Result<&[Column], Error> {
+        if let Some(columns) = self.columns.get() {
+            return Ok(columns);
+        }
+
+        let columns = self.load_columns()?;
+        Ok(self.columns.get_or_init(|| columns))
+    }
+
+    fn load_columns(&self) -> Result<Vec<Column>, Error> {
+        let storage = match &self.source {
+            TableSource::Pages(storage) => storage,
+            TableSource::Backup(backup) => {
+                let columns: Vec<Column> = backup
+                    .columns
+                    .iter()
+                    .cloned()
+                    .map(|mut col| {
+                        if col.charset == Charset::None {
+                            col.charset = self.none_charset;
+                        }
+                        col
+                    })
+                    .collect();
+
+                return Ok(columns);
+            }
+        };
 
-impl<'a> TablePreparated<'a> {
-    pub fn load(table: &'a Table) -> Result<Self, Error> {
         let mut columns = vec![];
 
-        for data in table.pages.iter() {
+        for data in storage.pages.iter() {
             // RDB$RELATIONS_FIELDS table
             if data.relation == 5 {
                 for rec in data.get_records()? {
@@ -87,89 +215,498 @@ impl<'a> TablePreparated<'a> {
 
                     // RDB$RELATION_NAME field
                     let brname = &rec_data[35..66];
-                    let rname = String::from_utf8_lossy(&brname).trim().to_string();
+                    let rname = String::from_utf8_lossy(brname).trim().to_string();
 
-                    if rname != table.name {
+                    if rname != self.name {
                         continue;
                     }
 
                     // RDB$FIELD_NAME field
                     let bfname = &rec_data[4..35];
-                    let fname = String::from_utf8_lossy(&bfname).trim().to_string();
+                    let fname = String::from_utf8_lossy(bfname).trim().to_string();
 
                     // RDB$FIELD_SOURCE field
-                    let bsource = &rec_data[65..96];
-                    let source = String::from_utf8_lossy(&bsource).trim().to_string();
+                    let bsource = &rec_data[66..97];
+                    let source = String::from_utf8_lossy(bsource).trim().to_string();
 
                     // RDB$NULL_FLAG
-                    let bnnull = &rec_data[392..393];
-                    let not_null = bnnull[0] == 1;
+                    let not_null = rec_data.get(392) == Some(&1);
 
                     let mut size = 0;
                     let mut scale = 0;
                     let mut tp = ColumnType::Smallint;
+                    let mut sub_type = 0;
+                    let mut charset = Charset::None;
+                    let mut char_length = 0;
+                    let mut dimensions = vec![];
                     let mut computed = false;
 
                     // Firebird have a specific table to storage
                     // the infos about columns types
-                    for fdata in table.pages.iter() {
+                    for fdata in storage.pages.iter() {
                         // RDB$FIELDS table
                         if fdata.relation == 2 {
                             for frec in fdata.get_records()? {
                                 let frec_data = frec.read()?;
 
-                                if frec_data.len() < 30 {
+                                if frec_data.len() < 35 {
                                     continue;
                                 }
 
                                 let bfield = &frec_data[4..35];
-                                let field = String::from_utf8_lossy(&bfield).trim().to_string();
+                                let field = String::from_utf8_lossy(bfield).trim().to_string();
 
                                 if field != source {
                                     continue;
                                 }
 
-                                let bcomputed = &frec_data[88..89];
-                                computed = bcomputed[0] > 0;
+                                // Records of older formats are shorter, the
+                                // fields missing are taken as nulls
+                                computed = frec_data.get(88).is_some_and(|c| *c > 0);
+                                size = field_i16(&frec_data, 120).unwrap_or(0).max(0) as usize;
+                                scale = field_i16(&frec_data, 122).unwrap_or(0);
+
+                                if let Some(ptype) = field_i16(&frec_data, 124) {
+                                    tp = ColumnType::try_from(ptype)
+                                        .map_err(|e| Error::from(e.to_string()))?;
+                                }
+
+                                sub_type = field_i16(&frec_data, 126).unwrap_or(0);
 
-                                let bsize = &frec_data[120..121];
-                                size = bsize[0] as usize;
+                                // Unknown charsets are left without one
+                                let pcharset = field_i16(&frec_data, 314).unwrap_or(0);
+                                charset = Charset::try_from(pcharset).unwrap_or(Charset::None);
+                                if charset == Charset::None {
+                                    charset = self.none_charset;
+                                }
 
-                                let bscale = &frec_data[122..124];
-                                scale = LittleEndian::read_i16(bscale);
+                                // RDB$CHARACTER_LENGTH field
+                                char_length =
+                                    field_i16(&frec_data, 310).unwrap_or(0).max(0) as usize;
+                                if char_length == 0
+                                    && matches!(tp, ColumnType::Char | ColumnType::Varchar)
+                                {
+                                    char_length =
+                                        size / load_bytes_per_character(&storage.pages, pcharset)?;
+                                }
 
-                                let btype = &frec_data[124..126];
-                                let ptype = LittleEndian::read_i16(btype);
-                                tp = ColumnType::try_from(ptype)
-                                    .map_err(|e| Error::from(e.to_string()))?;
+                                // RDB$DIMENSIONS field
+                                if field_i16(&frec_data, 306).unwrap_or(0) > 0 {
+                                    dimensions = load_dimensions(&storage.pages, &source)?;
+                                }
                             }
                         }
                     }
 
                     // RDB$FIELD_POSITION field
-                    let bposition = &rec_data[290..291];
-                    let position = bposition[0] as usize;
+                    let position = rec_data.get(290).copied().unwrap_or(0) as usize;
+
+                    // RDB$FIELD_ID field
+                    let id = field_i16(&rec_data, 306).unwrap_or(0).max(0) as usize;
 
                     columns.push(Column {
                         name: fname,
+                        id,
                         position,
                         size,
                         source,
                         scale,
                         not_null,
                         tp,
+                        sub_type,
+                        charset,
+                        char_length,
+                        dimensions,
                         computed,
                     });
                 }
             }
         }
 
-        let pages = table.pages.iter();
+        Ok(columns)
+    }
+
+    /// Prepare the table for access its rows
+    pub fn prepare(&self) -> Result<TablePreparated<'_>, Error> {
+        TablePreparated::load(self)
+    }
+
+    /// Read the rows on `threads` threads, each one reading a range
+    /// of the data pages, 0 uses one thread per core
+    ///
+    /// The rows are given to `f` out of order, the first error stops
+    /// the reading.
+    pub fn read_parallel<F>(&self, threads: usize, f: F) -> Result<(), Error>
+    where
+        F: Fn(Row) -> Result<(), Error> + Sync,
+    {
+        let threads = match threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            threads => threads,
+        };
+        let parts = self.prepare()?.split(threads);
+        let failed = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = parts
+                .into_iter()
+                .map(|mut part| {
+                    let (f, failed) = (&f, &failed);
+                    scope.spawn(move || {
+                        let result = (|| {
+                            while !failed.load(AtomicOrdering::Relaxed) {
+                                match part.read()? {
+                                    Some(row) => f(row)?,
+                                    None => break,
+                                }
+                            }
+                            Ok(())
+                        })();
+                        if result.is_err() {
+                            failed.store(true, AtomicOrdering::Relaxed);
+                        }
+                        result
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .try_for_each(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+        })
+    }
+
+    /// Open a blob for reading its content
+    pub fn blob(&self, id: &BlobId) -> Result<BlobReader, Error> {
+        let source = self.pages()?;
+        let (sequence, _) = id.location(source.header.page_size);
+
+        let data = if id.relation == self.relation {
+            self.data_page(sequence)?
+        } else {
+            source
+                .pages
+                .iter()
+                .find(|p| p.relation == id.relation && p.sequence == sequence)
+        };
+        let data =
+            data.ok_or_else(|| Error::Other(format!("Data page of blob {} not found", id)))?;
+
+        BlobReader::open(id, data, source.buffer.clone(), source.header.page_size)
+    }
+
+    /// Index root page of the table, none on backups
+    pub fn index_root(&self) -> Result<Option<IndexRootPage>, Error> {
+        let source = match &self.source {
+            TableSource::Pages(source) => source,
+            TableSource::Backup(_) => return Ok(None),
+        };
+
+        for data in source.pages.iter() {
+            // RDB$PAGES table
+            if data.relation == 0 {
+                for rec in data.get_records()? {
+                    let rec_data = rec.read()?;
+
+                    if rec_data.len() < 18 {
+                        continue;
+                    }
+
+                    // RDB$RELATION_ID and RDB$PAGE_TYPE fields
+                    let relation = LittleEndian::read_u16(&rec_data[8..10]);
+                    let ptype = LittleEndian::read_i16(&rec_data[16..18]);
+
+                    if relation != self.relation || ptype != 0x06 {
+                        continue;
+                    }
+
+                    // RDB$PAGE_NUMBER field
+                    let pageno = LittleEndian::read_u32(&rec_data[4..8]);
+
+                    let bytes = read_page(&source.buffer, source.header.page_size, pageno)?;
+                    return Ok(Some(IndexRootPage::from_bytes(bytes)?));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Load the indexes of the table
+    ///
+    /// Backups only keep the definition of the indexes, so the
+    /// tables read from them have none.
+    pub fn indexes(&self) -> Result<Vec<Index>, Error> {
+        Ok(self.cached_indexes()?.to_vec())
+    }
+
+    /// Indexes of the table, loaded on the first use
+    fn cached_indexes(&self) -> Result<&[Index], Error> {
+        if let Some(indexes) = self.indexes.get() {
+            return Ok(indexes);
+        }
+
+        let indexes = self.load_indexes()?;
+        Ok(self.indexes.get_or_init(|| indexes))
+    }
+
+    fn load_indexes(&self) -> Result<Vec<Index>, Error> {
+        let source = match &self.source {
+            TableSource::Pages(source) => source,
+            TableSource::Backup(_) => return Ok(vec![]),
+        };
+
+        let irt = self.index_root()?;
+        let mut indexes = vec![];
+
+        for data in source.pages.iter() {
+            // RDB$INDICES table
+            if data.relation == 4 {
+                for rec in data.get_records()? {
+                    let rec_data = rec.read()?;
+
+                    if rec_data.len() < 118 {
+                        continue;
+                    }
+
+                    // RDB$RELATION_NAME field
+                    let brname = &rec_data[35..66];
+                    let rname = String::from_utf8_lossy(brname).trim().to_string();
+
+                    if rname != self.name {
+                        continue;
+                    }
+
+                    // RDB$INDEX_NAME field
+                    let bname = &rec_data[4..35];
+                    let name = String::from_utf8_lossy(bname).trim().to_string();
+
+                    // RDB$INDEX_ID field
+                    let id = LittleEndian::read_u16(&rec_data[66..68]);
+
+                    // RDB$UNIQUE_FLAG field
+                    let unique = LittleEndian::read_i16(&rec_data[68..70]) == 1;
+
+                    // RDB$INDEX_INACTIVE field
+                    let inactive = LittleEndian::read_i16(&rec_data[82..84]) == 1;
+
+                    // RDB$INDEX_TYPE field
+                    let descending = LittleEndian::read_i16(&rec_data[84..86]) == 1;
+
+                    // RDB$FOREIGN_KEY field
+                    let bforeign = &rec_data[86..117];
+                    let foreign = String::from_utf8_lossy(bforeign)
+                        .trim_end_matches(['\0', ' '])
+                        .to_string();
+                    let foreign_key = Some(foreign).filter(|f| !f.is_empty());
+
+                    let segments = self.index_segments(&name)?;
+
+                    let root = irt
+                        .as_ref()
+                        .and_then(|irt| irt.indexes.get((id as usize).wrapping_sub(1)));
+
+                    indexes.push(Index::new(
+                        name,
+                        id,
+                        unique,
+                        descending,
+                        inactive,
+                        foreign_key,
+                        segments,
+                        root,
+                        source.header.page_size,
+                        source.buffer.clone(),
+                    ));
+                }
+            }
+        }
+
+        indexes.sort_by_key(|i| i.id);
+
+        Ok(indexes)
+    }
+
+    /// Index of the table by its name
+    pub fn index(&self, name: &str) -> Result<Index, Error> {
+        self.find_index(name).cloned()
+    }
+
+    fn find_index(&self, name: &str) -> Result<&Index, Error> {
+        self.cached_indexes()?
+            .iter()
+            .find(|i| i.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::Other(format!("Index {} not found on table {}", name, self.name)))
+    }
+
+    /// Check the indexes against the rows of the table
+    pub fn check_indexes(&self) -> Result<Vec<IndexCheck>, Error> {
+        self.cached_indexes()?
+            .iter()
+            .map(|index| check_index(self, index))
+            .collect()
+    }
+
+    /// Row by its RDB$DB_KEY
+    pub fn get_by_db_key(&self, key: &DbKey) -> Result<Option<Row>, Error> {
+        if key.relation != self.relation {
+            return Err(Error::Other(format!(
+                "DB key {} belongs to the relation {}, not to the table {}",
+                key, key.relation, self.name
+            )));
+        }
+
+        self.prepare()?.get(key.record_number)
+    }
+
+    /// Rows with the key equal to the values, using the index
+    ///
+    /// The values can be only of the first segments of the key.
+    pub fn lookup(&self, index: &str, key: &[Value]) -> Result<Vec<Row>, Error> {
+        self.range(index, key, key)
+    }
+
+    /// Rows with the key between the values, inclusive, using the
+    /// index and following its order
+    pub fn range(&self, index: &str, from: &[Value], to: &[Value]) -> Result<Vec<Row>, Error> {
+        let index = self.find_index(index)?;
+        let ptable = self.prepare()?;
+
+        let lower = index.encode_key(from, &ptable.columns)?;
+        let upper = index.encode_key(to, &ptable.columns)?;
+
+        let mut numbers = HashSet::new();
+        let mut rows = vec![];
+
+        for entry in index.range(from, to, &ptable.columns)? {
+            // Updated records keep the entries of the old
+            // versions until the garbage collection
+            if !numbers.insert(entry.record_number) {
+                continue;
+            }
+
+            let row = match ptable.get(entry.record_number)? {
+                Some(row) => row,
+                None => continue,
+            };
+
+            let key = index.row_key(&row, &ptable.columns)?;
+            if compare_segments(&key, &lower) != Ordering::Less
+                && compare_segments(&key, &upper) != Ordering::Greater
+            {
+                rows.push(row);
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Field names of each segment of the index
+    fn index_segments(&self, index: &str) -> Result<Vec<String>, Error> {
+        let mut segments = vec![];
+
+        for data in self.pages()?.pages.iter() {
+            // RDB$INDEX_SEGMENTS table
+            if data.relation == 3 {
+                for rec in data.get_records()? {
+                    let rec_data = rec.read()?;
+
+                    if rec_data.len() < 68 {
+                        continue;
+                    }
+
+                    // RDB$INDEX_NAME field
+                    let bname = &rec_data[4..35];
+                    let name = String::from_utf8_lossy(bname).trim().to_string();
+
+                    if name != index {
+                        continue;
+                    }
+
+                    // RDB$FIELD_NAME field
+                    let bfield = &rec_data[35..66];
+                    let field = String::from_utf8_lossy(bfield).trim().to_string();
+
+                    // RDB$FIELD_POSITION field
+                    let position = LittleEndian::read_i16(&rec_data[66..68]);
+
+                    segments.push((position, field));
+                }
+            }
+        }
+
+        segments.sort_by_key(|s| s.0);
+
+        Ok(segments.into_iter().map(|(_, field)| field).collect())
+    }
+}
+
+/// Preparated table for rows acesss
+pub struct TablePreparated<'a> {
+    /// Positions of the data pages of the table not read yet
+    pages: Iter<'a, usize>,
+    current_page: Option<&'a DataPage>,
+    current_record_idx: usize,
+    /// Records of the backup not read yet
+    records: Range<usize>,
+    table: &'a Table,
+    pub columns: Vec<Column>,
+    /// Places of the columns on the records
+    layout: Vec<Option<(usize, usize)>>,
+    /// Position of each column among the ones of the table, for
+    /// the records of backups
+    positions: Vec<usize>,
+    /// Columns of the table and its places, before the projection
+    table_columns: Vec<Column>,
+    table_layout: Vec<Option<(usize, usize)>>,
+    /// Load the blobs and arrays of the rows, only for the columns
+    /// picked by the projection
+    projected: bool,
+    /// Condition of the rows to read
+    filter: Option<Filter>,
+    /// Rows still to skip, without decoding them
+    skip: usize,
+    /// Rows still to read
+    limit: Option<usize>,
+    /// Uncompressed record, reused by the rows read
+    rec_data: Vec<u8>,
+}
+
+/// Record found by the cursor, not uncompressed yet
+#[derive(Clone)]
+enum Found<'a> {
+    Record(RecordHeader<'a>, u64),
+    Backup(&'a BackupRecord),
+}
+
+impl<'a> TablePreparated<'a> {
+    pub fn load(table: &'a Table) -> Result<Self, Error> {
+        let columns = table.columns()?.to_vec();
+
+        // The rows of backups have no layout
+        let (pages, records, layout) = match &table.source {
+            TableSource::Pages(_) => (
+                table.table_pages()?.positions.iter(),
+                0..0,
+                layout(&columns),
+            ),
+            TableSource::Backup(backup) => ([].iter(), 0..backup.records.len(), vec![]),
+        };
 
         Ok(TablePreparated {
+            table_columns: columns.clone(),
+            table_layout: layout.clone(),
+            projected: false,
+            filter: None,
+            layout,
+            positions: (0..columns.len()).collect(),
+            skip: 0,
+            limit: None,
+            rec_data: vec![],
             columns,
             table,
             pages,
+            records,
             current_record_idx: 0,
             current_page: None,
         })
@@ -177,37 +714,568 @@ impl<'a> TablePreparated<'a> {
 
     /// Return a row from the table using a cursor
     pub fn read(&mut self) -> Result<Option<Row>, Error> {
-        if let Some(data) = self.current_page {
-            self.current_record_idx = self.current_record_idx + 1;
-            if self.current_record_idx >= data.records.len() {
-                self.current_page = None;
+        match self.advance()? {
+            Some(RowRecord::Record(record_number)) => {
+                Ok(Some(self.load_row(&self.rec_data, record_number)?))
             }
+            Some(RowRecord::Backup(rec)) => Ok(Some(rec.load_row(&self.columns, &self.positions)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return a row borrowing the record, with the values decoded
+    /// only when asked
+    pub fn read_ref(&mut self) -> Result<Option<RowRef<'_>>, Error> {
+        let record = match self.advance()? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+
+        Ok(Some(RowRef {
+            ptable: self,
+            record,
+        }))
+    }
+
+    /// Decode only the columns named, in the given order
+    ///
+    /// The blobs and arrays of these columns are loaded on the rows,
+    /// without a projection only their ids are.
+    pub fn project(mut self, names: &[&str]) -> Result<Self, Error> {
+        let mut picked = vec![];
+        for name in names {
+            let pos = self
+                .columns
+                .iter()
+                .position(|c| c.name.eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| {
+                    Error::Other(format!(
+                        "Column {} not found on table {}",
+                        name, self.table.name
+                    ))
+                })?;
+            picked.push(pos);
+        }
+
+        self.columns = picked.iter().map(|p| self.columns[*p].clone()).collect();
+        self.positions = picked.iter().map(|p| self.positions[*p]).collect();
+        self.projected = true;
+        // The rows of backups have no layout
+        if !self.layout.is_empty() {
+            self.layout = picked.iter().map(|p| self.layout[*p]).collect();
         }
 
-        if self.current_page.is_none() {
-            while let Some(data) = self.pages.next() {
-                if data.relation == self.table.relation {
-                    self.current_page = Some(data);
-                    self.current_record_idx = 0;
+        Ok(self)
+    }
+
+    /// Read only the rows matching the filter, along with the
+    /// filters given before
+    ///
+    /// The filter can use any column of the table, even the ones
+    /// left out by the projection, and only its columns are decoded
+    /// for checking the rows. The rows left out don't count for the
+    /// skip and the limit.
+    pub fn filter_by(mut self, filter: Filter) -> Result<Self, Error> {
+        for name in filter.columns() {
+            self.table_column(name)?;
+        }
+
+        self.filter = Some(match self.filter.take() {
+            Some(current) => current.and(filter),
+            None => filter,
+        });
+
+        Ok(self)
+    }
+
+    /// Position of the column among the ones of the table
+    fn table_column(&self, name: &str) -> Result<usize, Error> {
+        self.table_columns
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| {
+                Error::Other(format!(
+                    "Column {} not found on table {}",
+                    name, self.table.name
+                ))
+            })
+    }
+
+    /// Leave out the next `count` rows, without decoding them
+    pub fn skip(mut self, count: usize) -> Self {
+        self.skip += count;
+        self
+    }
+
+    /// Read at most `count` rows more
+    pub fn limit(mut self, count: usize) -> Self {
+        self.limit = Some(self.limit.map_or(count, |limit| limit.min(count)));
+        self
+    }
+
+    /// Move to the next row, leaving out the ones not matching the
+    /// filter and the ones to skip
+    ///
+    /// The records are uncompressed on the buffer of the cursor, only
+    /// when they must be checked or read.
+    fn advance(&mut self) -> Result<Option<RowRecord<'a>>, Error> {
+        loop {
+            if self.limit == Some(0) {
+                return Ok(None);
+            }
+
+            let found = match self.next_record()? {
+                Some(found) => found,
+                None => return Ok(None),
+            };
+
+            let checked = if self.filter.is_some() {
+                let record = self.uncompress(found.clone())?;
+                if !self.matches(&record)? {
+                    continue;
                 }
+                Some(record)
+            } else {
+                None
+            };
+
+            if self.skip > 0 {
+                self.skip -= 1;
+                continue;
+            }
+            if let Some(limit) = self.limit.as_mut() {
+                *limit -= 1;
+            }
+
+            return match checked {
+                Some(record) => Ok(Some(record)),
+                None => Ok(Some(self.uncompress(found)?)),
+            };
+        }
+    }
+
+    /// Uncompress the record found on the buffer
+    fn uncompress(&mut self, found: Found<'a>) -> Result<RowRecord<'a>, Error> {
+        match found {
+            Found::Record(rec, record_number) => {
+                rec.read_into(&mut self.rec_data)?;
+                Ok(RowRecord::Record(record_number))
             }
+            Found::Backup(rec) => Ok(RowRecord::Backup(rec)),
+        }
+    }
+
+    /// Check the record against the filter, decoding only the
+    /// columns needed
+    fn matches(&self, record: &RowRecord) -> Result<bool, Error> {
+        let filter = match &self.filter {
+            Some(filter) => filter,
+            None => return Ok(true),
+        };
+
+        let result = filter.eval(&mut |name| {
+            let pos = self.table_column(name)?;
+            let place = self.table_layout.get(pos).copied().flatten();
+
+            self.value(record, &self.table_columns[pos], place, pos)
+        })?;
+
+        Ok(result == Some(true))
+    }
+
+    /// Value of a column of the record on the buffer, with the blobs
+    /// and arrays loaded
+    fn value(
+        &self,
+        record: &RowRecord,
+        col: &Column,
+        place: Option<(usize, usize)>,
+        position: usize,
+    ) -> Result<Option<Value>, Error> {
+        let val = match record {
+            RowRecord::Record(_) => match column_bytes(place, &self.rec_data) {
+                Some(bcol) => record_value(col, bcol, &self.rec_data)?,
+                None => None,
+            },
+            RowRecord::Backup(rec) => rec.value(col, position)?,
+        };
 
+        match val {
+            Some(Value::Blob(id)) => Ok(Some(self.load_blob(col, &id)?)),
+            val => Ok(val),
+        }
+    }
+
+    /// Next current record of the table
+    fn next_record(&mut self) -> Result<Option<Found<'a>>, Error> {
+        let table = self.table;
+        if let TableSource::Backup(backup) = &table.source {
+            let rec = self.records.next().and_then(|n| backup.records.get(n));
+
+            return Ok(rec.map(Found::Backup));
+        }
+
+        let source = table.pages()?;
+        loop {
             if self.current_page.is_none() {
-                return Ok(None);
+                self.current_page = self.pages.next().map(|pos| &source.pages[*pos]);
+                self.current_record_idx = 0;
+
+                if self.current_page.is_none() {
+                    return Ok(None);
+                }
+            }
+
+            if let Some(data) = self.current_page {
+                if self.current_record_idx >= data.records.len() {
+                    self.current_page = None;
+                    continue;
+                }
+
+                let line = self.current_record_idx;
+                let idx = data.records[line];
+                self.current_record_idx += 1;
+
+                if let Some(rec) = data.get_record(idx)? {
+                    // Blobs and old versions of records are
+                    // stored on the same pages
+                    if !rec.is_primary() {
+                        continue;
+                    }
+
+                    let max = DataPage::max_records(source.header.page_size) as u64;
+                    let record_number = data.sequence as u64 * max + line as u64;
+
+                    return Ok(Some(Found::Record(rec, record_number)));
+                }
             }
         }
+    }
+
+    /// Split the rows not read yet on up to `parts` cursors, each
+    /// one with a range of the data pages, for reading them on
+    /// several threads
+    ///
+    /// The rows of the data page being read stay on the first one,
+    /// the rows to skip and the limit aren't taken to the parts, but
+    /// the filter is.
+    pub fn split(self, parts: usize) -> Vec<TablePreparated<'a>> {
+        let pages = self.pages.as_slice();
+        let records = self.records.clone();
+        let parts = parts.max(1);
+        let page_chunk = pages.len().div_ceil(parts);
+        let record_chunk = records.len().div_ceil(parts);
 
-        if let Some(data) = self.current_page {
-            let idx = data.records[self.current_record_idx];
-            if let Some(rec) = data.get_record(idx)? {
+        let mut split = vec![];
+        for part in 0..parts {
+            let pstart = (part * page_chunk).min(pages.len());
+            let pend = (pstart + page_chunk).min(pages.len());
+            let rstart = (records.start + part * record_chunk).min(records.end);
+            let rend = (rstart + record_chunk).min(records.end);
+
+            if part > 0 && pstart == pend && rstart == rend {
+                break;
+            }
+
+            let first = part == 0;
+            split.push(TablePreparated {
+                pages: pages[pstart..pend].iter(),
+                current_page: self.current_page.filter(|_| first),
+                current_record_idx: if first { self.current_record_idx } else { 0 },
+                records: rstart..rend,
+                table: self.table,
+                columns: self.columns.clone(),
+                layout: self.layout.clone(),
+                positions: self.positions.clone(),
+                table_columns: self.table_columns.clone(),
+                table_layout: self.table_layout.clone(),
+                projected: self.projected,
+                filter: self.filter.clone(),
+                skip: 0,
+                limit: None,
+                rec_data: vec![],
+            });
+        }
+
+        split
+    }
+
+    /// Rows not read yet as a parallel iterator, the data pages are
+    /// spread between the threads of the rayon pool
+    #[cfg(feature = "rayon")]
+    pub fn par_rows(self) -> impl rayon::iter::ParallelIterator<Item = Result<Row, Error>> + 'a {
+        use rayon::prelude::*;
+
+        // More parts than threads, for balancing tables with pages
+        // fuller than others
+        let parts = rayon::current_num_threads() * 4;
+
+        self.split(parts)
+            .into_par_iter()
+            .flat_map_iter(|mut part| std::iter::from_fn(move || part.read().transpose()))
+    }
+
+    /// Row by its record number, like the ones on the indexes
+    ///
+    /// The record number is the sequence of the data page times
+    /// the max records per page, plus the line on the page. The
+    /// rows of backups are numbered by their order.
+    pub fn get(&self, record_number: u64) -> Result<Option<Row>, Error> {
+        if let TableSource::Backup(backup) = &self.table.source {
+            return backup
+                .records
+                .get(record_number as usize)
+                .map(|rec| rec.load_row(&self.columns, &self.positions))
+                .transpose();
+        }
+
+        match self.record(record_number)? {
+            Some(rec) if rec.is_primary() => Ok(Some(self.load_row(&rec.read()?, record_number)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Header of any record by its number, not only the rows
+    pub(crate) fn record(&self, record_number: u64) -> Result<Option<RecordHeader<'_>>, Error> {
+        let source = self.table.pages()?;
+        let max = DataPage::max_records(source.header.page_size) as u64;
+        let sequence = (record_number / max) as u32;
+        let line = (record_number % max) as usize;
+
+        match self
+            .table
+            .data_page(sequence)?
+            .and_then(|data| data.records.get(line).map(|idx| (data, *idx)))
+        {
+            Some((data, idx)) => data.get_record(idx),
+            None => Ok(None),
+        }
+    }
+
+    fn load_row(&self, rec_data: &[u8], record_number: u64) -> Result<Row, Error> {
+        let mut row = Row::load_with_layout(&self.columns, &self.layout, rec_data)?;
+        row.db_key = Some(DbKey {
+            relation: self.table.relation,
+            record_number,
+        });
+        self.load_blobs(&mut row)?;
+
+        Ok(row)
+    }
+
+    /// Replace the blob ids of the row by its content, once the
+    /// columns are projected
+    fn load_blobs(&self, row: &mut Row) -> Result<(), Error> {
+        if !self.projected {
+            return Ok(());
+        }
+
+        for (col, val) in self.columns.iter().zip(row.values.iter_mut()) {
+            if let Some(Value::Blob(id)) = val {
+                *val = Some(self.load_blob(col, id)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Content of the blob or array of the column
+    fn load_blob(&self, col: &Column, id: &BlobId) -> Result<Value, Error> {
+        let mut content = vec![];
+        self.table.blob(id)?.read_to_end(&mut content)?;
+
+        if col.is_array() {
+            parse_array(col, &content)
+        } else {
+            parse_blob_content(col, content)
+        }
+    }
+}
+
+impl Iterator for TablePreparated<'_> {
+    type Item = Result<Row, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+/// Row read from a table, borrowing its record until the next one
+/// is read
+pub struct RowRef<'r> {
+    ptable: &'r TablePreparated<'r>,
+    record: RowRecord<'r>,
+}
+
+enum RowRecord<'r> {
+    /// Record number, with the record on the cursor buffer
+    Record(u64),
+    Backup(&'r BackupRecord),
+}
+
+impl<'r> RowRef<'r> {
+    pub fn columns(&self) -> &'r [Column] {
+        &self.ptable.columns
+    }
+
+    /// Amount of columns of the row
+    pub fn len(&self) -> usize {
+        self.ptable.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// RDB$DB_KEY of the record, none on backups
+    pub fn db_key(&self) -> Option<DbKey> {
+        match self.record {
+            RowRecord::Record(record_number) => Some(DbKey {
+                relation: self.ptable.table.relation,
+                record_number,
+            }),
+            RowRecord::Backup(_) => None,
+        }
+    }
+
+    fn column(&self, idx: usize) -> Result<&'r Column, Error> {
+        self.ptable.columns.get(idx).ok_or_else(|| {
+            Error::Other(format!(
+                "Column {} out of the {} of the row",
+                idx,
+                self.len()
+            ))
+        })
+    }
+
+    /// Raw bytes of the column, none when not stored
+    pub fn raw(&self, idx: usize) -> Option<&'r [u8]> {
+        match self.record {
+            RowRecord::Record(_) => {
+                column_bytes(*self.ptable.layout.get(idx)?, &self.ptable.rec_data)
+            }
+            RowRecord::Backup(rec) => rec.bytes(*self.ptable.positions.get(idx)?),
+        }
+    }
+
+    /// Decode the value of the column, with the blobs and arrays
+    /// loaded even without a projection
+    pub fn get(&self, idx: usize) -> Result<Option<Value>, Error> {
+        let col = self.column(idx)?;
+        let place = self.ptable.layout.get(idx).copied().flatten();
+
+        self.ptable
+            .value(&self.record, col, place, self.ptable.positions[idx])
+    }
+
+    /// Text of a char or varchar column, borrowing the record when
+    /// it's already valid UTF-8
+    pub fn get_str(&self, idx: usize) -> Result<Option<Cow<'r, str>>, Error> {
+        let col = self.column(idx)?;
+
+        let bytes = match self.record {
+            RowRecord::Record(_) => match self.raw(idx) {
+                Some(bcol) if has_value(col, bcol, &self.ptable.rec_data) => bcol,
+                _ => return Ok(None),
+            },
+            RowRecord::Backup(rec) => match rec.bytes(self.ptable.positions[idx]) {
+                Some(bytes) => bytes,
+                None => return Ok(None),
+            },
+        };
+
+        Ok(Some(parse_text(col, bytes)?))
+    }
+
+    /// Decode all the values to an owned row
+    pub fn to_row(&self) -> Result<Row, Error> {
+        let mut row = match self.record {
+            RowRecord::Record(_) => Row::load_with_layout(
+                &self.ptable.columns,
+                &self.ptable.layout,
+                &self.ptable.rec_data,
+            )?,
+            RowRecord::Backup(rec) => {
+                return rec.load_row(&self.ptable.columns, &self.ptable.positions)
+            }
+        };
+        row.db_key = self.db_key();
+        self.ptable.load_blobs(&mut row)?;
+
+        Ok(row)
+    }
+}
+
+/// Bounds of each dimension of an array field
+fn load_dimensions(pages: &[DataPage], field: &str) -> Result<Vec<(i32, i32)>, Error> {
+    let mut dimensions = vec![];
+
+    for data in pages.iter() {
+        // RDB$FIELD_DIMENSIONS table
+        if data.relation == 21 {
+            for rec in data.get_records()? {
                 let rec_data = rec.read()?;
 
-                let row = Row::load(&self.columns, rec_data)?;
+                if rec_data.len() < 48 {
+                    continue;
+                }
 
-                return Ok(Some(row));
+                // RDB$FIELD_NAME field
+                let bname = &rec_data[4..35];
+                let name = String::from_utf8_lossy(bname).trim().to_string();
+
+                if name != field {
+                    continue;
+                }
+
+                // RDB$DIMENSION field
+                let dimension = LittleEndian::read_i16(&rec_data[36..38]);
+                // RDB$LOWER_BOUND and RDB$UPPER_BOUND fields
+                let lower = LittleEndian::read_i32(&rec_data[40..44]);
+                let upper = LittleEndian::read_i32(&rec_data[44..48]);
+
+                dimensions.push((dimension, lower, upper));
             }
         }
+    }
 
-        Ok(None)
+    dimensions.sort_by_key(|d| d.0);
+
+    Ok(dimensions
+        .into_iter()
+        .map(|(_, lower, upper)| (lower, upper))
+        .collect())
+}
+
+/// Field of a system table record at the offset, none when the
+/// record is too short to have it
+fn field_i16(rec_data: &[u8], offset: usize) -> Option<i16> {
+    rec_data.get(offset..offset + 2).map(LittleEndian::read_i16)
+}
+
+/// Maximum bytes used by each character of the charset
+fn load_bytes_per_character(pages: &[DataPage], charset: i16) -> Result<usize, Error> {
+    for data in pages.iter() {
+        // RDB$CHARACTER_SETS table
+        if data.relation == 28 {
+            for rec in data.get_records()? {
+                let rec_data = rec.read()?;
+
+                if rec_data.len() < 154 {
+                    continue;
+                }
+
+                // RDB$CHARACTER_SET_ID field
+                if LittleEndian::read_i16(&rec_data[104..106]) != charset {
+                    continue;
+                }
+
+                // RDB$BYTES_PER_CHARACTER field
+                let bytes = LittleEndian::read_i16(&rec_data[152..154]);
+                return Ok(bytes.max(1) as usize);
+            }
+        }
     }
+
+    Ok(1)
 }
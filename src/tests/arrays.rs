@@ -0,0 +1,81 @@
+use crate::*;
+
+#[test]
+fn reading_varchar_array() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "JOB");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let ptable = table.prepare()?;
+    let col = &ptable.columns[7];
+    assert_eq!("LANGUAGE_REQ", col.name);
+    assert_eq!(vec![(1, 5)], col.dimensions);
+
+    let mut ptable = ptable.project(&["LANGUAGE_REQ"])?;
+    let mut arrays = vec![];
+    while let Some(row) = ptable.read()? {
+        if let Some(val) = &row.values[0] {
+            arrays.push(val.to_string());
+        }
+    }
+    assert_eq!(10, arrays.len());
+    assert_eq!("[Japanese\n, Mandarin\n, English\n, \n, \n]", arrays[0]);
+    assert_eq!("[English\n, Spanish\n, \n, \n, \n]", arrays[3]);
+
+    Ok(())
+}
+
+#[test]
+fn reading_integer_array() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "PROJ_DEPT_BUDGET");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let ptable = table.prepare()?;
+    let col = &ptable.columns[3];
+    assert_eq!("QUART_HEAD_CNT", col.name);
+    assert_eq!(ColumnType::Integer, col.tp);
+    assert_eq!(vec![(1, 4)], col.dimensions);
+
+    let mut ptable = ptable.project(&["QUART_HEAD_CNT", "PROJ_ID"])?;
+    let row = ptable.read()?;
+    assert!(row.is_some());
+    let row = row.unwrap();
+    assert_eq!(
+        Some(Value::Array(vec![
+            Some(Value::Int(1)),
+            Some(Value::Int(1)),
+            Some(Value::Int(1)),
+            Some(Value::Int(0))
+        ])),
+        row.values[0]
+    );
+    assert_eq!(Some(Value::String("GUIDE".to_string())), row.values[1]);
+
+    Ok(())
+}
+
+#[test]
+fn rejecting_array_without_dimensions() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+    let table = tables
+        .into_iter()
+        .find(|t| t.name == "PROJ_DEPT_BUDGET")
+        .unwrap();
+
+    let mut col = table.prepare()?.columns[3].clone();
+    col.dimensions.clear();
+    assert!(crate::row::parse_array(&col, &[0; 64]).is_err());
+
+    Ok(())
+}
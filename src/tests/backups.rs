@@ -0,0 +1,550 @@
+use crate::*;
+
+/// Attribute with a short value
+fn att(buf: &mut Vec<u8>, id: u8, value: &[u8]) {
+    buf.push(id);
+    buf.push(value.len() as u8);
+    buf.extend_from_slice(value);
+}
+
+fn att_int(buf: &mut Vec<u8>, id: u8, value: i32) {
+    att(buf, id, &value.to_le_bytes());
+}
+
+/// Attribute of a blob with BLR
+fn att_blr(buf: &mut Vec<u8>, id: u8, blr: &[u8]) {
+    att_int(buf, id, blr.len() as i32);
+    buf.extend_from_slice(blr);
+}
+
+/// Attribute of a text blob, one segment by line
+fn att_source(buf: &mut Vec<u8>, id: u8, lines: &[&str]) {
+    let len: usize = lines.iter().map(|l| l.len() + 2).sum();
+    att_int(buf, id, len as i32);
+    for line in lines {
+        buf.extend_from_slice(&(line.len() as u16).to_le_bytes());
+        buf.extend_from_slice(line.as_bytes());
+    }
+}
+
+/// Run length encoding of the data records
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut literal = vec![];
+    let mut i = 0;
+
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(127)
+            .take_while(|b| **b == data[i])
+            .count();
+
+        if (run >= 3 || literal.len() == 127) && !literal.is_empty() {
+            out.push(literal.len() as u8);
+            out.append(&mut literal);
+        }
+        if run >= 3 {
+            out.push((-(run as i8)) as u8);
+            out.push(data[i]);
+            i += run;
+        } else {
+            literal.push(data[i]);
+            i += 1;
+        }
+    }
+    if !literal.is_empty() {
+        out.push(literal.len() as u8);
+        out.extend(literal);
+    }
+
+    out
+}
+
+struct Person<'a> {
+    id: i32,
+    name: Option<&'a str>,
+    notes: Option<&'a [&'a str]>,
+    salary: i32,
+    codes: Option<&'a [i16]>,
+}
+
+/// Message of the row, with the layout of the platform
+fn native_message(p: &Person) -> Vec<u8> {
+    let mut msg = vec![];
+    let align = |msg: &mut Vec<u8>, n: usize| msg.resize(msg.len().div_ceil(n) * n, 0);
+
+    msg.extend_from_slice(&p.id.to_le_bytes());
+    // VARCHAR(10) of UTF8
+    let name = p.name.unwrap_or_default();
+    msg.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    let mut bname = name.as_bytes().to_vec();
+    bname.resize(40, 0);
+    msg.extend(bname);
+    align(&mut msg, 4);
+    msg.extend_from_slice(&[0xaa; 8]);
+    msg.extend_from_slice(&p.salary.to_le_bytes());
+    msg.extend_from_slice(&[0xbb; 8]);
+
+    for null in [
+        false,
+        p.name.is_none(),
+        p.notes.is_none(),
+        false,
+        p.codes.is_none(),
+    ] {
+        msg.extend_from_slice(&(if null { -1i16 } else { 0 }).to_le_bytes());
+    }
+
+    msg
+}
+
+/// Message of the row encoded with XDR
+fn xdr_message(p: &Person) -> Vec<u8> {
+    let mut msg = vec![];
+
+    msg.extend_from_slice(&p.id.to_be_bytes());
+    let name = p.name.unwrap_or_default();
+    msg.extend_from_slice(&(name.len() as u32).to_be_bytes());
+    msg.extend_from_slice(name.as_bytes());
+    msg.resize(msg.len().div_ceil(4) * 4, 0);
+    msg.extend_from_slice(&[0xaa; 8]);
+    msg.extend_from_slice(&p.salary.to_be_bytes());
+    msg.extend_from_slice(&[0xbb; 8]);
+
+    for null in [
+        false,
+        p.name.is_none(),
+        p.notes.is_none(),
+        false,
+        p.codes.is_none(),
+    ] {
+        msg.extend_from_slice(&(if null { -1i32 } else { 0 }).to_be_bytes());
+    }
+
+    msg
+}
+
+/// Backup of a database with a single table, its metadata and rows
+fn people_backup(transportable: bool, compressed: bool) -> Vec<u8> {
+    let mut b = vec![];
+
+    // rec_burp
+    b.push(0);
+    att_int(&mut b, 2, 10);
+    att_int(&mut b, 4, compressed as i32);
+    att_int(&mut b, 5, transportable as i32);
+    b.push(0);
+
+    // rec_database
+    b.push(1);
+    att(&mut b, 1, b"/srv/people.fdb");
+    att_int(&mut b, 5, 8192);
+    att_source(&mut b, 10, &["People ", "database"]);
+    att_int(&mut b, 14, 3);
+    b.push(0);
+
+    // rec_global_field of each domain
+    let domains: [(&str, i32, i32, i32, i32); 6] = [
+        ("D_ID", 8, 4, 0, 0),
+        ("D_NAME", 37, 40, 0, 4),
+        ("D_NOTES", 261, 8, 1, 4),
+        ("D_SALARY", 8, 4, -2, 0),
+        ("D_CODES", 7, 2, 0, 0),
+        ("D_TOTAL", 8, 4, 0, 0),
+    ];
+    for (name, tp, length, scale, charset) in domains {
+        b.push(2);
+        att(&mut b, 1, name.as_bytes());
+        att_int(&mut b, 7, tp);
+        att_int(&mut b, 9, length);
+        att_int(&mut b, 10, scale);
+        if tp == 261 {
+            att_int(&mut b, 8, 1);
+            att_blr(&mut b, 14, &[0x05, 0x15, 0x4c]);
+        }
+        if charset > 0 {
+            att_int(&mut b, 34, charset);
+            att_int(&mut b, 33, 10);
+        }
+        if name == "D_CODES" {
+            att_int(&mut b, 26, 1);
+        }
+        if name == "D_TOTAL" {
+            att_blr(&mut b, 17, &[0x05, 0x4c]);
+            att_source(&mut b, 29, &["(salary * 12)"]);
+        }
+        b.push(0);
+    }
+
+    // rec_security_class, skipped
+    b.push(12);
+    att(&mut b, 1, b"SQL$1");
+    att_blr(&mut b, 2, &[1, 2, 3, 4, 5]);
+    b.push(0);
+
+    // rec_relation and its rec_field
+    b.push(3);
+    att(&mut b, 1, b"PEOPLE");
+    att_source(&mut b, 13, &["Registered people"]);
+    b.push(0);
+    let fields = ["ID", "NAME", "NOTES", "SALARY", "CODES", "TOTAL"];
+    for (i, (field, domain)) in fields.iter().zip(domains.iter()).enumerate() {
+        b.push(4);
+        att(&mut b, 1, field.as_bytes());
+        att(&mut b, 2, domain.0.as_bytes());
+        att_int(&mut b, 12, i as i32);
+        att_int(&mut b, 21, i as i32);
+        if i == 0 {
+            att_int(&mut b, 30, 1);
+        }
+        b.push(0);
+    }
+
+    // rec_index
+    b.push(5);
+    att(&mut b, 1, b"PK_PEOPLE");
+    att_int(&mut b, 2, 1);
+    att_int(&mut b, 4, 1);
+    att(&mut b, 5, b"ID");
+    b.push(0);
+
+    // rec_relation_end
+    b.push(9);
+
+    // rec_generator
+    b.push(26);
+    att(&mut b, 1, b"GEN_PEOPLE");
+    att(&mut b, 3, &42i64.to_le_bytes());
+    att_int(&mut b, 9, 2);
+    b.push(0);
+
+    // rec_procedure, its rec_procedure_prm and rec_procedure_end
+    b.push(27);
+    att(&mut b, 1, b"GET_PEOPLE");
+    att_source(&mut b, 5, &["begin\n", "  suspend;\n", "end"]);
+    att_blr(&mut b, 6, &[0x05, 0x02, 0x4c]);
+    b.push(0);
+    b.push(28);
+    att(&mut b, 1, b"ID");
+    att_int(&mut b, 3, 1);
+    b.push(0);
+    b.push(29);
+
+    // rec_trigger
+    b.push(13);
+    att_int(&mut b, 1, 1);
+    att_blr(&mut b, 2, &[0x05, 0x4c]);
+    att(&mut b, 4, b"SET_PEOPLE_ID");
+    att(&mut b, 5, b"PEOPLE");
+    att_int(&mut b, 6, 0);
+    att_source(&mut b, 10, &["as begin end"]);
+    b.push(0);
+
+    // rec_relation_data followed by the rows
+    b.push(8);
+    att(&mut b, 1, b"PEOPLE");
+    b.push(0);
+
+    let people = [
+        Person {
+            id: 1,
+            name: Some("Zoë"),
+            notes: Some(&["hello ", "world"]),
+            salary: 123456,
+            codes: Some(&[1, 2, 3]),
+        },
+        Person {
+            id: 2,
+            name: None,
+            notes: None,
+            salary: 0,
+            codes: None,
+        },
+    ];
+    for p in people.iter() {
+        let native = native_message(p);
+        let msg = if transportable {
+            xdr_message(p)
+        } else {
+            native.clone()
+        };
+
+        // rec_data
+        b.push(6);
+        att_int(&mut b, 1, native.len() as i32);
+        if transportable {
+            att_int(&mut b, 3, msg.len() as i32);
+        }
+        b.push(2);
+        if compressed {
+            b.extend(compress(&msg));
+        } else {
+            b.extend(msg);
+        }
+
+        // rec_blob
+        if let Some(notes) = p.notes {
+            b.push(7);
+            att_int(&mut b, 1, 2);
+            att_int(&mut b, 2, 0);
+            att_int(&mut b, 3, notes.len() as i32);
+            att_int(&mut b, 4, 6);
+            b.push(5);
+            for segment in notes {
+                b.extend_from_slice(&(segment.len() as u16).to_le_bytes());
+                b.extend_from_slice(segment.as_bytes());
+            }
+        }
+
+        // rec_array
+        if let Some(codes) = p.codes {
+            b.push(23);
+            att_int(&mut b, 1, 4);
+            att_int(&mut b, 6, 1);
+            att_int(&mut b, 7, 1);
+            att_int(&mut b, 8, codes.len() as i32);
+            b.push(5);
+            let data: Vec<u8> = codes
+                .iter()
+                .flat_map(|c| {
+                    if transportable {
+                        (*c as i32).to_be_bytes().to_vec()
+                    } else {
+                        c.to_le_bytes().to_vec()
+                    }
+                })
+                .collect();
+            b.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            b.extend(data);
+        }
+    }
+
+    // rec_relation_end and rec_end
+    b.push(9);
+    b.push(10);
+
+    b
+}
+
+fn check_people(backup: &Backup) -> Result<(), Error> {
+    assert_eq!(10, backup.format);
+    assert_eq!("/srv/people.fdb", backup.database);
+    assert_eq!(8192, backup.page_size);
+    assert_eq!(3, backup.dialect);
+
+    let tables = backup.tables()?;
+    assert_eq!(1, tables.len());
+    let table = &tables[0];
+    assert_eq!("PEOPLE", table.name);
+    assert!(!table.is_system_table);
+    assert!(table.is_from_backup());
+    assert!(table.indexes()?.is_empty());
+
+    let mut ptable = table.prepare()?;
+    let columns: Vec<_> = ptable
+        .columns
+        .iter()
+        .map(|c| (c.name.as_str(), c.tp.clone(), c.computed))
+        .collect();
+    assert_eq!(
+        vec![
+            ("ID", ColumnType::Integer, false),
+            ("NAME", ColumnType::Varchar, false),
+            ("NOTES", ColumnType::Blob, false),
+            ("SALARY", ColumnType::Integer, false),
+            ("CODES", ColumnType::Smallint, false),
+            ("TOTAL", ColumnType::Integer, true),
+        ],
+        columns
+    );
+    assert!(ptable.columns[0].not_null);
+    assert_eq!(Charset::Utf8, ptable.columns[1].charset);
+
+    let row = ptable.read()?.unwrap();
+    assert_eq!(
+        vec![
+            Some(Value::Int(1)),
+            Some(Value::String("Zoë".to_string())),
+            Some(Value::String("hello world".to_string())),
+            Some(Value::Decimal(123456, -2)),
+            Some(Value::Array(vec![
+                Some(Value::SmallInt(1)),
+                Some(Value::SmallInt(2)),
+                Some(Value::SmallInt(3)),
+            ])),
+            None,
+        ],
+        row.values
+    );
+
+    let row = ptable.read()?.unwrap();
+    assert_eq!(
+        vec![
+            Some(Value::Int(2)),
+            None,
+            None,
+            Some(Value::Decimal(0, -2)),
+            None,
+            None
+        ],
+        row.values
+    );
+    assert!(ptable.read()?.is_none());
+
+    // The bounds come with the array of the first row
+    assert_eq!(vec![(1, 3)], table.prepare()?.columns[4].dimensions);
+    assert_eq!(Some(Value::Int(2)), ptable.get(1)?.unwrap().values[0]);
+
+    assert_eq!(
+        vec![BackupIndex {
+            name: "PK_PEOPLE".to_string(),
+            relation: "PEOPLE".to_string(),
+            fields: vec!["ID".to_string()],
+            unique: true,
+            descending: false,
+            inactive: false,
+            foreign_key: None,
+        }],
+        backup.indexes
+    );
+    assert_eq!(
+        vec![Generator {
+            name: "GEN_PEOPLE".to_string(),
+            id: 0,
+            is_system_generator: false,
+            value: 42,
+            initial_value: 0,
+            increment: 2,
+        }],
+        backup.generators
+    );
+    assert_eq!(
+        vec![BackupProcedure {
+            name: "GET_PEOPLE".to_string(),
+            source: Some("begin\n  suspend;\nend".to_string()),
+            parameters: vec!["ID".to_string()],
+        }],
+        backup.procedures
+    );
+    assert_eq!(
+        vec![BackupTrigger {
+            name: "SET_PEOPLE_ID".to_string(),
+            relation: Some("PEOPLE".to_string()),
+            tp: 1,
+            sequence: 0,
+            inactive: false,
+            source: Some("as begin end".to_string()),
+        }],
+        backup.triggers
+    );
+
+    Ok(())
+}
+
+#[test]
+fn reading_backup() -> Result<(), Error> {
+    let backup = Backup::open(&people_backup(false, true)[..])?;
+    assert!(backup.compressed);
+    assert!(!backup.transportable);
+
+    check_people(&backup)
+}
+
+#[test]
+fn reading_transportable_backup() -> Result<(), Error> {
+    let backup = Backup::open(&people_backup(true, false)[..])?;
+    assert!(!backup.compressed);
+    assert!(backup.transportable);
+
+    check_people(&backup)
+}
+
+#[test]
+fn projecting_backup_rows() -> Result<(), Error> {
+    let backup = Backup::open(&people_backup(false, true)[..])?;
+    let tables = backup.tables()?;
+
+    let ptable = tables[0].prepare()?.project(&["NOTES", "NAME"])?;
+    let mut ptable = ptable.skip(1);
+    let row = ptable.read_ref()?.unwrap();
+    assert_eq!(None, row.get(1)?);
+    assert_eq!(None, row.get_str(1)?);
+    assert_eq!(None, row.db_key());
+    assert!(ptable.read()?.is_none());
+
+    let mut ptable = tables[0].prepare()?.project(&["NOTES", "NAME"])?;
+    let row = ptable.read_ref()?.unwrap();
+    assert_eq!(Some(Value::String("hello world".to_string())), row.get(0)?);
+    assert_eq!(Some("Zoë"), row.get_str(1)?.as_deref());
+    assert_eq!(
+        vec![
+            Some(Value::String("hello world".to_string())),
+            Some(Value::String("Zoë".to_string())),
+        ],
+        row.to_row()?.values
+    );
+
+    // Filters on backups, using the columns left out
+    let ids = |filter: &str| -> Result<Vec<Option<Value>>, Error> {
+        tables[0]
+            .prepare()?
+            .project(&["ID"])?
+            .filter_by(filter.parse()?)?
+            .map(|row| row.map(|r| r.values[0].clone()))
+            .collect()
+    };
+    assert_eq!(vec![Some(Value::Int(1))], ids("name = 'Zoë'")?);
+    assert_eq!(vec![Some(Value::Int(1))], ids("notes like 'hello%'")?);
+    assert_eq!(vec![Some(Value::Int(2))], ids("notes is null")?);
+    assert!(ids("salary > 1000000")?.is_empty());
+
+    Ok(())
+}
+
+#[cfg(feature = "sql")]
+#[test]
+fn querying_backups() -> Result<(), Error> {
+    let backup = Backup::open(&people_backup(false, true)[..])?;
+
+    let result = backup.query("select name, notes from people where id = 1")?;
+    assert_eq!("PLAN (PEOPLE NATURAL)", result.plan);
+    assert_eq!(
+        vec![vec![
+            Some(Value::String("Zoë".to_string())),
+            Some(Value::String("hello world".to_string())),
+        ]],
+        result.rows
+    );
+
+    let result = backup.query("select count(*), count(notes) from people")?;
+    assert_eq!(
+        vec![vec![Some(Value::BigInt(2)), Some(Value::BigInt(1))]],
+        result.rows
+    );
+
+    Ok(())
+}
+
+#[test]
+fn detecting_backup_files() -> Result<(), Error> {
+    let path = std::env::temp_dir().join("rfirebird_people.fbk");
+    std::fs::write(&path, people_backup(true, true))?;
+
+    let result = (|| {
+        assert!(Backup::is_backup(&path)?);
+        assert!(!Backup::is_backup("dbs/employee.fdb")?);
+        check_people(&Backup::open_file(&path)?)
+    })();
+
+    std::fs::remove_file(&path)?;
+
+    result
+}
+
+#[test]
+fn truncated_backup() {
+    let bytes = people_backup(false, false);
+
+    assert!(Backup::open(&bytes[..bytes.len() - 1]).is_err());
+}
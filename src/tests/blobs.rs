@@ -0,0 +1,163 @@
+use std::io::Read;
+
+use crate::*;
+
+#[test]
+fn reading_segmented_blob() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "JOB");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let mut ptable = table.prepare()?;
+
+    let row1 = ptable.read()?;
+    assert!(row1.is_some());
+    let row1 = row1.unwrap();
+    assert_eq!(Some(Value::String("CEO".to_string())), row1.values[0]);
+
+    // JOB_REQUIREMENT column
+    let id = BlobId::from_bytes(&row1.raw[6])?;
+    assert_eq!("81:1ea", id.to_string());
+
+    let mut blob = table.blob(&id)?;
+    assert!(!blob.header.is_stream());
+    assert_eq!(0, blob.header.level);
+    assert_eq!(1, blob.header.count);
+
+    let mut content = String::new();
+    blob.read_to_string(&mut content)?;
+    assert_eq!("No specific requirements.\n", content);
+
+    Ok(())
+}
+
+#[test]
+fn reading_stream_blob() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "JOB");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let mut ptable = table.prepare()?;
+
+    let mut ids = vec![];
+    while let Some(row) = ptable.read()? {
+        // LANGUAGE_REQ column, an array stored as stream blob
+        let id = BlobId::from_bytes(&row.raw[7])?;
+        if id.relation != 0 {
+            ids.push(id);
+        }
+    }
+    assert_eq!(10, ids.len());
+
+    let mut blob = table.blob(&ids[0])?;
+    assert!(blob.header.is_stream());
+
+    let mut content = vec![];
+    blob.read_to_end(&mut content)?;
+    assert_eq!(blob.header.length as usize, content.len());
+    assert_eq!(&[0x01, 0x01, 0x01, 0x00], &content[0..4]);
+
+    Ok(())
+}
+
+#[test]
+fn reading_text_blob_as_string() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "JOB");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let mut ptable = table.prepare()?;
+    assert!(ptable.columns[6].is_text_blob());
+
+    // Only the id while the column isn't projected
+    let row = ptable.read()?.unwrap();
+    assert!(matches!(row.values[6], Some(Value::Blob(_))));
+
+    let mut ptable = table.prepare()?.project(&["JOB_REQUIREMENT"])?;
+    let row1 = ptable.read()?;
+    assert!(row1.is_some());
+    let row1 = row1.unwrap();
+    assert_eq!(
+        Some(Value::String("No specific requirements.\n".to_string())),
+        row1.values[0]
+    );
+
+    let row2 = ptable.read()?;
+    assert!(row2.is_some());
+    let row2 = row2.unwrap();
+    assert_eq!(
+        Some(Value::String(
+            "15+ years in finance or 5+ years as a CFO\nwith a proven track record.\nMBA or J.D. degree.\n"
+                .to_string()
+        )),
+        row2.values[0]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn reading_system_blobs() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "RDB$TRIGGERS");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let mut ptable =
+        table
+            .prepare()?
+            .project(&["RDB$TRIGGER_NAME", "RDB$TRIGGER_SOURCE", "RDB$TRIGGER_BLR"])?;
+
+    let pname = ptable
+        .columns
+        .iter()
+        .position(|c| c.name == "RDB$TRIGGER_NAME")
+        .unwrap();
+    let psource = ptable
+        .columns
+        .iter()
+        .position(|c| c.name == "RDB$TRIGGER_SOURCE")
+        .unwrap();
+    let pblr = ptable
+        .columns
+        .iter()
+        .position(|c| c.name == "RDB$TRIGGER_BLR")
+        .unwrap();
+    assert_eq!(Charset::UnicodeFss, ptable.columns[psource].charset);
+
+    let mut found = false;
+    while let Some(row) = ptable.read()? {
+        if row.values[pname] != Some(Value::String(format!("{:31}", "SET_EMP_NO"))) {
+            continue;
+        }
+        found = true;
+
+        match &row.values[psource] {
+            Some(Value::String(source)) => assert!(source.contains("gen_id(emp_no_gen, 1)")),
+            _ => panic!("RDB$TRIGGER_SOURCE should be a text"),
+        }
+
+        match &row.values[pblr] {
+            Some(Value::Blr(blr)) => assert_eq!(Some(&5), blr.first()),
+            _ => panic!("RDB$TRIGGER_BLR should be a BLR"),
+        }
+    }
+    assert!(found);
+
+    Ok(())
+}
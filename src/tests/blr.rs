@@ -0,0 +1,80 @@
+use crate::*;
+
+#[test]
+fn printing_trigger_blr() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "RDB$TRIGGERS");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let mut ptable = table
+        .prepare()?
+        .project(&["RDB$TRIGGER_NAME", "RDB$TRIGGER_BLR"])?;
+
+    let pname = ptable
+        .columns
+        .iter()
+        .position(|c| c.name == "RDB$TRIGGER_NAME")
+        .unwrap();
+    let pblr = ptable
+        .columns
+        .iter()
+        .position(|c| c.name == "RDB$TRIGGER_BLR")
+        .unwrap();
+
+    let mut found = false;
+    while let Some(row) = ptable.read()? {
+        if row.values[pname] != Some(Value::String(format!("{:31}", "SET_EMP_NO"))) {
+            continue;
+        }
+        found = true;
+
+        let blr = match &row.values[pblr] {
+            Some(Value::Blr(blr)) => print_blr(blr)?,
+            _ => panic!("RDB$TRIGGER_BLR should be a BLR"),
+        };
+        assert!(blr.starts_with("blr_version5,\nblr_begin,"));
+        assert!(blr.contains("blr_gen_id, 10, 'E','M','P','_','N','O','_','G','E','N',\n"));
+        assert!(blr.ends_with("   blr_end,\nblr_eoc"));
+    }
+    assert!(found);
+
+    Ok(())
+}
+
+#[test]
+fn printing_all_system_blr() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let mut count = 0;
+    for table in db.tables()? {
+        let blr_tables = [
+            "RDB$RELATIONS",
+            "RDB$TRIGGERS",
+            "RDB$PROCEDURES",
+            "RDB$RELATION_FIELDS",
+            "RDB$FIELDS",
+        ];
+        if !blr_tables.contains(&table.name.as_str()) {
+            continue;
+        }
+
+        let mut ptable = table.prepare()?;
+        while let Some(row) = ptable.read_ref()? {
+            for (idx, col) in row.columns().iter().enumerate() {
+                if let Some(Value::Blr(blr)) = row.get(idx)? {
+                    if let Err(e) = print_blr(&blr) {
+                        panic!("{}.{}: {} {:?}", table.name, col.name, e, blr);
+                    }
+                    count += 1;
+                }
+            }
+        }
+    }
+    assert!(count > 0);
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::borrow::Cow;
+
+use crate::*;
+
+fn employee(db: &Database) -> Result<Table, Error> {
+    let tables = db.tables()?;
+    Ok(tables.into_iter().find(|t| t.name == "EMPLOYEE").unwrap())
+}
+
+fn all_rows(table: &Table) -> Result<Vec<Row>, Error> {
+    let mut ptable = table.prepare()?;
+    let mut rows = vec![];
+    while let Some(row) = ptable.read()? {
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+#[test]
+fn iterating_rows() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let table = employee(&db)?;
+    let expected = all_rows(&table)?;
+
+    let rows = table.prepare()?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(42, rows.len());
+    for (row, expected) in rows.iter().zip(expected.iter()) {
+        assert_eq!(expected.values, row.values);
+        assert_eq!(expected.db_key, row.db_key);
+    }
+
+    let names: Vec<String> = table
+        .prepare()?
+        .project(&["last_name"])?
+        .filter_map(|row| match row {
+            Ok(row) => match &row.values[0] {
+                Some(Value::String(name)) => Some(Ok(name.clone())),
+                _ => None,
+            },
+            Err(e) => Some(Err(e)),
+        })
+        .take(3)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(vec!["Nelson", "Young", "Lambert"], names);
+
+    Ok(())
+}
+
+#[test]
+fn projecting_columns() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let table = employee(&db)?;
+    let expected = all_rows(&table)?;
+
+    let ptable = table.prepare()?;
+    let full_name = ptable
+        .columns
+        .iter()
+        .position(|c| c.name == "FULL_NAME")
+        .unwrap();
+    let emp_no = ptable
+        .columns
+        .iter()
+        .position(|c| c.name == "EMP_NO")
+        .unwrap();
+    let salary = ptable
+        .columns
+        .iter()
+        .position(|c| c.name == "SALARY")
+        .unwrap();
+
+    let ptable = ptable.project(&["SALARY", "FULL_NAME", "EMP_NO"])?;
+    let names: Vec<&str> = ptable.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(vec!["SALARY", "FULL_NAME", "EMP_NO"], names);
+
+    let rows = ptable.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(expected.len(), rows.len());
+    for (row, expected) in rows.iter().zip(expected.iter()) {
+        assert_eq!(
+            vec![
+                expected.values[salary].clone(),
+                expected.values[full_name].clone(),
+                expected.values[emp_no].clone(),
+            ],
+            row.values
+        );
+        assert_eq!(expected.db_key, row.db_key);
+    }
+
+    // Projecting again picks from the projected columns
+    let ptable = table
+        .prepare()?
+        .project(&["SALARY", "EMP_NO"])?
+        .project(&["EMP_NO"])?;
+    let row = ptable.get(expected[1].db_key.unwrap().record_number)?;
+    assert_eq!(
+        vec![expected[1].values[emp_no].clone()],
+        row.unwrap().values
+    );
+
+    assert!(table.prepare()?.project(&["NOT_A_COLUMN"]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn skipping_rows() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let table = employee(&db)?;
+    let expected: Vec<_> = all_rows(&table)?.into_iter().map(|r| r.values).collect();
+
+    let values = |ptable: TablePreparated| -> Result<Vec<_>, Error> {
+        ptable.map(|row| row.map(|r| r.values)).collect()
+    };
+
+    assert_eq!(expected[5..15], values(table.prepare()?.skip(5).limit(10))?);
+    assert_eq!(expected[40..], values(table.prepare()?.skip(40).limit(10))?);
+    assert_eq!(expected[..3], values(table.prepare()?.limit(5).limit(3))?);
+    assert!(values(table.prepare()?.skip(42))?.is_empty());
+    assert!(values(table.prepare()?.limit(0))?.is_empty());
+
+    // Already read rows count for neither
+    let mut ptable = table.prepare()?;
+    ptable.read()?;
+    assert_eq!(expected[3..5], values(ptable.skip(2).limit(2))?);
+
+    Ok(())
+}
+
+#[test]
+fn borrowing_rows() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let table = employee(&db)?;
+    let expected = all_rows(&table)?;
+
+    let mut ptable = table.prepare()?;
+    let first_name = ptable
+        .columns
+        .iter()
+        .position(|c| c.name == "FIRST_NAME")
+        .unwrap();
+    let emp_no = ptable
+        .columns
+        .iter()
+        .position(|c| c.name == "EMP_NO")
+        .unwrap();
+
+    let mut count = 0;
+    while let Some(row) = ptable.read_ref()? {
+        let expected = &expected[count];
+        count += 1;
+
+        assert_eq!(expected.values.len(), row.len());
+        assert_eq!(expected.db_key, row.db_key());
+        for (idx, value) in expected.values.iter().enumerate() {
+            assert_eq!(*value, row.get(idx)?);
+        }
+        assert_eq!(expected.values, row.to_row()?.values);
+        assert_eq!(expected.db_key, row.to_row()?.db_key);
+
+        // Stored as UTF-8, so the text is borrowed
+        match row.get_str(first_name)? {
+            Some(Cow::Borrowed(name)) => {
+                assert_eq!(
+                    expected.values[first_name],
+                    Some(Value::String(name.into()))
+                )
+            }
+            other => panic!("Expected a borrowed name, found {:?}", other),
+        }
+        assert!(row.get_str(emp_no).is_err());
+        assert!(row.get(row.len()).is_err());
+    }
+    assert_eq!(42, count);
+
+    Ok(())
+}
+
+#[test]
+fn borrowing_rows_with_blobs() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let tables = db.tables()?;
+    let table = tables.into_iter().find(|t| t.name == "PROJECT").unwrap();
+    let expected = all_rows(&table)?;
+
+    let mut ptable = table.prepare()?.project(&["PROJ_DESC", "PROJ_NAME"])?;
+    let row = ptable.read_ref()?.unwrap();
+    assert!(matches!(row.get(0)?, Some(Value::String(_))));
+    assert_eq!(8, row.raw(0).unwrap().len());
+
+    let projected = table.prepare()?.project(&["PROJ_DESC"])?.read()?.unwrap();
+    assert_eq!(projected.values[0], row.get(0)?);
+
+    // Only the id of the blob without the projection
+    let desc = table
+        .prepare()?
+        .columns
+        .iter()
+        .position(|c| c.name == "PROJ_DESC")
+        .unwrap();
+    assert!(matches!(expected[0].values[desc], Some(Value::Blob(_))));
+
+    Ok(())
+}
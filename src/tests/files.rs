@@ -0,0 +1,290 @@
+use std::path::{Path, PathBuf};
+
+use crate::*;
+
+const PAGE_SIZE: usize = 8192;
+
+/// Split the employee database on two files, with the pages after
+/// `last` on a secondary file named `secondary` on its header
+fn split_database(dir: &Path, secondary: &str, last: usize) -> Result<PathBuf, Error> {
+    std::fs::create_dir_all(dir)?;
+    let bytes = std::fs::read("dbs/employee.fdb")?;
+
+    let mut primary = bytes[..(last + 1) * PAGE_SIZE].to_vec();
+    let mut clumplets = vec![2, secondary.len() as u8];
+    clumplets.extend_from_slice(secondary.as_bytes());
+    clumplets.extend_from_slice(&[3, 4]);
+    clumplets.extend_from_slice(&(last as u32).to_le_bytes());
+    primary[128..128 + clumplets.len()].copy_from_slice(&clumplets);
+    primary[66..68].copy_from_slice(&(128 + clumplets.len() as u16).to_le_bytes());
+
+    // The secondary file starts with its own header page
+    let mut header = bytes[..PAGE_SIZE].to_vec();
+    header[40..42].copy_from_slice(&1u16.to_le_bytes());
+    let mut other = header;
+    other.extend_from_slice(&bytes[(last + 1) * PAGE_SIZE..]);
+
+    let path = dir.join("employee.fdb");
+    std::fs::write(&path, primary)?;
+    std::fs::write(dir.join("employee.fdb2"), other)?;
+
+    Ok(path)
+}
+
+fn employee_rows(db: &mut Database) -> Result<Vec<Vec<Option<Value>>>, Error> {
+    let tables = db.tables()?;
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE").unwrap();
+
+    let mut ptable = table.prepare()?;
+    let mut rows = vec![];
+    while let Some(row) = ptable.read()? {
+        rows.push(row.values);
+    }
+
+    Ok(rows)
+}
+
+#[test]
+fn reading_multi_file_database() -> Result<(), Error> {
+    let dir = std::env::temp_dir().join("rfirebird_multi_file");
+    let path = split_database(&dir, "/srv/firebird/employee.fdb2", 150)?;
+
+    let result = (|| {
+        // Found on the directory of the primary file
+        let mut db = Database::open_file(path.to_str().unwrap())?;
+        assert_eq!(
+            vec![dir.join("employee.fdb"), dir.join("employee.fdb2")],
+            db.files()
+        );
+        assert_eq!(
+            vec![
+                HeaderClumplet::File("/srv/firebird/employee.fdb2".to_string()),
+                HeaderClumplet::LastPage(150)
+            ],
+            db.clumplets
+        );
+        assert_eq!(326, db.page_count()?);
+        assert!(matches!(db.page(178)?, AnyPage::TransactionInventory(_)));
+
+        let mut single = Database::open_file("dbs/employee.fdb")?;
+        let rows = employee_rows(&mut db)?;
+        assert_eq!(42, rows.len());
+        assert_eq!(employee_rows(&mut single)?, rows);
+
+        Ok(())
+    })();
+
+    std::fs::remove_dir_all(&dir)?;
+
+    result
+}
+
+#[test]
+fn remapping_secondary_files() -> Result<(), Error> {
+    let dir = std::env::temp_dir().join("rfirebird_multi_file_remap");
+    let path = split_database(&dir, "D:\\DATA\\EMP.FD2", 40)?;
+
+    let result = (|| {
+        let path = path.to_str().unwrap();
+        assert!(Database::open_file(path).is_err());
+
+        let mut db = Database::open_file_with(path, |name| {
+            assert_eq!("D:\\DATA\\EMP.FD2", name);
+            dir.join("employee.fdb2")
+        })?;
+        assert_eq!(2, db.files().len());
+        assert_eq!(42, employee_rows(&mut db)?.len());
+
+        Ok(())
+    })();
+
+    std::fs::remove_dir_all(&dir)?;
+
+    result
+}
+
+/// Copy of the employee database locked by nbackup, with the generator
+/// page changed and a new page on the delta file
+fn stalled_database(dir: &Path, difference: Option<&str>) -> Result<PathBuf, Error> {
+    std::fs::create_dir_all(dir)?;
+    let mut bytes = std::fs::read("dbs/employee.fdb")?;
+
+    // Stalled nbackup state on the header flags
+    bytes[43] |= 0x04;
+    if let Some(name) = difference {
+        let mut clumplet = vec![6, name.len() as u8];
+        clumplet.extend_from_slice(name.as_bytes());
+        bytes[128..128 + clumplet.len()].copy_from_slice(&clumplet);
+    }
+
+    let mut generators = bytes[157 * PAGE_SIZE..158 * PAGE_SIZE].to_vec();
+    // EMP_NO_GEN value
+    generators[24 + 12 * 8..24 + 13 * 8].copy_from_slice(&200i64.to_le_bytes());
+
+    let mut alloc = vec![0u8; PAGE_SIZE];
+    for (i, value) in [2u32, 157, 326].iter().enumerate() {
+        alloc[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    let mut delta = vec![0u8; PAGE_SIZE];
+    delta.extend_from_slice(&alloc);
+    delta.extend_from_slice(&generators);
+    delta.extend_from_slice(&generators);
+
+    let path = dir.join("employee.fdb");
+    std::fs::write(&path, bytes)?;
+    std::fs::write(dir.join("employee.fdb.delta"), delta)?;
+
+    Ok(path)
+}
+
+fn emp_no_gen(db: &mut Database) -> Result<i64, Error> {
+    let generators = db.generators()?;
+
+    Ok(generators
+        .into_iter()
+        .find(|g| g.name == "EMP_NO_GEN")
+        .unwrap()
+        .value)
+}
+
+#[test]
+fn reading_delta_of_stalled_database() -> Result<(), Error> {
+    let dir = std::env::temp_dir().join("rfirebird_delta");
+
+    let result = (|| {
+        for difference in [None, Some("/backup/employee.fdb.delta")] {
+            let path = stalled_database(&dir, difference)?;
+            let path = path.to_str().unwrap();
+
+            let mut db = Database::open_file(path)?;
+            assert_eq!(BackupState::Stalled, db.header.backup_state());
+            assert_eq!(vec![157, 326], db.delta_pages());
+            assert_eq!(327, db.page_count()?);
+            assert!(matches!(db.page(326)?, AnyPage::Generator(_)));
+            assert_eq!(200, emp_no_gen(&mut db)?);
+            assert_eq!(42, employee_rows(&mut db)?.len());
+
+            std::fs::remove_file(dir.join("employee.fdb.delta"))?;
+            assert!(Database::open_file(path).is_err());
+        }
+
+        let mut db = Database::open_file("dbs/employee.fdb")?;
+        assert_eq!(BackupState::Normal, db.header.backup_state());
+        assert!(db.delta_pages().is_empty());
+        assert_eq!(145, emp_no_gen(&mut db)?);
+
+        Ok(())
+    })();
+
+    std::fs::remove_dir_all(&dir)?;
+
+    result
+}
+
+/// Incremental nbackup file with the given pages
+fn incremental_backup(level: u16, guid: u8, prev: u8, pages: &[(u32, &[u8])]) -> Vec<u8> {
+    let mut bytes = b"NBAK\0\0\0\0".to_vec();
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&level.to_le_bytes());
+    bytes.extend_from_slice(&[guid; 16]);
+    bytes.extend_from_slice(&[prev; 16]);
+    bytes.extend_from_slice(&(PAGE_SIZE as u32).to_le_bytes());
+    bytes.extend_from_slice(&(level as u32 * 10).to_le_bytes());
+    bytes.extend_from_slice(&((level as u32 - 1) * 10).to_le_bytes());
+
+    for (pageno, page) in pages {
+        bytes.extend_from_slice(&pageno.to_le_bytes());
+        bytes.extend_from_slice(page);
+    }
+
+    bytes
+}
+
+/// Chain of nbackup files of the employee database, each level
+/// changing the value of EMP_NO_GEN
+fn backup_chain(dir: &Path) -> Result<Vec<String>, Error> {
+    std::fs::create_dir_all(dir)?;
+    let bytes = std::fs::read("dbs/employee.fdb")?;
+
+    let with_guid = |guid: u8| {
+        let mut header = bytes[..PAGE_SIZE].to_vec();
+        header[128..130].copy_from_slice(&[7, 16]);
+        header[130..146].copy_from_slice(&[guid; 16]);
+        header
+    };
+    let with_emp_no = |value: i64| {
+        let mut page = bytes[157 * PAGE_SIZE..158 * PAGE_SIZE].to_vec();
+        page[24 + 12 * 8..24 + 13 * 8].copy_from_slice(&value.to_le_bytes());
+        page
+    };
+
+    let mut level0 = bytes.clone();
+    level0[..PAGE_SIZE].copy_from_slice(&with_guid(0xa0));
+
+    let level1 = incremental_backup(
+        1,
+        0xa1,
+        0xa0,
+        &[(0, &with_guid(0xa1)), (157, &with_emp_no(150))],
+    );
+    let level2 = incremental_backup(
+        2,
+        0xa2,
+        0xa1,
+        &[
+            (0, &with_guid(0xa2)),
+            (157, &with_emp_no(160)),
+            (326, &with_emp_no(0)),
+        ],
+    );
+    // Made from other level 1 backup
+    let other = incremental_backup(2, 0xb2, 0xb1, &[(157, &with_emp_no(170))]);
+
+    let mut paths = vec![];
+    for (name, content) in [
+        ("employee.nbk0", level0),
+        ("employee.nbk1", level1),
+        ("employee.nbk2", level2),
+        ("other.nbk2", other),
+    ] {
+        let path = dir.join(name);
+        std::fs::write(&path, content)?;
+        paths.push(path.to_str().unwrap().to_string());
+    }
+
+    Ok(paths)
+}
+
+#[test]
+fn reading_nbackup_chain() -> Result<(), Error> {
+    let dir = std::env::temp_dir().join("rfirebird_nbackup_chain");
+    let paths = backup_chain(&dir)?;
+    let [level0, level1, level2, other] = [&paths[0], &paths[1], &paths[2], &paths[3]];
+
+    let result = (|| {
+        let mut db = Database::open_backup_chain(&[level0])?;
+        assert_eq!(145, emp_no_gen(&mut db)?);
+        assert_eq!(326, db.page_count()?);
+
+        let mut db = Database::open_backup_chain(&[level0, level1])?;
+        assert_eq!(150, emp_no_gen(&mut db)?);
+
+        // In any order
+        let mut db = Database::open_backup_chain(&[level2, level0, level1])?;
+        assert_eq!(160, emp_no_gen(&mut db)?);
+        assert_eq!(327, db.page_count()?);
+        assert_eq!(vec![HeaderClumplet::BackupGuid([0xa2; 16])], db.clumplets);
+        assert_eq!(42, employee_rows(&mut db)?.len());
+
+        assert!(Database::open_backup_chain(&[level0, level2]).is_err());
+        assert!(Database::open_backup_chain(&[level0, level1, other]).is_err());
+        assert!(Database::open_backup_chain(&[level1, level2]).is_err());
+
+        Ok(())
+    })();
+
+    std::fs::remove_dir_all(&dir)?;
+
+    result
+}
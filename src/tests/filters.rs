@@ -0,0 +1,197 @@
+use crate::*;
+
+fn employee(db: &Database) -> Result<Table, Error> {
+    let tables = db.tables()?;
+    Ok(tables.into_iter().find(|t| t.name == "EMPLOYEE").unwrap())
+}
+
+/// Rows of the table matching the filter, checked by `check`
+/// against the full rows
+fn check_filter<F>(table: &Table, filter: Filter, check: F) -> Result<usize, Error>
+where
+    F: Fn(&dyn Fn(&str) -> Option<Value>) -> bool,
+{
+    let mut ptable = table.prepare()?;
+    let columns = ptable.columns.clone();
+    let mut expected = vec![];
+    while let Some(row) = ptable.read_ref()? {
+        let value = |name: &str| {
+            let pos = columns.iter().position(|c| c.name == name).unwrap();
+            row.get(pos).unwrap()
+        };
+        if check(&value) {
+            expected.push(row.to_row()?.values);
+        }
+    }
+
+    let rows = table
+        .prepare()?
+        .filter_by(filter)?
+        .map(|row| row.map(|r| r.values))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(expected, rows);
+
+    Ok(rows.len())
+}
+
+fn text(value: Option<Value>) -> String {
+    match value {
+        Some(Value::String(st)) => st.trim_end().to_string(),
+        _ => String::new(),
+    }
+}
+
+fn number(value: Option<Value>) -> f64 {
+    match value {
+        Some(Value::Decimal(v, scale)) => v as f64 * 10f64.powi(scale as i32),
+        Some(Value::SmallInt(v)) => v as f64,
+        Some(Value::Int(v)) => v as f64,
+        _ => f64::NAN,
+    }
+}
+
+#[test]
+fn filtering_rows() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let table = employee(&db)?;
+
+    let count = check_filter(&table, Filter::column("job_country").eq("USA"), |v| {
+        text(v("JOB_COUNTRY")) == "USA"
+    })?;
+    assert_eq!(33, count);
+
+    let count = check_filter(
+        &table,
+        Filter::column("SALARY")
+            .gt(100000)
+            .and(Filter::column("DEPT_NO").ne("000")),
+        |v| number(v("SALARY")) > 100000.0 && text(v("DEPT_NO")) != "000",
+    )?;
+    assert_eq!(9, count);
+
+    let count = check_filter(
+        &table,
+        Filter::column("EMP_NO").is_in([2i16, 4, 5, 999]),
+        |v| [2.0, 4.0, 5.0].contains(&number(v("EMP_NO"))),
+    )?;
+    assert_eq!(3, count);
+
+    let count = check_filter(
+        &table,
+        Filter::column("LAST_NAME")
+            .like("%son")
+            .or(Filter::column("FIRST_NAME").starting_with("Ro")),
+        |v| text(v("LAST_NAME")).ends_with("son") || text(v("FIRST_NAME")).starts_with("Ro"),
+    )?;
+    assert!(count > 0);
+
+    let count = check_filter(&table, Filter::column("PHONE_EXT").is_null(), |v| {
+        v("PHONE_EXT").is_none()
+    })?;
+    assert!(count > 0);
+
+    let count = check_filter(
+        &table,
+        !Filter::column("HIRE_DATE").lt("1991-01-01"),
+        |v| matches!(v("HIRE_DATE"), Some(Value::Timestamp(date, _)) if date >= 48257),
+    )?;
+    assert!(count > 0 && count < 42);
+
+    // Unknown for every row
+    let count = check_filter(
+        &table,
+        Filter::column("FULL_NAME").eq("Nelson, Robert"),
+        |_| false,
+    )?;
+    assert_eq!(0, count);
+
+    Ok(())
+}
+
+#[test]
+fn filtering_with_cursor_options() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let table = employee(&db)?;
+    let filter: Filter = "job_country = 'USA' and salary < 100000".parse()?;
+
+    let all = table
+        .prepare()?
+        .project(&["EMP_NO"])?
+        .filter_by(filter.clone())?
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(all.len() > 10);
+    assert!(all.iter().all(|r| r.values.len() == 1));
+
+    // Skip and limit count only the matching rows
+    let rows = table
+        .prepare()?
+        .skip(2)
+        .limit(5)
+        .project(&["EMP_NO"])?
+        .filter_by(filter.clone())?
+        .collect::<Result<Vec<_>, _>>()?;
+    let values = |rows: &[Row]| rows.iter().map(|r| r.values.clone()).collect::<Vec<_>>();
+    assert_eq!(values(&all[2..7]), values(&rows));
+
+    // Filters given later are joined
+    let rows = table
+        .prepare()?
+        .project(&["EMP_NO"])?
+        .filter_by(filter.clone())?
+        .filter_by(Filter::column("emp_no").le(20))?
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        values(&all)
+            .into_iter()
+            .filter(|v| matches!(v[0], Some(Value::SmallInt(n)) if n <= 20))
+            .collect::<Vec<_>>(),
+        values(&rows)
+    );
+
+    let mut ptable = table
+        .prepare()?
+        .project(&["JOB_COUNTRY"])?
+        .filter_by(filter.clone())?;
+    let mut count = 0;
+    while let Some(row) = ptable.read_ref()? {
+        assert_eq!(Some("USA"), row.get_str(0)?.as_deref().map(str::trim_end));
+        count += 1;
+    }
+    assert_eq!(all.len(), count);
+
+    let parts = table.prepare()?.filter_by(filter)?.split(3);
+    let mut count = 0;
+    for part in parts {
+        count += part.count();
+    }
+    assert_eq!(all.len(), count);
+
+    assert!(table
+        .prepare()?
+        .filter_by(Filter::column("NOT_A_COLUMN").is_null())
+        .is_err());
+    assert!(table
+        .prepare()?
+        .filter_by(Filter::column("EMP_NO").eq("one"))?
+        .next()
+        .unwrap()
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn filtering_blobs() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let tables = db.tables()?;
+    let table = tables.into_iter().find(|t| t.name == "PROJECT").unwrap();
+
+    let count = check_filter(
+        &table,
+        Filter::column("PROJ_DESC").like("%software%"),
+        |v| text(v("PROJ_DESC")).contains("software"),
+    )?;
+    assert!(count > 0 && count < 6);
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use crate::*;
+
+#[test]
+fn reading_generators() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let generators = db.generators()?;
+
+    assert_eq!(13, generators.len());
+
+    let emp_no = generators.iter().find(|g| g.name == "EMP_NO_GEN").unwrap();
+    assert_eq!(12, emp_no.id);
+    assert!(!emp_no.is_system_generator);
+    assert_eq!(145, emp_no.value);
+    assert_eq!(0, emp_no.initial_value);
+    assert_eq!(1, emp_no.increment);
+
+    let cust_no = generators.iter().find(|g| g.name == "CUST_NO_GEN").unwrap();
+    assert_eq!(1015, cust_no.value);
+
+    let security = generators
+        .iter()
+        .find(|g| g.name == "RDB$SECURITY_CLASS")
+        .unwrap();
+    assert!(security.is_system_generator);
+    assert_eq!(520, security.value);
+
+    Ok(())
+}
+
+#[test]
+fn generator_page() -> Result<(), Error> {
+    let bytes = std::fs::read("dbs/employee.fdb")?;
+
+    let gpg = GeneratorPage::from_bytes(bytes[157 * 8192..158 * 8192].to_vec())?;
+
+    assert_eq!(0, gpg.sequence);
+    assert_eq!(GeneratorPage::values_per_page(8192), gpg.values.len());
+    assert_eq!(&[13, 520, 61], &gpg.values[..3]);
+    assert_eq!(&[145, 1015], &gpg.values[12..14]);
+
+    Ok(())
+}
@@ -0,0 +1,442 @@
+use crate::*;
+
+#[test]
+fn reading_index_root() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let irt = table.index_root()?;
+    assert!(irt.is_some());
+    let irt = irt.unwrap();
+
+    assert_eq!(131, irt.relation);
+    assert_eq!(4, irt.count);
+    assert_eq!(214, irt.indexes[0].root);
+    assert!(irt.indexes[0].is_primary());
+    assert!(irt.indexes[0].is_unique());
+    assert!(irt.indexes[1].is_foreign());
+    assert_eq!(3, irt.indexes[2].keys.len());
+    assert_eq!(6, irt.indexes[2].keys[0].field);
+
+    Ok(())
+}
+
+#[test]
+fn indexes_of_table_employee() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let indexes = table.indexes()?;
+    assert_eq!(4, indexes.len());
+
+    let primary = &indexes[0];
+    assert_eq!("RDB$PRIMARY7", primary.name);
+    assert_eq!(1, primary.id);
+    assert!(primary.unique);
+    assert_eq!(vec!["EMP_NO".to_string()], primary.segments);
+    assert_eq!(214, primary.root);
+
+    let foreign = &indexes[2];
+    assert_eq!("RDB$FOREIGN9", foreign.name);
+    assert_eq!(Some("RDB$PRIMARY2".to_string()), foreign.foreign_key);
+    assert_eq!(
+        vec![
+            "JOB_CODE".to_string(),
+            "JOB_GRADE".to_string(),
+            "JOB_COUNTRY".to_string()
+        ],
+        foreign.segments
+    );
+
+    let namex = &indexes[3];
+    assert_eq!("NAMEX", namex.name);
+    assert!(!namex.unique);
+    assert_eq!(2, namex.keys.len());
+
+    Ok(())
+}
+
+#[test]
+fn reading_index_entries() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let indexes = table.indexes()?;
+    let entries = indexes[0]
+        .entries()?
+        .collect::<Result<Vec<IndexEntry>, Error>>()?;
+    assert_eq!(42, entries.len());
+    assert_eq!(vec![0xc0, 0x10], entries[1].key);
+    assert!(entries.windows(2).all(|w| w[0].key < w[1].key));
+
+    Ok(())
+}
+
+#[test]
+fn reading_multi_level_index_entries() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "RDB$RELATION_FIELDS");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let indexes = table.indexes()?;
+    let index = indexes.iter().find(|i| i.root == 109);
+    assert!(index.is_some());
+    let index = index.unwrap();
+
+    let root = index.page(index.root)?;
+    assert_eq!(1, root.level);
+    assert_eq!(3, root.nodes()?.len());
+
+    let leaf = index.page(107)?;
+    assert!(leaf.is_leaf());
+    assert_eq!(9, leaf.jumps.len());
+    assert_eq!(180, leaf.sibling);
+
+    let entries = index
+        .entries()?
+        .collect::<Result<Vec<IndexEntry>, Error>>()?;
+    assert!(entries.windows(2).all(|w| w[0].key <= w[1].key));
+
+    let mut ptable = table.prepare()?;
+    let mut count = 0;
+    while ptable.read()?.is_some() {
+        count += 1;
+    }
+    assert_eq!(count, entries.len());
+
+    Ok(())
+}
+
+type Segments = Vec<Vec<Option<Value>>>;
+
+/// Decoded keys of all entries and the values of the segments on
+/// the rows, both sorted
+fn keys_and_rows(table: &str, index: &str) -> Result<(Segments, Segments), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == table);
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let indexes = table.indexes()?;
+    let index = indexes.into_iter().find(|i| i.name == index);
+    assert!(index.is_some());
+    let index = index.unwrap();
+
+    let mut ptable = table.prepare()?;
+
+    let mut keys = vec![];
+    for entry in index.entries()? {
+        keys.push(index.decode_key(&entry?.key, &ptable.columns)?);
+    }
+
+    let positions: Vec<usize> = index
+        .segments
+        .iter()
+        .map(|name| ptable.columns.iter().position(|c| &c.name == name))
+        .collect::<Option<_>>()
+        .unwrap();
+
+    let mut rows = vec![];
+    while let Some(mut row) = ptable.read()? {
+        rows.push(
+            positions
+                .iter()
+                .map(|p| row.values[*p].take())
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    keys.sort_by_key(|k| format!("{:?}", k));
+    rows.sort_by_key(|k| format!("{:?}", k));
+
+    Ok((keys, rows))
+}
+
+#[test]
+fn decoding_numeric_index_keys() -> Result<(), Error> {
+    let (keys, rows) = keys_and_rows("EMPLOYEE", "RDB$PRIMARY7")?;
+
+    assert_eq!(42, keys.len());
+    assert!(keys.contains(&vec![Some(Value::SmallInt(2))]));
+    assert_eq!(rows, keys);
+
+    // descending BIGINT with scale
+    let (keys, rows) = keys_and_rows("DEPARTMENT", "BUDGETX")?;
+    assert!(keys.contains(&vec![Some(Value::Decimal(200000000, -2))]));
+    assert_eq!(rows, keys);
+
+    Ok(())
+}
+
+#[test]
+fn decoding_compound_index_keys() -> Result<(), Error> {
+    let (keys, rows) = keys_and_rows("EMPLOYEE", "RDB$FOREIGN9")?;
+
+    assert_eq!(
+        vec![
+            Some(Value::String("Admin".to_string())),
+            Some(Value::SmallInt(4)),
+            Some(Value::String("USA".to_string()))
+        ],
+        keys[0]
+    );
+    assert_eq!(rows, keys);
+
+    let (keys, rows) = keys_and_rows("EMPLOYEE", "NAMEX")?;
+    assert_eq!(rows, keys);
+
+    let (keys, rows) = keys_and_rows("JOB", "MINSALX")?;
+    assert_eq!(rows, keys);
+
+    let (keys, rows) = keys_and_rows("JOB", "MAXSALX")?;
+    assert_eq!(rows, keys);
+
+    let (keys, rows) = keys_and_rows("SALES", "QTYX")?;
+    assert_eq!(rows, keys);
+
+    Ok(())
+}
+
+#[test]
+fn decoding_date_index_keys() -> Result<(), Error> {
+    let (keys, rows) = keys_and_rows("SALARY_HISTORY", "RDB$PRIMARY20")?;
+
+    assert_eq!(rows, keys);
+
+    let (keys, rows) = keys_and_rows("SALARY_HISTORY", "CHANGEX")?;
+    assert_eq!(rows, keys);
+
+    // nullable TIMESTAMP
+    let (keys, rows) = keys_and_rows("SALES", "NEEDX")?;
+    assert!(keys.contains(&vec![None]));
+    assert_eq!(rows, keys);
+
+    Ok(())
+}
+
+#[test]
+fn lookup_by_index() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let rows = table.lookup("RDB$PRIMARY7", &[Value::SmallInt(4)])?;
+    assert_eq!(1, rows.len());
+    assert_eq!(Some(Value::String("Bruce".to_string())), rows[0].values[1]);
+
+    assert!(table.lookup("RDB$PRIMARY7", &[Value::Int(3)])?.is_empty());
+
+    // first segment of a compound key
+    let rows = table.lookup("RDB$FOREIGN9", &[Value::String("Eng".to_string())])?;
+    assert_eq!(15, rows.len());
+    assert!(rows
+        .iter()
+        .all(|r| r.values[6] == Some(Value::String("Eng".to_string()))));
+
+    let rows = table.lookup(
+        "NAMEX",
+        &[
+            Value::String("Young".to_string()),
+            Value::String("Katherine".to_string()),
+        ],
+    )?;
+    assert_eq!(1, rows.len());
+
+    assert!(table.lookup("UNKNOWN", &[]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn range_by_index() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let rows = table.range(
+        "RDB$PRIMARY7",
+        &[Value::SmallInt(10)],
+        &[Value::SmallInt(30)],
+    )?;
+    let numbers: Vec<_> = rows.iter().map(|r| r.values[0].clone()).collect();
+    assert_eq!(
+        vec![11, 12, 14, 15, 20, 24, 28, 29]
+            .into_iter()
+            .map(|n| Some(Value::SmallInt(n)))
+            .collect::<Vec<_>>(),
+        numbers
+    );
+
+    let rows = table.range("RDB$PRIMARY7", &[Value::SmallInt(140)], &[])?;
+    assert_eq!(3, rows.len());
+
+    // descending index, in the reverse order
+    let tables = db.tables()?;
+    let table = tables.into_iter().find(|t| t.name == "DEPARTMENT").unwrap();
+    let rows = table.range(
+        "BUDGETX",
+        &[Value::Decimal(50000000, -2)],
+        &[Value::Int(1000000)],
+    )?;
+    let budgets: Vec<_> = rows.iter().map(|r| r.values[4].clone()).collect();
+    assert_eq!(
+        vec![1000000, 850000, 700000, 650000, 600000, 500000, 500000, 500000, 500000]
+            .into_iter()
+            .map(|n| Some(Value::Decimal(n * 100, -2)))
+            .collect::<Vec<_>>(),
+        budgets
+    );
+
+    // Keys not fitting on the scale of the column
+    assert!(table.lookup("BUDGETX", &[Value::BigInt(i64::MAX)]).is_err());
+    assert!(table
+        .lookup("BUDGETX", &[Value::Decimal(1, -30)])?
+        .is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn lookup_by_multi_level_index() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "RDB$RELATION_FIELDS");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let indexes = table.indexes()?;
+    let index = indexes.iter().find(|i| i.root == 109);
+    assert!(index.is_some());
+    let index = index.unwrap();
+
+    let ptable = table.prepare()?;
+    let mut keys = vec![];
+    for entry in index.entries()? {
+        keys.push(index.decode_key(&entry?.key, &ptable.columns)?);
+    }
+
+    for key in keys.iter().step_by(37) {
+        let values: Vec<Value> = key.iter().flatten().cloned().collect();
+
+        let rows = table.lookup(&index.name, &values)?;
+        assert_eq!(keys.iter().filter(|k| *k == key).count(), rows.len());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn checking_indexes() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    for table in db.tables()? {
+        for check in table.check_indexes()? {
+            assert_eq!(None, check.skipped, "{}", check.index);
+            assert_eq!(Vec::<IndexIssue>::new(), check.issues, "{}", check.index);
+        }
+    }
+
+    let tables = db.tables()?;
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE").unwrap();
+    let checks = table.check_indexes()?;
+    assert_eq!(4, checks.len());
+    assert_eq!("RDB$PRIMARY7", checks[0].index);
+    assert_eq!(42, checks[0].entries);
+    assert_eq!(42, checks[0].rows);
+
+    Ok(())
+}
+
+/// Check the primary key of EMPLOYEE on a copy of the database
+/// with some bytes of the first leaf page changed
+fn check_corrupted(name: &str, changes: &[(usize, u8)]) -> Result<Vec<IndexIssue>, Error> {
+    let mut bytes = std::fs::read("dbs/employee.fdb")?;
+    for (offset, byte) in changes {
+        bytes[214 * 8192 + offset] = *byte;
+    }
+
+    let path = std::env::temp_dir().join(format!("rfirebird_{}.fdb", name));
+    std::fs::write(&path, bytes)?;
+
+    let result = (|| {
+        let db = Database::open_file(path.to_str().unwrap())?;
+        let tables = db.tables()?;
+        let table = tables.into_iter().find(|t| t.name == "EMPLOYEE").unwrap();
+
+        let checks = table.check_indexes()?;
+        let check = checks
+            .into_iter()
+            .find(|c| c.index == "RDB$PRIMARY7")
+            .unwrap();
+
+        Ok(check.issues)
+    })();
+
+    std::fs::remove_file(&path)?;
+
+    result
+}
+
+#[test]
+fn checking_corrupted_indexes() -> Result<(), Error> {
+    // key of the second entry, the EMP_NO 4, changed to 3
+    let issues = check_corrupted("key_mismatch", &[(46, 0x08)])?;
+    assert_eq!(
+        vec![IndexIssue::KeyMismatch {
+            db_key: "8300000002000000".parse()?,
+            entry: vec![Some(Value::SmallInt(3))],
+            row: vec![Some(Value::SmallInt(4))],
+        }],
+        issues
+    );
+
+    // record number of the third entry changed from 2 to 1
+    let issues = check_corrupted("missing_entry", &[(47, 0xa1)])?;
+    assert_eq!(2, issues.len());
+    assert!(matches!(issues[0], IndexIssue::KeyMismatch { .. }));
+    assert_eq!(
+        IndexIssue::MissingEntry {
+            db_key: "8300000003000000".parse()?,
+            key: vec![Some(Value::SmallInt(5))],
+        },
+        issues[1]
+    );
+    assert_eq!(
+        "row 8300000003000000 without entry for key (5)",
+        issues[1].to_string()
+    );
+
+    Ok(())
+}
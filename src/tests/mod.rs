@@ -1,3 +1,17 @@
+mod arrays;
+mod backups;
+mod blobs;
+mod blr;
+mod cursors;
+mod files;
+mod filters;
+mod generators;
+mod indexes;
 mod pages;
+mod parallel;
+#[cfg(feature = "sql")]
+mod queries;
 mod records;
+mod shadows;
+mod sources;
 mod tables;
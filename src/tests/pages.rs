@@ -9,3 +9,161 @@ fn header_page() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn page_inventory() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let pages = db.page_allocation()?;
+
+    assert_eq!(326, pages.len());
+    assert_eq!(28, pages.iter().filter(|p| p.is_free).count());
+    assert!(!pages[297].is_free);
+    assert!(pages[298].is_free);
+    assert!(pages[287].is_extent_full);
+    assert!(!pages[296].is_extent_full);
+
+    Ok(())
+}
+
+#[test]
+fn space_usage() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let usage = db.space_usage()?;
+
+    assert_eq!(326, usage.pages);
+    assert_eq!(28, usage.free_pages);
+    assert_eq!(106, usage.data_pages);
+    assert_eq!(500340, usage.data_free_space);
+    assert_eq!([39, 16, 6, 27, 18], usage.fill_distribution);
+    assert_eq!(28 * 8192 + 500340, usage.reclaimable());
+
+    Ok(())
+}
+
+#[test]
+fn page_generation_and_scn() -> Result<(), Error> {
+    let bytes = std::fs::read("dbs/employee.fdb")?;
+
+    let pag = Page::from_bytes(&bytes[157 * 8192..])?;
+    assert_eq!(0x09, pag.ptype);
+    assert_eq!(59, pag.generation);
+    assert_eq!(0, pag.scn);
+    assert_eq!(157, pag.pageno);
+
+    assert!(Page::from_bytes(&bytes[..8]).is_err());
+
+    // Slices at any offset are read, not only the aligned ones
+    let mut shifted = vec![0u8];
+    shifted.extend_from_slice(&bytes[157 * 8192..157 * 8192 + 16]);
+    let pag = Page::from_bytes(&shifted[1..])?;
+    assert_eq!(59, pag.generation);
+    assert_eq!(157, pag.pageno);
+
+    Ok(())
+}
+
+#[test]
+fn scn_pages() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    assert_eq!(1024, ScnPage::pages_per_scn(8192));
+    assert_eq!(2, ScnPage::scn_page(8192, 0));
+    assert_eq!(2048, ScnPage::scn_page(8192, 2));
+
+    // Never backed up with nbackup
+    let scns = db.page_scns()?;
+    assert_eq!(326, scns.len());
+    assert!(scns.iter().all(|scn| *scn == 0));
+    assert!(db.changed_pages(0)?.is_empty());
+
+    let mut bytes = std::fs::read("dbs/employee.fdb")?[2 * 8192..3 * 8192].to_vec();
+    bytes[20 + 4 * 5] = 3;
+    let scn = ScnPage::from_bytes(bytes)?;
+    assert_eq!(0, scn.sequence);
+    assert_eq!(1024, scn.scns.len());
+    assert_eq!(3, scn.scns[5]);
+
+    Ok(())
+}
+
+#[test]
+fn walking_all_pages() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let mut counts = std::collections::HashMap::new();
+    for pageno in 0..db.page_count()? as u32 {
+        let page = db.page(pageno)?;
+
+        assert_eq!(page.page_type() as u8, page.pag().ptype);
+        *counts.entry(page.page_type()).or_insert(0) += 1;
+    }
+
+    assert_eq!(Some(&1), counts.get(&PageType::Header));
+    assert_eq!(Some(&1), counts.get(&PageType::PageInventory));
+    assert_eq!(Some(&1), counts.get(&PageType::TransactionInventory));
+    assert_eq!(Some(&46), counts.get(&PageType::Pointer));
+    assert_eq!(Some(&106), counts.get(&PageType::Data));
+    assert_eq!(Some(&46), counts.get(&PageType::IndexRoot));
+    assert_eq!(Some(&95), counts.get(&PageType::Btree));
+    assert_eq!(Some(&1), counts.get(&PageType::Generator));
+    assert_eq!(Some(&1), counts.get(&PageType::ScnInventory));
+    assert_eq!(Some(&28), counts.get(&PageType::Undefined));
+
+    Ok(())
+}
+
+#[test]
+fn pointer_and_tip_pages() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    match db.page(14)? {
+        AnyPage::Pointer(ppg) => {
+            assert_eq!(5, ppg.relation);
+            assert_eq!(0, ppg.sequence);
+            assert!(ppg.is_eof());
+            assert_eq!(16, ppg.pages.len());
+            assert_eq!(&[76, 78, 79], &ppg.pages[..3]);
+            assert!(ppg.is_full(0));
+            assert!(ppg.is_secondary(7));
+            assert!(ppg.is_empty(9));
+        }
+        page => panic!("Expected a pointer page, found {:?}", page.page_type()),
+    }
+
+    match db.page(178)? {
+        AnyPage::TransactionInventory(tip) => {
+            assert_eq!(0, tip.next);
+            assert_eq!(TransactionState::Committed, tip.state(1));
+        }
+        page => panic!("Expected a TIP, found {:?}", page.page_type()),
+    }
+
+    assert!(matches!(db.page(157)?, AnyPage::Generator(_)));
+
+    Ok(())
+}
+
+/// Header of a page of the type, shorter than the page headers
+fn truncated_page(ptype: u8) -> Vec<u8> {
+    let mut bytes = vec![0u8; 18];
+    bytes[0] = ptype;
+    bytes
+}
+
+#[test]
+fn rejecting_truncated_pages() -> Result<(), Error> {
+    assert!(GeneratorPage::from_bytes(vec![]).is_err());
+    assert!(GeneratorPage::from_bytes(truncated_page(0x09)).is_err());
+    assert!(PageInventoryPage::from_bytes(vec![]).is_err());
+    assert!(PageInventoryPage::from_bytes(truncated_page(0x02)).is_err());
+    assert!(ScnPage::from_bytes(vec![]).is_err());
+    assert!(ScnPage::from_bytes(truncated_page(0x0a)).is_err());
+    assert!(PointerPage::from_bytes(vec![]).is_err());
+    assert!(PointerPage::from_bytes(truncated_page(0x04)).is_err());
+    assert!(TransactionInventoryPage::from_bytes(vec![]).is_err());
+    assert!(TransactionInventoryPage::from_bytes(truncated_page(0x03)).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::sync::{Arc, Mutex};
+
+use crate::*;
+
+/// Rows of the table read by a single cursor
+fn table_rows(table: &Table) -> Result<Vec<Row>, Error> {
+    let mut ptable = table.prepare()?;
+    let mut rows = vec![];
+    while let Some(row) = ptable.read()? {
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+fn sorted(mut rows: Vec<Row>) -> Vec<(Option<DbKey>, Vec<Option<Value>>)> {
+    rows.sort_by_key(|r| r.db_key.map(|k| k.record_number));
+    rows.into_iter().map(|r| (r.db_key, r.values)).collect()
+}
+
+#[test]
+fn sharing_between_threads() -> Result<(), Error> {
+    fn send_sync<T: Send + Sync>() {}
+    send_sync::<Database>();
+    send_sync::<Table>();
+    send_sync::<Index>();
+    send_sync::<Row>();
+
+    let db = Arc::new(Database::open_file("dbs/employee.fdb")?);
+
+    let handles: Vec<_> = ["EMPLOYEE", "PROJECT", "SALES"]
+        .into_iter()
+        .map(|name| {
+            let db = db.clone();
+            std::thread::spawn(move || -> Result<usize, Error> {
+                let tables = db.tables()?;
+                let table = tables.into_iter().find(|t| t.name == name).unwrap();
+                Ok(table_rows(&table)?.len())
+            })
+        })
+        .collect();
+
+    let counts: Vec<usize> = handles
+        .into_iter()
+        .map(|h| h.join().unwrap())
+        .collect::<Result<_, _>>()?;
+    assert_eq!(vec![42, 6, 33], counts);
+
+    Ok(())
+}
+
+#[test]
+fn reading_table_on_threads() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    db.set_cache_size(16);
+
+    // PROJECT has blobs, read through the shared cache
+    for name in ["EMPLOYEE", "PROJECT", "RDB$FIELDS"] {
+        let tables = db.tables()?;
+        let table = tables.into_iter().find(|t| t.name == name).unwrap();
+        let expected = sorted(table_rows(&table)?);
+
+        for threads in [1, 3, 0] {
+            let rows = Mutex::new(vec![]);
+            table.read_parallel(threads, |row| {
+                rows.lock().unwrap().push(row);
+                Ok(())
+            })?;
+
+            assert_eq!(expected, sorted(rows.into_inner().unwrap()));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn splitting_cursor() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let tables = db.tables()?;
+    let table = tables.into_iter().find(|t| t.name == "RDB$FIELDS").unwrap();
+    let expected = sorted(table_rows(&table)?);
+
+    // The rows already read are left out
+    let mut ptable = table.prepare()?;
+    let mut rows = vec![ptable.read()?.unwrap(), ptable.read()?.unwrap()];
+
+    let parts = ptable.split(4);
+    assert!(parts.len() > 1 && parts.len() <= 4);
+    for mut part in parts {
+        while let Some(row) = part.read()? {
+            rows.push(row);
+        }
+    }
+
+    assert_eq!(expected, sorted(rows));
+
+    Ok(())
+}
+
+#[test]
+fn stopping_on_error() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let tables = db.tables()?;
+    let table = tables.into_iter().find(|t| t.name == "RDB$FIELDS").unwrap();
+
+    let result = table.read_parallel(4, |row| match row.values.first() {
+        Some(Some(Value::String(name))) if name.trim() == "DEPTNO" => Err(Error::from("stop")),
+        _ => Ok(()),
+    });
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn reading_with_rayon() -> Result<(), Error> {
+    use rayon::prelude::*;
+
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let tables = db.tables()?;
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE").unwrap();
+    let expected = sorted(table_rows(&table)?);
+
+    let rows: Vec<Row> = table.prepare()?.par_rows().collect::<Result<_, _>>()?;
+    assert_eq!(expected, sorted(rows));
+
+    Ok(())
+}
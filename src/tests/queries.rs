@@ -0,0 +1,245 @@
+use crate::*;
+
+fn employee_rows(db: &Database) -> Result<(Vec<Column>, Vec<Row>), Error> {
+    let tables = db.tables()?;
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE").unwrap();
+    let ptable = table.prepare()?;
+    let columns = ptable.columns.clone();
+    let rows = ptable.collect::<Result<_, _>>()?;
+
+    Ok((columns, rows))
+}
+
+fn position(columns: &[Column], name: &str) -> usize {
+    columns.iter().position(|c| c.name == name).unwrap()
+}
+
+#[test]
+fn selecting_rows() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let (columns, rows) = employee_rows(&db)?;
+    let (emp_no, last_name, country) = (
+        position(&columns, "EMP_NO"),
+        position(&columns, "LAST_NAME"),
+        position(&columns, "JOB_COUNTRY"),
+    );
+
+    let result = db.query(
+        "SELECT last_name AS name, e.emp_no FROM employee e \
+         WHERE job_country <> 'USA' ORDER BY last_name DESC",
+    )?;
+    assert_eq!(vec!["NAME", "EMP_NO"], result.columns);
+    assert_eq!("PLAN (E NATURAL)", result.plan);
+
+    let mut expected: Vec<_> = rows
+        .iter()
+        .filter(|r| r.values[country] != Some(Value::String("USA".into())))
+        .map(|r| vec![r.values[last_name].clone(), r.values[emp_no].clone()])
+        .collect();
+    expected.sort_by_key(|v| std::cmp::Reverse(v[0].as_ref().map(|v| v.to_string())));
+    assert_eq!(9, expected.len());
+    assert_eq!(expected, result.rows);
+
+    let result = db.query("select * from employee")?;
+    assert_eq!(
+        columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>(),
+        result.columns
+    );
+    assert_eq!(
+        rows.iter().map(|r| r.values.clone()).collect::<Vec<_>>(),
+        result.rows
+    );
+
+    let result = db.query("select emp_no from employee offset 5 rows fetch first 3 rows only")?;
+    assert_eq!(
+        rows[5..8]
+            .iter()
+            .map(|r| vec![r.values[emp_no].clone()])
+            .collect::<Vec<_>>(),
+        result.rows
+    );
+
+    let result = db.query(
+        "select first_name from employee \
+         where hire_date between date '1990-01-01' and '1990-12-31' and phone_ext is not null \
+         order by 1 limit 2",
+    )?;
+    assert_eq!(
+        vec![
+            vec![Some(Value::String("Chris".into()))],
+            vec![Some(Value::String("K. J.".into()))]
+        ],
+        result.rows
+    );
+
+    Ok(())
+}
+
+#[test]
+fn looking_up_indexes() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let indexed = db.query("select emp_no, last_name from employee where emp_no = 24")?;
+    assert_eq!("PLAN (EMPLOYEE INDEX (RDB$PRIMARY7))", indexed.plan);
+    assert_eq!(
+        vec![vec![
+            Some(Value::SmallInt(24)),
+            Some(Value::String("Fisher".into()))
+        ]],
+        indexed.rows
+    );
+
+    let natural = db.query("select emp_no, last_name from employee where emp_no in (24)")?;
+    assert_eq!("PLAN (EMPLOYEE NATURAL)", natural.plan);
+    assert_eq!(indexed.rows, natural.rows);
+
+    // The other conditions are still checked
+    let result = db.query("select emp_no from employee where '24' = emp_no and last_name = 'x'")?;
+    assert_eq!("PLAN (EMPLOYEE INDEX (RDB$PRIMARY7))", result.plan);
+    assert!(result.rows.is_empty());
+
+    let result = db.query("select emp_no from employee where emp_no = 24.5")?;
+    assert!(result.rows.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn joining_tables() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let joined = db.query(
+        "SELECT e.emp_no, d.department, d.dept_no FROM employee e \
+         INNER JOIN department d ON d.dept_no = e.dept_no \
+         WHERE d.budget > 400000 ORDER BY e.emp_no",
+    )?;
+    assert_eq!("PLAN JOIN (E NATURAL, D INDEX (RDB$PRIMARY5))", joined.plan);
+
+    // Same rows of the nested loops over both tables
+    let crossed = db.query(
+        "SELECT e.emp_no, d.department, d.dept_no FROM employee e, department d \
+         WHERE e.dept_no = d.dept_no AND d.budget > 400000 ORDER BY 1",
+    )?;
+    assert_eq!(joined.rows, crossed.rows);
+    assert!(!joined.rows.is_empty() && joined.rows.len() < 42);
+
+    let all = db.query(
+        "select count(*) from employee e join department d on e.dept_no = d.dept_no \
+         join job j on j.job_code = e.job_code and j.job_grade = e.job_grade \
+         and j.job_country = e.job_country",
+    )?;
+    assert_eq!(vec![vec![Some(Value::BigInt(42))]], all.rows);
+
+    assert!(db
+        .query("select dept_no from employee e join department d on e.dept_no = d.dept_no")
+        .is_err());
+    assert!(db
+        .query("select * from employee e left join department d on e.dept_no = d.dept_no")
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn grouping_rows() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+    let (columns, rows) = employee_rows(&db)?;
+    let (salary, country) = (
+        position(&columns, "SALARY"),
+        position(&columns, "JOB_COUNTRY"),
+    );
+
+    let result = db.query(
+        "select job_country, count(*) as total, sum(salary), avg(salary), max(salary) \
+         from employee group by job_country order by total desc, job_country",
+    )?;
+    assert_eq!(
+        vec!["JOB_COUNTRY", "TOTAL", "SUM", "AVG", "MAX"],
+        result.columns
+    );
+
+    let usa: Vec<i64> = rows
+        .iter()
+        .filter(|r| r.values[country] == Some(Value::String("USA".into())))
+        .map(|r| match r.values[salary] {
+            Some(Value::Decimal(v, -2)) => v,
+            _ => panic!("Unexpected salary"),
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            Some(Value::String("USA".into())),
+            Some(Value::BigInt(usa.len() as i64)),
+            Some(Value::Decimal(usa.iter().sum(), -2)),
+            Some(Value::Decimal(
+                usa.iter().sum::<i64>() / usa.len() as i64,
+                -2
+            )),
+            Some(Value::Decimal(*usa.iter().max().unwrap(), -2)),
+        ],
+        result.rows[0]
+    );
+
+    let total: i64 = result
+        .rows
+        .iter()
+        .map(|r| match r[1] {
+            Some(Value::BigInt(n)) => n,
+            _ => 0,
+        })
+        .sum();
+    assert_eq!(42, total);
+
+    let result = db.query(
+        "select job_country from employee group by job_country \
+         having count(distinct dept_no) > 1 and min(salary) > 0 order by 1",
+    )?;
+    assert!(result.rows.iter().all(|r| r[0].is_some()));
+    assert!(
+        result.rows.len()
+            < db.query("select distinct job_country from employee")?
+                .rows
+                .len()
+    );
+
+    let result =
+        db.query("select count(*), count(phone_ext), sum(salary) from employee where 1 = 0")?;
+    assert_eq!(
+        vec![vec![Some(Value::BigInt(0)), Some(Value::BigInt(0)), None]],
+        result.rows
+    );
+
+    assert!(db
+        .query("select last_name, count(*) from employee")
+        .is_err());
+    assert!(db
+        .query("select job_country from employee group by dept_no")
+        .is_err());
+    assert!(db
+        .query("select emp_no from employee where count(*) > 1")
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn rejecting_queries() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    for sql in [
+        "delete from employee",
+        "select * from employee; select * from department",
+        "select * from not_a_table",
+        "select not_a_column from employee",
+        "select emp_no + 1 from employee",
+        "select upper(last_name) from employee",
+        "select * from employee order by 20",
+        "select * from employee limit -1",
+        "select * from (select * from employee) e",
+        "selec * from employee",
+    ] {
+        assert!(db.query(sql).is_err(), "{}", sql);
+    }
+
+    Ok(())
+}
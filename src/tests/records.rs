@@ -2,7 +2,7 @@ use crate::*;
 
 #[test]
 fn reading_some_row_raw() -> Result<(), Error> {
-    let mut db = Database::open_file("dbs/employee.fdb")?;
+    let db = Database::open_file("dbs/employee.fdb")?;
 
     let tables = db.tables()?;
 
@@ -63,7 +63,7 @@ fn reading_some_row_raw() -> Result<(), Error> {
 
 #[test]
 fn reading_some_row_typed() -> Result<(), Error> {
-    let mut db = Database::open_file("dbs/employee.fdb")?;
+    let db = Database::open_file("dbs/employee.fdb")?;
 
     let tables = db.tables()?;
 
@@ -90,7 +90,7 @@ fn reading_some_row_typed() -> Result<(), Error> {
 
 #[test]
 fn reading_some_row_typed_costumer() -> Result<(), Error> {
-    let mut db = Database::open_file("dbs/employee.fdb")?;
+    let db = Database::open_file("dbs/employee.fdb")?;
 
     let tables = db.tables()?;
 
@@ -111,7 +111,7 @@ fn reading_some_row_typed_costumer() -> Result<(), Error> {
 
 #[test]
 fn reading_some_row_typed_sales() -> Result<(), Error> {
-    let mut db = Database::open_file("dbs/employee.fdb")?;
+    let db = Database::open_file("dbs/employee.fdb")?;
 
     let tables = db.tables()?;
 
@@ -129,13 +129,13 @@ fn reading_some_row_typed_sales() -> Result<(), Error> {
     assert_eq!(Some(Value::Int(1004)), row1.values[1]);
     assert_eq!(Some(Value::SmallInt(11)), row1.values[2]);
     assert_eq!(Some(Value::String("shipped".to_string())), row1.values[3]);
-    assert_eq!(None, row1.values[4]); // timestamp not supported yet
-    assert_eq!(None, row1.values[5]); // timestamp not supported yet
-    assert_eq!(None, row1.values[6]); // timestamp not supported yet
+    assert_eq!(Some(Value::Timestamp(48319, 0)), row1.values[4]);
+    assert_eq!(Some(Value::Timestamp(48320, 0)), row1.values[5]);
+    assert_eq!(None, row1.values[6]);
     assert_eq!(Some(Value::String("y".to_string())), row1.values[7]);
-    //assert_eq!(Some(Value::Int(10)), row1.values[8]);
-    assert_eq!(None, row1.values[9]); // decimal not supported yet
-    assert_eq!(None, row1.values[10]); // float not supported yet
+    assert_eq!(Some(Value::Int(10)), row1.values[8]);
+    assert_eq!(Some(Value::Decimal(500000, -2)), row1.values[9]);
+    assert_eq!(Some(Value::Float(0.1)), row1.values[10]);
     assert_eq!(Some(Value::String("hardware".to_string())), row1.values[11]);
 
     Ok(())
@@ -143,7 +143,7 @@ fn reading_some_row_typed_sales() -> Result<(), Error> {
 
 #[test]
 fn reading_some_row_typed_salary_history() -> Result<(), Error> {
-    let mut db = Database::open_file("dbs/employee.fdb")?;
+    let db = Database::open_file("dbs/employee.fdb")?;
 
     let tables = db.tables()?;
 
@@ -158,18 +158,18 @@ fn reading_some_row_typed_salary_history() -> Result<(), Error> {
 
     let row1 = row1.unwrap();
     assert_eq!(Some(Value::SmallInt(28)), row1.values[0]);
-    assert_eq!(None, row1.values[1]); // timestamp not supported yet
+    assert_eq!(Some(Value::Timestamp(48971, 0)), row1.values[1]);
     assert_eq!(Some(Value::String("admin2".to_string())), row1.values[2]);
-    assert_eq!(None, row1.values[3]); // numeric not supported yet
-    assert_eq!(None, row1.values[4]); // numeric not supported yet
-    assert_eq!(None, row1.values[5]); // numeric not supported
+    assert_eq!(Some(Value::Decimal(2000000, -2)), row1.values[3]);
+    assert_eq!(Some(Value::Double(10.0)), row1.values[4]);
+    assert_eq!(None, row1.values[5]); // computed not supported
 
     Ok(())
 }
 
 #[test]
 fn reading_some_row_typed_employee() -> Result<(), Error> {
-    let mut db = Database::open_file("dbs/employee.fdb")?;
+    let db = Database::open_file("dbs/employee.fdb")?;
 
     let tables = db.tables()?;
 
@@ -187,12 +187,12 @@ fn reading_some_row_typed_employee() -> Result<(), Error> {
     assert_eq!(Some(Value::String("Robert".to_string())), row1.values[1]);
     assert_eq!(Some(Value::String("Nelson".to_string())), row1.values[2]);
     assert_eq!(Some(Value::String("250".to_string())), row1.values[3]);
-    assert_eq!(None, row1.values[4]); // timestamp not supported yet
+    assert_eq!(Some(Value::Timestamp(47523, 0)), row1.values[4]);
     assert_eq!(Some(Value::String("600".to_string())), row1.values[5]);
     assert_eq!(Some(Value::String("VP".to_string())), row1.values[6]);
     assert_eq!(Some(Value::SmallInt(2)), row1.values[7]);
     assert_eq!(Some(Value::String("USA".to_string())), row1.values[8]);
-    assert_eq!(None, row1.values[9]); // numeric not supported yet
+    assert_eq!(Some(Value::Decimal(10590000, -2)), row1.values[9]);
     assert_eq!(None, row1.values[10]); // computed not supported
 
     let row2 = ptable.read()?;
@@ -203,13 +203,150 @@ fn reading_some_row_typed_employee() -> Result<(), Error> {
     assert_eq!(Some(Value::String("Bruce".to_string())), row2.values[1]);
     assert_eq!(Some(Value::String("Young".to_string())), row2.values[2]);
     assert_eq!(Some(Value::String("233".to_string())), row2.values[3]);
-    assert_eq!(None, row2.values[4]); // timestamp not supported yet
+    assert_eq!(Some(Value::Timestamp(47523, 0)), row2.values[4]);
     assert_eq!(Some(Value::String("621".to_string())), row2.values[5]);
     assert_eq!(Some(Value::String("Eng".to_string())), row2.values[6]);
     assert_eq!(Some(Value::SmallInt(2)), row2.values[7]);
     assert_eq!(Some(Value::String("USA".to_string())), row2.values[8]);
-    assert_eq!(None, row2.values[9]); // numeric not supported yet
+    assert_eq!(Some(Value::Decimal(9750000, -2)), row2.values[9]);
     assert_eq!(None, row2.values[10]); // computed not supported
 
     Ok(())
 }
+
+#[test]
+fn decoding_text_with_charsets() -> Result<(), Error> {
+    let column =
+        |id: usize, tp: ColumnType, size: usize, charset: Charset, char_length: usize| Column {
+            name: format!("COL{}", id),
+            id,
+            position: id,
+            size,
+            source: format!("RDB${}", id),
+            scale: 0,
+            tp,
+            sub_type: 0,
+            charset,
+            char_length,
+            dimensions: vec![],
+            not_null: false,
+            computed: false,
+        };
+    let columns = vec![
+        // CHAR(4) CHARACTER SET UTF8
+        column(0, ColumnType::Char, 16, Charset::Utf8, 4),
+        // VARCHAR(4) CHARACTER SET OCTETS
+        column(1, ColumnType::Varchar, 4, Charset::Octets, 4),
+        // VARCHAR(10) CHARACTER SET WIN1252
+        column(2, ColumnType::Varchar, 10, Charset::Win1252, 10),
+    ];
+
+    let mut rec_data = vec![0, 0, 0, 0];
+    rec_data.extend("ação".as_bytes());
+    rec_data.extend([b' '; 10]);
+    rec_data.extend([2, 0, 0xca, 0xfe, 0, 0]);
+    rec_data.extend([4, 0, 0x41, 0xe7, 0x61, 0xed, 0, 0, 0, 0, 0, 0]);
+
+    let row = Row::load(&columns, &rec_data)?;
+    assert_eq!(Some(Value::String("ação".to_string())), row.values[0]);
+    assert_eq!(Some(Value::Bytes(vec![0xca, 0xfe])), row.values[1]);
+    assert_eq!(Some(Value::String("Açaí".to_string())), row.values[2]);
+
+    Ok(())
+}
+
+#[test]
+fn reading_with_none_charset_override() -> Result<(), Error> {
+    let mut db = Database::open_file("dbs/employee.fdb")?;
+    db.set_none_charset(Charset::Win1252);
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "COUNTRY");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let mut ptable = table.prepare()?;
+    assert_eq!(Charset::Win1252, ptable.columns[0].charset);
+
+    let row1 = ptable.read()?;
+    assert!(row1.is_some());
+    assert_eq!(
+        Some(Value::String("USA".to_string())),
+        row1.unwrap().values[0]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn reading_rows_by_db_key() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    let tables = db.tables()?;
+
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE");
+    assert!(table.is_some());
+    let table = table.unwrap();
+
+    let mut ptable = table.prepare()?;
+
+    let mut count = 0;
+    while let Some(row) = ptable.read()? {
+        let key = row.db_key.expect("rows read from a table have a db key");
+        assert_eq!(table.relation, key.relation);
+
+        let found = table.get_by_db_key(&key)?;
+        assert!(found.is_some());
+        assert_eq!(row.values, found.unwrap().values);
+
+        count += 1;
+    }
+    assert_eq!(42, count);
+
+    let key: DbKey = "8300000001000000".parse()?;
+    assert_eq!(0, key.record_number);
+    assert_eq!("8300000001000000", key.to_string());
+
+    let row = table.get_by_db_key(&key)?;
+    assert!(row.is_some());
+    assert_eq!(Some(Value::SmallInt(2)), row.unwrap().values[0]);
+
+    let missing = DbKey {
+        relation: table.relation,
+        record_number: 479,
+    };
+    assert!(table.get_by_db_key(&missing)?.is_none());
+
+    let other = DbKey {
+        relation: 128,
+        record_number: 0,
+    };
+    assert!(table.get_by_db_key(&other).is_err());
+
+    assert!("83000000".parse::<DbKey>().is_err());
+    assert!("8300000000000000".parse::<DbKey>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn displaying_decimals() {
+    assert_eq!("5000.00", Value::Decimal(500000, -2).to_string());
+    assert_eq!("-0.05", Value::Decimal(-5, -2).to_string());
+    assert_eq!("1200", Value::Decimal(12, 2).to_string());
+
+    assert_eq!(
+        format!("0.{}1", "0".repeat(39)),
+        Value::Decimal(1, -40).to_string()
+    );
+    assert_eq!(
+        format!("-1{}", "0".repeat(40)),
+        Value::Decimal(-1, 40).to_string()
+    );
+    assert_eq!("0", Value::Decimal(0, 40).to_string());
+    assert_eq!(
+        format!("{}{}", i64::MAX, "0".repeat(30)),
+        Value::Decimal(i64::MAX, 30).to_string()
+    );
+}
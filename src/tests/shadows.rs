@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use crate::*;
+
+const PAGE_SIZE: usize = 8192;
+
+/// Copy of the employee database flagged as its shadow, with the
+/// pages changed by `change`
+fn shadow_database<F>(path: &Path, change: F) -> Result<(), Error>
+where
+    F: Fn(&mut Vec<u8>),
+{
+    let mut bytes = std::fs::read("dbs/employee.fdb")?;
+
+    // hdr_active_shadow flag and the root file name clumplet
+    bytes[42] |= 0x01;
+    let root = b"/srv/firebird/employee.fdb";
+    let mut clumplet = vec![1, root.len() as u8];
+    clumplet.extend_from_slice(root);
+    bytes[128..128 + clumplet.len()].copy_from_slice(&clumplet);
+    bytes[66..68].copy_from_slice(&(128 + clumplet.len() as u16).to_le_bytes());
+
+    change(&mut bytes);
+    std::fs::write(path, bytes)?;
+
+    Ok(())
+}
+
+#[test]
+fn declared_shadows() -> Result<(), Error> {
+    let db = Database::open_file("dbs/employee.fdb")?;
+
+    assert!(!db.header.is_shadow());
+    assert_eq!(None, db.shadowed_file());
+    assert!(db.shadows()?.is_empty());
+    assert!(Database::open_shadow("dbs/employee.fdb").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn comparing_shadow() -> Result<(), Error> {
+    let path = std::env::temp_dir().join("rfirebird_employee.shd");
+
+    let result = (|| {
+        let db = Database::open_file("dbs/employee.fdb")?;
+
+        shadow_database(&path, |_| {})?;
+        let shadow = Database::open_shadow(path.to_str().unwrap())?;
+        assert_eq!(Some("/srv/firebird/employee.fdb"), shadow.shadowed_file());
+        assert!(db.compare_shadow(&shadow)?.is_empty());
+
+        // A changed data page and the last pages not copied yet
+        shadow_database(&path, |bytes| {
+            bytes[200 * PAGE_SIZE + 100] ^= 0xff;
+            bytes.truncate(324 * PAGE_SIZE);
+        })?;
+        let shadow = Database::open_shadow(path.to_str().unwrap())?;
+        assert_eq!(324, shadow.page_count()?);
+
+        let tp = db.page(200)?.page_type();
+        assert_eq!(
+            vec![
+                PageDifference {
+                    pageno: 200,
+                    database: Some(tp),
+                    shadow: Some(tp),
+                },
+                PageDifference {
+                    pageno: 324,
+                    database: Some(PageType::Undefined),
+                    shadow: None,
+                },
+                PageDifference {
+                    pageno: 325,
+                    database: Some(PageType::Undefined),
+                    shadow: None,
+                },
+            ],
+            db.compare_shadow(&shadow)?
+        );
+
+        Ok(())
+    })();
+
+    std::fs::remove_file(&path)?;
+
+    result
+}
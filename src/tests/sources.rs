@@ -0,0 +1,142 @@
+use std::fs::File;
+use std::io::Cursor;
+
+use crate::*;
+
+fn employee_rows(db: &mut Database) -> Result<Vec<Vec<Option<Value>>>, Error> {
+    let tables = db.tables()?;
+    let table = tables.into_iter().find(|t| t.name == "EMPLOYEE").unwrap();
+
+    let mut ptable = table.prepare()?;
+    let mut rows = vec![];
+    while let Some(row) = ptable.read()? {
+        rows.push(row.values);
+    }
+
+    Ok(rows)
+}
+
+/// Data pages read from the database
+fn data_pages(db: &mut Database) -> Result<Vec<DataPage>, Error> {
+    let mut pages = vec![];
+    for pageno in 1..db.page_count()? as u32 {
+        if let AnyPage::Data(data) = db.page(pageno)? {
+            pages.push(data);
+        }
+    }
+
+    Ok(pages)
+}
+
+#[test]
+fn reading_from_memory() -> Result<(), Error> {
+    let mut expected = Database::open_file("dbs/employee.fdb")?;
+    let expected = employee_rows(&mut expected)?;
+    assert_eq!(42, expected.len());
+
+    let bytes = std::fs::read("dbs/employee.fdb")?;
+    let mut db = Database::open_bytes(bytes)?;
+    assert_eq!(326, db.page_count()?);
+    assert!(db.files().is_empty());
+    assert_eq!(expected, employee_rows(&mut db)?);
+
+    // The data pages borrow the bytes, the others are copied
+    let pages = data_pages(&mut db)?;
+    assert!(!pages.is_empty());
+    assert!(pages.iter().all(|p| p.raw.is_shared()));
+    assert!(matches!(db.page(1)?, AnyPage::PageInventory(_)));
+
+    let mut db = Database::open_bytes(include_bytes!("../../dbs/employee.fdb"))?;
+    assert_eq!(expected, employee_rows(&mut db)?);
+
+    // Pages starting on odd addresses
+    struct Shifted(Vec<u8>);
+    impl AsRef<[u8]> for Shifted {
+        fn as_ref(&self) -> &[u8] {
+            &self.0[1..]
+        }
+    }
+    let mut bytes = vec![0u8];
+    bytes.extend(std::fs::read("dbs/employee.fdb")?);
+    let mut db = Database::open_bytes(Shifted(bytes))?;
+    assert_eq!(expected, employee_rows(&mut db)?);
+
+    Ok(())
+}
+
+#[test]
+fn rejecting_truncated_data_pages() -> Result<(), Error> {
+    let mut page = vec![0u8; 1024];
+    page[0] = 0x05;
+    page[22] = 0x10;
+    assert_eq!(16, DataPage::from_bytes(page.clone())?.records.len());
+
+    // More records than the page can hold
+    page[23] = 0x01;
+    assert!(DataPage::from_bytes(page.clone()).is_err());
+    assert!(DataPage::from_bytes(page[..20].to_vec()).is_err());
+    assert!(DataPage::from_bytes(vec![]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn reading_from_reader() -> Result<(), Error> {
+    let mut expected = Database::open_file("dbs/employee.fdb")?;
+    let expected = employee_rows(&mut expected)?;
+
+    let mut db = Database::open_reader(File::open("dbs/employee.fdb")?)?;
+    assert_eq!(326, db.page_count()?);
+    assert_eq!(expected, employee_rows(&mut db)?);
+    assert!(data_pages(&mut db)?.iter().all(|p| !p.raw.is_shared()));
+
+    // Sources implemented by the users
+    struct Encrypted(Cursor<Vec<u8>>);
+
+    impl std::io::Read for Encrypted {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let size = self.0.read(buf)?;
+            buf[..size].iter_mut().for_each(|b| *b ^= 0x5a);
+            Ok(size)
+        }
+    }
+
+    impl std::io::Seek for Encrypted {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    impl PageSource for Encrypted {}
+
+    let bytes = std::fs::read("dbs/employee.fdb")?
+        .into_iter()
+        .map(|b| b ^ 0x5a)
+        .collect();
+    let mut db = Database::open(Encrypted(Cursor::new(bytes)))?;
+    assert_eq!(326, db.page_count()?);
+    assert_eq!(expected, employee_rows(&mut db)?);
+
+    Ok(())
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn reading_memory_mapped() -> Result<(), Error> {
+    let mut expected = Database::open_file("dbs/employee.fdb")?;
+    let expected = employee_rows(&mut expected)?;
+
+    let mut db = Database::open_mmap("dbs/employee.fdb")?;
+    assert_eq!(326, db.page_count()?);
+    assert_eq!(expected, employee_rows(&mut db)?);
+
+    let mut copied = Database::open_file("dbs/employee.fdb")?;
+    let pages = data_pages(&mut db)?;
+    assert!(pages.iter().all(|p| p.raw.is_shared()));
+    for (page, copy) in pages.iter().zip(data_pages(&mut copied)?) {
+        assert_eq!(copy.raw, page.raw);
+        assert_eq!(copy.get_records()?.len(), page.get_records()?.len());
+    }
+
+    Ok(())
+}
@@ -2,7 +2,7 @@ use crate::*;
 
 #[test]
 fn list_tables() -> Result<(), Error> {
-    let mut db = Database::open_file("dbs/employee.fdb")?;
+    let db = Database::open_file("dbs/employee.fdb")?;
 
     let tables = db.tables()?;
     assert_eq!(61, tables.len());
@@ -28,7 +28,7 @@ fn list_tables() -> Result<(), Error> {
 
 #[test]
 fn columns_of_table_department() -> Result<(), Error> {
-    let mut db = Database::open_file("dbs/employee.fdb")?;
+    let db = Database::open_file("dbs/employee.fdb")?;
 
     let tables = db.tables()?;
 
@@ -42,11 +42,16 @@ fn columns_of_table_department() -> Result<(), Error> {
     assert_eq!(
         Some(&Column {
             name: "DEPT_NO".to_string(),
+            id: 0,
             position: 0,
             size: 3,
             source: "DEPTNO".to_string(),
             scale: 0,
             tp: ColumnType::Char,
+            sub_type: 0,
+            charset: Charset::None,
+            char_length: 3,
+            dimensions: vec![],
             not_null: true,
             computed: false
         }),
@@ -55,11 +60,16 @@ fn columns_of_table_department() -> Result<(), Error> {
     assert_eq!(
         Column {
             name: "BUDGET".to_string(),
+            id: 4,
             position: 4,
             size: 8,
             source: "BUDGET".to_string(),
             scale: -2,
             tp: ColumnType::Bigint,
+            sub_type: 2,
+            charset: Charset::None,
+            char_length: 0,
+            dimensions: vec![],
             not_null: false,
             computed: false
         },
@@ -68,11 +78,16 @@ fn columns_of_table_department() -> Result<(), Error> {
     assert_eq!(
         Some(&Column {
             name: "PHONE_NO".to_string(),
+            id: 6,
             position: 6,
             size: 20,
             source: "PHONENUMBER".to_string(),
             scale: 0,
             tp: ColumnType::Varchar,
+            sub_type: 0,
+            charset: Charset::None,
+            char_length: 20,
+            dimensions: vec![],
             not_null: false,
             computed: false
         }),
@@ -84,7 +99,7 @@ fn columns_of_table_department() -> Result<(), Error> {
 
 #[test]
 fn columns_of_table_employee() -> Result<(), Error> {
-    let mut db = Database::open_file("dbs/employee.fdb")?;
+    let db = Database::open_file("dbs/employee.fdb")?;
 
     let tables = db.tables()?;
 
@@ -98,11 +113,16 @@ fn columns_of_table_employee() -> Result<(), Error> {
     assert_eq!(
         Some(&Column {
             name: "EMP_NO".to_string(),
+            id: 0,
             position: 0,
             size: 2,
             source: "EMPNO".to_string(),
             scale: 0,
             tp: ColumnType::Smallint,
+            sub_type: 0,
+            charset: Charset::None,
+            char_length: 0,
+            dimensions: vec![],
             not_null: true,
             computed: false
         }),
@@ -111,11 +131,16 @@ fn columns_of_table_employee() -> Result<(), Error> {
     assert_eq!(
         Column {
             name: "FIRST_NAME".to_string(),
+            id: 1,
             position: 1,
             size: 15,
             source: "FIRSTNAME".to_string(),
             scale: 0,
             tp: ColumnType::Varchar,
+            sub_type: 0,
+            charset: Charset::None,
+            char_length: 15,
+            dimensions: vec![],
             not_null: true,
             computed: false
         },
@@ -124,11 +149,16 @@ fn columns_of_table_employee() -> Result<(), Error> {
     assert_eq!(
         Some(&Column {
             name: "FULL_NAME".to_string(),
+            id: 10,
             position: 10,
             size: 37,
             source: "RDB$9".to_string(),
             scale: 0,
             tp: ColumnType::Varchar,
+            sub_type: 0,
+            charset: Charset::None,
+            char_length: 37,
+            dimensions: vec![],
             not_null: false,
             computed: true
         }),
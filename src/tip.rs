@@ -0,0 +1,73 @@
+//! Firebird transaction inventory page representation
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::page::*;
+use crate::Error;
+
+/// Size of the transaction inventory page header, before the states
+const TIP_SIZE: usize = 20;
+
+/// State of a transaction, with two bits per transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionState {
+    Active,
+    Limbo,
+    Dead,
+    Committed,
+}
+
+/// Transaction Inventory Page
+///
+/// Holds the state of each transaction, the TIPs are chained by
+/// the `next` page number.
+#[derive(Debug, Clone)]
+pub struct TransactionInventoryPage {
+    pub pag: Page,
+    /// Next TIP, 0 on the last one
+    pub next: u32,
+    /// Two bits per transaction
+    pub bits: Vec<u8>,
+}
+
+impl TransactionInventoryPage {
+    /// Parse the TransactionInventoryPage from bytes
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<TransactionInventoryPage, Error> {
+        let pag = Page::from_bytes(&bytes)?;
+        if pag.ptype != 0x03 {
+            return Err(Error::InvalidPage {
+                tpe: pag.ptype,
+                expected: 0x03,
+                desc: "transaction inventory".to_string(),
+            });
+        }
+        if bytes.len() < TIP_SIZE {
+            return Err(Error::Overflow {
+                limit: bytes.len(),
+                value: TIP_SIZE,
+                msg: "transaction inventory page header".to_string(),
+            });
+        }
+
+        Ok(TransactionInventoryPage {
+            pag,
+            next: LittleEndian::read_u32(&bytes[16..20]),
+            bits: bytes[TIP_SIZE..].to_vec(),
+        })
+    }
+
+    /// Amount of transactions tracked by each TIP
+    pub fn transactions_per_tip(page_size: u16) -> usize {
+        (page_size as usize - TIP_SIZE) * 4
+    }
+
+    /// State of the transaction at this position of the TIP
+    pub fn state(&self, slot: usize) -> TransactionState {
+        match (self.bits[slot / 4] >> ((slot % 4) * 2)) & 0x03 {
+            0 => TransactionState::Active,
+            1 => TransactionState::Limbo,
+            2 => TransactionState::Dead,
+            _ => TransactionState::Committed,
+        }
+    }
+}